@@ -0,0 +1,56 @@
+//! Measures `get` throughput with several threads reading the same client at once.
+//!
+//! Reads only take a shared lock on the state, so adding reader threads should scale the
+//! total number of lookups instead of queueing them behind each other. Compare the timings
+//! across thread counts with `cargo bench --bench concurrent_reads`.
+
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use quick_kv::prelude::*;
+use tempfile::tempdir;
+
+const KEYS: u64 = 1_000;
+const READS_PER_THREAD: u64 = 10_000;
+
+fn concurrent_reads(c: &mut Criterion)
+{
+    let tmp_dir = tempdir().expect("Failed to create tempdir");
+    let tmp_file = tmp_dir.path().join("bench.qkv").to_str().unwrap().to_string();
+
+    let mut client = QuickClient::<u64>::new(ClientConfig::new(tmp_file, false.into(), None));
+
+    for i in 0..KEYS {
+        client.set(&format!("key_{}", i), i).unwrap();
+    }
+
+    let mut group = c.benchmark_group("concurrent_reads");
+
+    for threads in [1, 2, 4, 8] {
+        group.throughput(Throughput::Elements(threads * READS_PER_THREAD));
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &threads| {
+            b.iter(|| {
+                let readers: Vec<_> = (0..threads)
+                    .map(|_| {
+                        let mut client = client.clone();
+
+                        thread::spawn(move || {
+                            for i in 0..READS_PER_THREAD {
+                                client.get(&format!("key_{}", i % KEYS)).unwrap();
+                            }
+                        })
+                    })
+                    .collect();
+
+                for reader in readers {
+                    reader.join().unwrap();
+                }
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, concurrent_reads);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+//! Compares seeding a fresh database with 10,000 keys through `set` in a loop against
+//! `QuickClient::from_iter`.
+//!
+//! Each `set` writes and syncs the file on its own, `from_iter` writes every entry at once.
+//! Run with `cargo bench --bench from_iter`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use quick_kv::prelude::*;
+use tempfile::tempdir;
+
+const KEYS: u64 = 10_000;
+
+fn config() -> (tempfile::TempDir, ClientConfig)
+{
+    let tmp_dir = tempdir().expect("Failed to create tempdir");
+    let tmp_file = tmp_dir.path().join("bench.qkv").to_str().unwrap().to_string();
+
+    (tmp_dir, ClientConfig::new(tmp_file, false.into(), None))
+}
+
+fn from_iter(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("from_iter");
+    group.sample_size(10);
+
+    group.bench_function("set_loop", |b| {
+        b.iter_batched(
+            config,
+            |(_tmp_dir, config)| {
+                let mut client = QuickClient::<u64>::new(config);
+
+                for i in 0..KEYS {
+                    client.set(&format!("key_{}", i), i).unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        );
+    });
+
+    group.bench_function("from_iter", |b| {
+        b.iter_batched(
+            config,
+            |(_tmp_dir, config)| {
+                let entries = (0..KEYS).map(|i| (format!("key_{}", i), i));
+
+                QuickClient::<u64>::from_iter(config, entries).unwrap();
+            },
+            BatchSize::PerIteration,
+        );
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, from_iter);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+//! Compares looking up 1,000,000 string keys in a map hashed with FxHash, the default, and
+//! with aHash, which the `ahash` feature switches the database to.
+//!
+//! Run with `cargo bench --bench hasher --features ahash`.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, BuildHasherDefault};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const KEYS: u64 = 1_000_000;
+
+fn lookup_all<S: BuildHasher + Default>(keys: &[String]) -> impl FnMut() + '_
+{
+    let mut map = HashMap::with_capacity_and_hasher(keys.len(), S::default());
+
+    for (i, key) in keys.iter().enumerate() {
+        map.insert(key.clone(), i);
+    }
+
+    move || {
+        for key in keys {
+            black_box(map.get(key));
+        }
+    }
+}
+
+fn hasher(c: &mut Criterion)
+{
+    let keys = (0..KEYS).map(|i| format!("user:{}:session", i)).collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("hasher");
+    group.sample_size(10);
+
+    let mut fx = lookup_all::<BuildHasherDefault<rustc_hash::FxHasher>>(&keys);
+    group.bench_function("fxhash", |b| b.iter(&mut fx));
+
+    let mut ahash = lookup_all::<ahash::RandomState>(&keys);
+    group.bench_function("ahash", |b| b.iter(&mut ahash));
+
+    group.finish();
+}
+
+criterion_group!(benches, hasher);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+//! Compares inserting 100,000 keys into an in-memory database with and without
+//! `initial_capacity`.
+//!
+//! Without it the map grows and rehashes every key several times along the way.
+//! Run with `cargo bench --bench initial_capacity`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use quick_kv::prelude::*;
+
+const KEYS: u64 = 100_000;
+
+fn client(initial_capacity: Option<usize>) -> QuickMemoryClient<u64>
+{
+    QuickMemoryClient::<u64>::new(ClientConfig {
+        initial_capacity,
+        ..ClientConfig::new("bench.qkv".to_string(), false.into(), None)
+    })
+}
+
+fn initial_capacity(c: &mut Criterion)
+{
+    let keys = (0..KEYS).map(|i| format!("key_{}", i)).collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("initial_capacity");
+    group.sample_size(10);
+
+    for (name, capacity) in [("default", None), ("reserved", Some(KEYS as usize))] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || client(capacity),
+                |mut client| {
+                    for (i, key) in keys.iter().enumerate() {
+                        client.set(key, i as u64).unwrap();
+                    }
+                },
+                BatchSize::PerIteration,
+            );
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, initial_capacity);
+criterion_main!(benches);
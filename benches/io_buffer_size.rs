@@ -0,0 +1,40 @@
+//! Compares loading a database of 200,000 keys with 8 KiB and 256 KiB file buffers.
+//!
+//! The file is written once, every iteration opens it again and reads every record.
+//! Run with `cargo bench --bench io_buffer_size`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use quick_kv::prelude::*;
+use tempfile::tempdir;
+
+const KEYS: u64 = 200_000;
+
+fn io_buffer_size(c: &mut Criterion)
+{
+    let tmp_dir = tempdir().expect("Failed to create tempdir");
+    let tmp_file = tmp_dir.path().join("bench.qkv").to_str().unwrap().to_string();
+
+    let config = ClientConfig::new(tmp_file, false.into(), None);
+
+    let entries = (0..KEYS).map(|i| (format!("key_{}", i), format!("value_{}", i)));
+    drop(QuickClient::<String>::from_iter(config.clone(), entries).unwrap());
+
+    let mut group = c.benchmark_group("io_buffer_size");
+    group.sample_size(10);
+
+    for size in [8 * 1024, 256 * 1024] {
+        let config = ClientConfig {
+            io_buffer_size: Some(size),
+            ..config.clone()
+        };
+
+        group.bench_with_input(BenchmarkId::new("load", size), &config, |b, config| {
+            b.iter(|| QuickClient::<String>::new(config.clone()));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, io_buffer_size);
+criterion_main!(benches);
@@ -0,0 +1,51 @@
+//! Compares inserting 10,000 keys with `set` in a loop against a single `set_many`.
+//!
+//! Each `set` writes and syncs the file on its own, `set_many` writes every entry at once.
+//! Run with `cargo bench --bench set_many`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use quick_kv::prelude::*;
+use tempfile::tempdir;
+
+const KEYS: u64 = 10_000;
+
+fn client() -> (tempfile::TempDir, QuickClient<u64>)
+{
+    let tmp_dir = tempdir().expect("Failed to create tempdir");
+    let tmp_file = tmp_dir.path().join("bench.qkv").to_str().unwrap().to_string();
+
+    let client = QuickClient::<u64>::new(ClientConfig::new(tmp_file, false.into(), None));
+
+    (tmp_dir, client)
+}
+
+fn set_many(c: &mut Criterion)
+{
+    let keys = (0..KEYS).map(|i| format!("key_{}", i)).collect::<Vec<_>>();
+    let key_refs = keys.iter().map(String::as_str).collect::<Vec<_>>();
+    let values = (0..KEYS).collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("set_many");
+    group.sample_size(10);
+
+    group.bench_function("set_loop", |b| {
+        let (_tmp_dir, mut client) = client();
+
+        b.iter(|| {
+            for (key, value) in key_refs.iter().zip(values.iter()) {
+                client.set(key, *value).unwrap();
+            }
+        });
+    });
+
+    group.bench_function("set_many", |b| {
+        let (_tmp_dir, mut client) = client();
+
+        b.iter(|| client.set_many(&key_refs, &values).unwrap());
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, set_many);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+//! Measures the latency of a single `update` as the database grows.
+//!
+//! Updates append one record instead of rewriting the whole file, so the time per update
+//! should stay flat across database sizes rather than grow with them. Compaction still
+//! rewrites the file now and then, which shows up as occasional slow samples.
+//! Run with `cargo bench --bench update_latency`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use quick_kv::prelude::*;
+use tempfile::tempdir;
+
+fn update_latency(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("update_latency");
+
+    for size in [100, 1_000, 10_000] {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("bench.qkv").to_str().unwrap().to_string();
+
+        let mut client = QuickClient::<u64>::new(ClientConfig::new(tmp_file, false.into(), None));
+
+        for i in 0..size {
+            client.set(&format!("key_{}", i), i).unwrap();
+        }
+
+        let mut i = 0;
+
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            b.iter(|| {
+                client.update(&format!("key_{}", i % size), i, None).unwrap();
+                i += 1;
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, update_latency);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+//! Measures how long 1,000 `set` calls take with `sync_on_write` on and off.
+//!
+//! With syncing on every write waits for the disk, with it off the writes only reach the
+//! operating system and a single `flush` at the end makes them durable.
+//! Run with `cargo bench --bench write_throughput`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use quick_kv::prelude::*;
+use tempfile::tempdir;
+
+const WRITES: u64 = 1_000;
+
+fn write_throughput(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("write_throughput");
+    group.throughput(Throughput::Elements(WRITES));
+    group.sample_size(10);
+
+    for sync_on_write in [true, false] {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("bench.qkv").to_str().unwrap().to_string();
+
+        let mut client = QuickClient::<u64>::new(ClientConfig {
+            sync_on_write: Some(sync_on_write),
+            ..ClientConfig::new(tmp_file, false.into(), None)
+        });
+
+        let name = if sync_on_write { "sync" } else { "no_sync" };
+
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                for i in 0..WRITES {
+                    client.set(&format!("key_{}", i), i).unwrap();
+                }
+
+                client.flush().unwrap();
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, write_throughput);
+criterion_main!(benches);
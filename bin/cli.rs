@@ -1,6 +1,9 @@
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
-use clap::{arg, Command};
+use clap::{arg, ArgMatches, Command};
 use log::LevelFilter;
 use quick_kv::prelude::*;
 
@@ -10,6 +13,26 @@ Welcome to the Quick-KV REPL!
 Run 'qkv help' to see the list of commands.
 "#;
 
+/// The file the CLI reads its settings from, looked up in the current directory and then `$HOME`.
+const CONFIG_FILE: &str = "qkv.toml";
+
+/// The contents of `qkv.toml`, every setting is optional.
+///
+/// ```toml
+/// path = "cli.qkv"
+/// log = true
+/// log_level = "debug"
+/// default_ttl = 60 # seconds
+/// ```
+#[derive(Debug, Default, Deserialize)]
+struct CliConfig
+{
+    path: Option<String>,
+    log: Option<bool>,
+    log_level: Option<String>,
+    default_ttl: Option<u64>,
+}
+
 fn cli() -> Command
 {
     Command::new("Quick-KV REPL")
@@ -31,6 +54,7 @@ fn cli() -> Command
                 .about("Sets new data into the database")
                 .arg(arg!(<KEY> "Key to set the value of"))
                 .arg(arg!(<VALUE> "Value to set"))
+                .arg(arg!(--ttl <SECONDS> "Seconds until the key expires").value_parser(parse_ttl))
                 .arg_required_else_help(true),
         )
         .subcommand(
@@ -47,19 +71,89 @@ fn cli() -> Command
                 .arg_required_else_help(true),
         )
         .subcommand(Command::new("keys").about("Lists all keys in the database"))
+        .subcommand(
+            Command::new("dump")
+                .about("Prints every key and value in the database")
+                .arg(arg!(--json "Print one JSON object per key")),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Backs up the database to a file")
+                .arg(arg!(<FILE> "File to write the backup to"))
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Restores a backup written by export into the database")
+                .arg(arg!(<FILE> "File to read the backup from"))
+                .arg_required_else_help(true),
+        )
         .subcommand(Command::new("exit").about("Exits the repl"))
     // .subcommand(Command::new("").about(""))
 }
 
-// todo - fix bug where if you type incorrect commands then the repl crashes.
-// todo - make a config file where users can define the type of data the database will store.
+/// Parses the `--ttl` argument of `set`, a whole number of seconds.
+fn parse_ttl(seconds: &str) -> Result<Duration, String>
+{
+    seconds
+        .parse::<u64>()
+        .map(Duration::from_secs)
+        .map_err(|_| format!("\"{}\" is not a number of seconds", seconds))
+}
+
+/// Looks for `qkv.toml` in the current directory, then in `$HOME`.
+fn find_config() -> Option<PathBuf>
+{
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+
+    [Some(PathBuf::from(".")), home]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Builds the client configuration from `qkv.toml`, falling back to the defaults when there is none.
+fn load_config() -> anyhow::Result<ClientConfig>
+{
+    match find_config() {
+        Some(path) => load_config_from(&path),
+        None => client_config(CliConfig::default()),
+    }
+}
+
+fn load_config_from(path: &Path) -> anyhow::Result<ClientConfig>
+{
+    client_config(toml::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+fn client_config(config: CliConfig) -> anyhow::Result<ClientConfig>
+{
+    let log_level = match config.log_level {
+        Some(level) => LevelFilter::from_str(&level).map_err(|_| anyhow::anyhow!("Invalid log_level \"{}\"", level))?,
+        None => LevelFilter::Debug,
+    };
+
+    Ok(ClientConfig {
+        default_ttl: config.default_ttl.map(Duration::from_secs),
+        ..ClientConfig::new(
+            config.path.unwrap_or_else(|| "cli.qkv".to_string()),
+            config.log.unwrap_or(true).into(),
+            log_level.into(),
+        )
+    })
+}
+
+/// Parses a line typed into the repl, the error carries the help or usage to show the user.
+fn parse_command(input: &str) -> Result<ArgMatches, clap::Error>
+{
+    cli().try_get_matches_from(input.split_whitespace())
+}
+
+// todo - let the config file define the type of data the database will store.
 fn main() -> anyhow::Result<()>
 {
-    let client = QuickClient::<String>::new(ClientConfig::new(
-        "cli.qkv".to_string(),
-        true.into(),
-        LevelFilter::Debug.into(),
-    ));
+    let client = QuickClient::<String>::new(load_config()?);
 
     println!("{}", START_MESSAGE);
 
@@ -72,6 +166,12 @@ fn main() -> anyhow::Result<()>
 
         let input = input.trim();
 
+        // Accept a bare `exit` too, it's what most people type to leave a repl.
+        if input == "exit" {
+            println!("Exiting repl...");
+            break;
+        }
+
         if !input.starts_with("qkv") {
             println!("Input must start with 'qkv'. Type 'qkv help' for more information.");
             continue;
@@ -79,14 +179,13 @@ fn main() -> anyhow::Result<()>
 
         let mut command_recognized = false;
 
-        match input {
-            "exit" => {
-                println!("Exiting repl...");
-                break;
+        match parse_command(input) {
+            // Help and usage errors are printed instead of exiting the process like `get_matches` would.
+            Err(e) => {
+                e.print()?;
+                continue;
             }
-            _ => {
-                let matches = cli().get_matches_from(input.split_whitespace().collect::<Vec<_>>());
-
+            Ok(matches) => {
                 match matches.subcommand() {
                     Some(("version", _)) => {
                         println!("Quick-KV CLI v{}", env!("CARGO_PKG_VERSION"));
@@ -100,7 +199,8 @@ fn main() -> anyhow::Result<()>
                     Some(("set", args)) => {
                         let key = args.get_one::<String>("KEY").expect("Key not provided?");
                         let value = args.get_one::<String>("VALUE").expect("Value not provided?");
-                        set(client.clone(), key, value.to_string())?;
+                        let ttl = args.get_one::<Duration>("ttl").copied();
+                        set(client.clone(), key, value.to_string(), ttl)?;
                         command_recognized = true;
                     }
                     Some(("delete", args)) => {
@@ -118,6 +218,24 @@ fn main() -> anyhow::Result<()>
                         keys(client.clone())?;
                         command_recognized = true;
                     }
+                    Some(("dump", args)) => {
+                        dump(client.clone(), args.get_flag("json"), &mut io::stdout())?;
+                        command_recognized = true;
+                    }
+                    Some(("export", args)) => {
+                        let file = args.get_one::<String>("FILE").expect("File not provided?");
+                        export(client.clone(), Path::new(file))?;
+                        command_recognized = true;
+                    }
+                    Some(("import", args)) => {
+                        let file = args.get_one::<String>("FILE").expect("File not provided?");
+                        import(client.clone(), Path::new(file))?;
+                        command_recognized = true;
+                    }
+                    Some(("exit", _)) => {
+                        println!("Exiting repl...");
+                        break;
+                    }
                     _ => println!("Unknown command. Type 'exit' to quit."),
                 }
 
@@ -144,21 +262,37 @@ fn get(mut client: QuickClient<String>, key: &str) -> anyhow::Result<()>
     Ok(())
 }
 
-fn set(mut client: QuickClient<String>, key: &str, value: String) -> anyhow::Result<()>
+fn set(mut client: QuickClient<String>, key: &str, value: String, ttl: Option<Duration>) -> anyhow::Result<()>
 {
-    client.set(key, value.clone())?;
+    let expires_at = match ttl {
+        Some(ttl) => {
+            client.set_with_ttl(key, value.clone(), ttl)?;
+            Some(Utc::now() + chrono::Duration::from_std(ttl)?)
+        }
+        None => {
+            client.set(key, value.clone())?;
+            None
+        }
+    };
 
     std::thread::sleep(std::time::Duration::from_secs(5));
 
-    println!("set: \"{}\"", key);
+    match expires_at {
+        Some(expires_at) => println!("set: \"{}\", expires at {}", key, expires_at),
+        None => println!("set: \"{}\"", key),
+    }
+
     Ok(())
 }
 
 fn update(mut client: QuickClient<String>, key: &str, value: String) -> anyhow::Result<()>
 {
-    client.update(key, value.to_owned(), None)?;
+    if client.update(key, value.to_owned(), None)? {
+        println!("Updated \"{}\"", key);
+    } else {
+        println!("No value found for \"{}\"", key);
+    }
 
-    println!("Updated \"{}\"", key);
     Ok(())
 }
 
@@ -177,3 +311,183 @@ fn keys(mut client: QuickClient<String>) -> anyhow::Result<()>
     println!("Keys: {:?}", keys);
     Ok(())
 }
+
+/// Writes every key and value to `out` sorted by key, as `"key": "value"` lines or as one JSON
+/// object per line with `json`.
+fn dump(mut client: QuickClient<String>, json: bool, out: &mut impl Write) -> anyhow::Result<()>
+{
+    let mut entries = client.entries()?;
+    entries.sort();
+
+    for (key, value) in entries {
+        if json {
+            writeln!(out, "{}", serde_json::json!({ "key": key, "value": value }))?;
+        } else {
+            writeln!(out, "\"{}\": \"{}\"", key, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a backup of the database to `file`, returning the number of records written.
+fn export(mut client: QuickClient<String>, file: &Path) -> anyhow::Result<usize>
+{
+    check_backup_path(&client, file)?;
+
+    if let Some(dir) = file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if !dir.is_dir() {
+            anyhow::bail!("The directory {} does not exist", dir.display());
+        }
+    }
+
+    let count = client.len()?;
+    let mut writer = io::BufWriter::new(std::fs::File::create(file)?);
+
+    client.export(&mut writer)?;
+
+    println!("Exported {} records to {}", count, file.display());
+    Ok(count)
+}
+
+/// Merges a backup written by `export` into the database, returning the number of records read.
+fn import(mut client: QuickClient<String>, file: &Path) -> anyhow::Result<usize>
+{
+    check_backup_path(&client, file)?;
+
+    if !file.is_file() {
+        anyhow::bail!("{} is not a file", file.display());
+    }
+
+    let mut reader = io::BufReader::new(std::fs::File::open(file)?);
+    let count = client.import(&mut reader)?;
+
+    println!("Imported {} records from {}", count, file.display());
+    Ok(count)
+}
+
+/// Backups use their own format, so writing one over the database file would destroy it.
+fn check_backup_path(client: &QuickClient<String>, file: &Path) -> anyhow::Result<()>
+{
+    if client.path().map(Path::new) == Some(file) {
+        anyhow::bail!("{} is the database file, choose another file for the backup", file.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_cli_set_ttl()
+    {
+        let matches = cli()
+            .try_get_matches_from(["qkv", "set", "key", "value", "--ttl", "30"])
+            .unwrap();
+        let (_, args) = matches.subcommand().unwrap();
+
+        assert_eq!(args.get_one::<Duration>("ttl"), Some(&Duration::from_secs(30)));
+
+        let matches = cli().try_get_matches_from(["qkv", "set", "key", "value"]).unwrap();
+        let (_, args) = matches.subcommand().unwrap();
+
+        assert_eq!(args.get_one::<Duration>("ttl"), None);
+
+        assert!(cli()
+            .try_get_matches_from(["qkv", "set", "key", "value", "--ttl", "soon"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_cli_load_config()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let config_path = tmp_dir.path().join(CONFIG_FILE);
+
+        std::fs::write(
+            &config_path,
+            "path = \"custom.qkv\"\nlog = false\nlog_level = \"warn\"\ndefault_ttl = 60\n",
+        )
+        .unwrap();
+
+        let config = load_config_from(&config_path).unwrap();
+
+        assert_eq!(config.path, Some("custom.qkv".to_string()));
+        assert_eq!(config.log, Some(false));
+        assert_eq!(config.log_level, Some(LevelFilter::Warn));
+        assert_eq!(config.default_ttl, Some(Duration::from_secs(60)));
+
+        std::fs::write(&config_path, "log_level = \"loud\"\n").unwrap();
+
+        assert!(load_config_from(&config_path).is_err());
+    }
+
+    #[test]
+    fn test_cli_dump()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let mut client = QuickClient::<String>::new(ClientConfig::new(tmp_file, false.into(), None));
+
+        client.set("user_2", "bob".to_string()).unwrap();
+        client.set("user_1", "jamal".to_string()).unwrap();
+
+        let mut out = Vec::new();
+        dump(client.clone(), false, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\"user_1\": \"jamal\"\n\"user_2\": \"bob\"\n"
+        );
+
+        let mut out = Vec::new();
+        dump(client, true, &mut out).unwrap();
+
+        let lines = String::from_utf8(out).unwrap();
+        let first: serde_json::Value = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+
+        assert_eq!(first, serde_json::json!({ "key": "user_1", "value": "jamal" }));
+        assert_eq!(lines.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_cli_export_import()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let source = tmp_dir.path().join("source.qkv").to_str().unwrap().to_string();
+        let target = tmp_dir.path().join("target.qkv").to_str().unwrap().to_string();
+        let backup = tmp_dir.path().join("backup.bin");
+
+        let mut client = QuickClient::<String>::new(ClientConfig::new(source.clone(), false.into(), None));
+
+        client.set("user_1", "jamal".to_string()).unwrap();
+        client.set("user_2", "bob".to_string()).unwrap();
+
+        assert_eq!(export(client.clone(), &backup).unwrap(), 2);
+        assert!(export(client.clone(), Path::new(&source)).is_err());
+        assert!(export(client.clone(), &tmp_dir.path().join("missing").join("backup.bin")).is_err());
+
+        let mut restored = QuickClient::<String>::new(ClientConfig::new(target, false.into(), None));
+
+        assert_eq!(import(restored.clone(), &backup).unwrap(), 2);
+        assert!(import(restored.clone(), &tmp_dir.path().join("missing.bin")).is_err());
+
+        assert_eq!(restored.snapshot().unwrap(), client.snapshot().unwrap());
+    }
+
+    #[test]
+    fn test_cli_parse_command()
+    {
+        assert!(parse_command("qkv get key").is_ok());
+        assert!(parse_command("qkv exit").is_ok());
+        assert!(parse_command("qkv frobnicate").is_err());
+        assert!(parse_command("qkv get").is_err());
+        assert!(parse_command("qkv help").is_err());
+    }
+}
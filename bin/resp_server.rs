@@ -0,0 +1,23 @@
+use std::sync::{Arc, Mutex};
+
+use log::LevelFilter;
+use quick_kv::prelude::*;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:6379";
+
+// Usage: resp-server [ADDR] [DB_PATH]
+fn main() -> anyhow::Result<()>
+{
+    let mut args = std::env::args().skip(1);
+
+    let addr = args.next().unwrap_or_else(|| DEFAULT_ADDR.to_string());
+    let path = args.next().unwrap_or_else(|| "resp.qkv".to_string());
+
+    let client = QuickClient::<String>::new(ClientConfig::new(path, true.into(), LevelFilter::Info.into()));
+
+    let server = RespServer::bind(addr.as_str(), Arc::new(Mutex::new(client)))?;
+
+    println!("Quick-KV RESP server listening on {}", server.local_addr()?);
+
+    server.run()
+}
@@ -0,0 +1,115 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::clients::normal::QuickClient;
+use crate::clients::{BaseClient, ClientConfig};
+use crate::error::QuickKvError;
+
+/// An async front for `QuickClient`, for use inside a tokio runtime.
+///
+/// Every call runs the blocking client on tokio's blocking thread pool, so disk writes never
+/// stall the reactor. Clones share the same database.
+#[derive(Debug, Clone)]
+pub struct AsyncQuickClient<T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
+{
+    inner: QuickClient<T>,
+}
+
+impl<T> AsyncQuickClient<T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
+{
+    /// Creates a new instance of the client, see `BaseClient::new`.
+    ///
+    /// Loading the database file happens right away, so prefer calling this before the
+    /// runtime starts handling requests.
+    pub fn new(config: ClientConfig) -> Self
+    {
+        Self {
+            inner: QuickClient::new(config),
+        }
+    }
+
+    /// Get the value associated with a key, see `BaseClient::get`.
+    pub async fn get(&self, key: &str) -> Result<Option<T>, QuickKvError>
+    {
+        let key = key.to_string();
+
+        self.run(move |mut client| client.get(&key)).await
+    }
+
+    /// Set the value associated with a key, see `BaseClient::set`.
+    pub async fn set(&self, key: &str, value: T) -> Result<(), QuickKvError>
+    {
+        let key = key.to_string();
+
+        self.run(move |mut client| client.set(&key, value)).await
+    }
+
+    /// Update the value associated with a key, see `BaseClient::update`.
+    pub async fn update(&self, key: &str, value: T, upsert: Option<bool>) -> Result<bool, QuickKvError>
+    {
+        let key = key.to_string();
+
+        self.run(move |mut client| client.update(&key, value, upsert)).await
+    }
+
+    /// Delete the value associated with a key, see `BaseClient::delete`.
+    pub async fn delete(&self, key: &str) -> Result<(), QuickKvError>
+    {
+        let key = key.to_string();
+
+        self.run(move |mut client| client.delete(&key)).await
+    }
+
+    /// Runs `f` with a handle to the client on the blocking thread pool.
+    async fn run<R, F>(&self, f: F) -> Result<R, QuickKvError>
+    where
+        F: FnOnce(QuickClient<T>) -> Result<R, QuickKvError> + Send + 'static,
+        R: Send + 'static,
+    {
+        let client = self.inner.clone();
+
+        tokio::task::spawn_blocking(move || f(client))
+            .await
+            .map_err(|e| QuickKvError::Other(e.into()))?
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_quick_client_round_trip()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let client = AsyncQuickClient::<String>::new(config);
+
+        client.set("key", "value".to_string()).await.unwrap();
+        assert_eq!(client.get("key").await.unwrap(), Some("value".to_string()));
+
+        assert!(client.update("key", "updated".to_string(), None).await.unwrap());
+        assert_eq!(client.get("key").await.unwrap(), Some("updated".to_string()));
+
+        client.delete("key").await.unwrap();
+        assert_eq!(client.get("key").await.unwrap(), None);
+    }
+}
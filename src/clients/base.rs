@@ -0,0 +1,513 @@
+/// Implements `BaseClient` for a client holding its `Database<T>` in `db`.
+///
+/// The clients only differ in how they set up the database, `$config` turns the `ClientConfig`
+/// into its `DatabaseConfiguration`. Every other method forwards to the database, using the
+/// imports of the module the macro is called in.
+macro_rules! impl_base_client {
+    ($client:ident, $config:ident) => {
+        impl<T> BaseClient<T> for $client<T>
+        where
+            T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
+        {
+            fn new(config: ClientConfig) -> Self
+            {
+                let db = Database::new($config(config).unwrap()).unwrap();
+
+                Self { db }
+            }
+
+            fn get<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<Option<T>, QuickKvError>
+            {
+                Ok(self.db.get(key.as_key().into_owned())?)
+            }
+
+            fn get_required<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<T, QuickKvError>
+            {
+                self.get(key)?
+                    .ok_or_else(|| QuickKvError::KeyNotFound(key.as_key().into_owned()))
+            }
+
+            fn get_status<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<KeyStatus<T>, QuickKvError>
+            {
+                Ok(self.db.get_status(&key.as_key())?)
+            }
+
+            fn get_with_meta<K: AsKey + ?Sized>(
+                &mut self,
+                key: &K,
+            ) -> Result<Option<(T, Option<DateTime<Utc>>)>, QuickKvError>
+            {
+                Ok(self.db.get_with_meta(&key.as_key())?)
+            }
+
+            fn get_or_wait<K: AsKey + ?Sized>(&mut self, key: &K, timeout: Duration) -> Result<Option<T>, QuickKvError>
+            {
+                Ok(self.db.get_or_wait(&key.as_key(), timeout)?)
+            }
+
+            fn with_value<K: AsKey + ?Sized, R, F>(&mut self, key: &K, f: F) -> Result<Option<R>, QuickKvError>
+            where
+                F: FnOnce(&T) -> R,
+            {
+                Ok(self.db.with_value(&key.as_key(), f)?)
+            }
+
+            fn set<K: AsKey + ?Sized>(&mut self, key: &K, value: T) -> Result<(), QuickKvError>
+            {
+                Ok(self.db.set(&key.as_key(), value, None)?)
+            }
+
+            fn set_with_ttl<K: AsKey + ?Sized>(&mut self, key: &K, value: T, ttl: Duration) -> Result<(), QuickKvError>
+            {
+                Ok(self.db.set(&key.as_key(), value, Some(ttl))?)
+            }
+
+            fn set_with_expiry<K: AsKey + ?Sized>(
+                &mut self,
+                key: &K,
+                value: T,
+                at: DateTime<Utc>,
+            ) -> Result<(), QuickKvError>
+            {
+                Ok(self.db.set_with_expiry(&key.as_key(), value, Some(at))?)
+            }
+
+            fn set_and_get_previous<K: AsKey + ?Sized>(&mut self, key: &K, value: T) -> Result<Option<T>, QuickKvError>
+            {
+                Ok(self.db.set_and_get_previous(&key.as_key(), value, None)?)
+            }
+
+            fn insert<K: AsKey + ?Sized>(&mut self, key: &K, value: T) -> Result<bool, QuickKvError>
+            {
+                Ok(self.db.compare_and_swap(&key.as_key(), None, value)?)
+            }
+
+            fn set_idempotent<K: AsKey + ?Sized>(&mut self, key: &K, value: T, idem: &str) -> Result<bool, QuickKvError>
+            {
+                Ok(self.db.set_idempotent(&key.as_key(), value, idem)?)
+            }
+
+            fn get_or_insert_with<K: AsKey + ?Sized, F>(&mut self, key: &K, f: F) -> Result<T, QuickKvError>
+            where
+                F: FnOnce() -> T,
+            {
+                Ok(self.db.get_or_insert_with(&key.as_key(), f)?)
+            }
+
+            fn ttl<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<Option<Duration>, QuickKvError>
+            {
+                Ok(self.db.ttl(&key.as_key())?)
+            }
+
+            fn touch<K: AsKey + ?Sized>(&mut self, key: &K, new_ttl: Duration) -> Result<bool, QuickKvError>
+            {
+                Ok(self.db.refresh_ttl(&key.as_key(), new_ttl)?)
+            }
+
+            fn default_ttl(&self) -> Option<Duration>
+            {
+                self.db.default_ttl()
+            }
+
+            fn path(&self) -> Option<&str>
+            {
+                self.db.path()
+            }
+
+            fn is_in_memory(&self) -> bool
+            {
+                self.db.is_in_memory()
+            }
+
+            fn compare_and_swap<K: AsKey + ?Sized>(
+                &mut self,
+                key: &K,
+                expected: Option<T>,
+                new: T,
+            ) -> Result<bool, QuickKvError>
+            {
+                Ok(self.db.compare_and_swap(&key.as_key(), expected, new)?)
+            }
+
+            fn merge<K: AsKey + ?Sized, F>(&mut self, key: &K, f: F) -> Result<Option<T>, QuickKvError>
+            where
+                F: FnOnce(Option<T>) -> Option<T>,
+            {
+                Ok(self.db.merge(&key.as_key(), f)?)
+            }
+
+            fn transaction<F>(&mut self, f: F) -> Result<(), QuickKvError>
+            where
+                F: FnOnce(&mut Batch<T>) -> anyhow::Result<()>,
+            {
+                Ok(self.db.transaction(f)?)
+            }
+
+            fn pipeline(&mut self) -> Pipeline<'_, T>
+            {
+                Pipeline::new(&mut self.db)
+            }
+
+            fn update<K: AsKey + ?Sized>(&mut self, key: &K, value: T, upsert: Option<bool>) -> Result<bool, QuickKvError>
+            {
+                Ok(self.db.update(&key.as_key(), value, None, upsert)?)
+            }
+
+            fn delete<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<(), QuickKvError>
+            {
+                Ok(self.db.delete(&key.as_key())?)
+            }
+
+            fn pop<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<Option<T>, QuickKvError>
+            {
+                Ok(self.db.pop(&key.as_key())?)
+            }
+
+            fn rename<A: AsKey + ?Sized, B: AsKey + ?Sized>(&mut self, from: &A, to: &B) -> Result<bool, QuickKvError>
+            {
+                Ok(self.db.rename(&from.as_key(), &to.as_key())?)
+            }
+
+            fn on_change(&mut self, f: ChangeListener)
+            {
+                self.db.on_change(f)
+            }
+
+            fn flush(&mut self) -> Result<(), QuickKvError>
+            {
+                Ok(self.db.flush()?)
+            }
+
+            fn metadata<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<Option<EntryMeta>, QuickKvError>
+            {
+                Ok(self.db.metadata(&key.as_key())?)
+            }
+
+            fn load_report(&self) -> LoadReport
+            {
+                self.db.load_report()
+            }
+
+            fn validate_schema(&mut self) -> Result<usize, QuickKvError>
+            {
+                Ok(self.db.validate_schema()?)
+            }
+
+            fn delete_prefix<K: AsKey + ?Sized>(&mut self, prefix: &K) -> Result<usize, QuickKvError>
+            {
+                Ok(self.db.delete_prefix(&prefix.as_key())?)
+            }
+
+            fn retain<F>(&mut self, f: F) -> Result<usize, QuickKvError>
+            where
+                F: Fn(&str, &T) -> bool,
+            {
+                Ok(self.db.retain(f)?)
+            }
+
+            fn clear_expired(&mut self) -> Result<usize, QuickKvError>
+            {
+                Ok(self.db.clear_expired()?)
+            }
+
+            fn compact(&mut self) -> Result<(), QuickKvError>
+            {
+                Ok(self.db.compact()?)
+            }
+
+            fn exists<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<bool, QuickKvError>
+            {
+                Ok(self.db.exists(&key.as_key())?)
+            }
+
+            fn exists_many<K: AsKey>(&mut self, keys: &[K]) -> Result<Vec<bool>, QuickKvError>
+            {
+                Ok(self.db.exists_many(keys)?)
+            }
+
+            fn keys(&mut self) -> Result<Option<Vec<String>>, QuickKvError>
+            {
+                let keys = read_lock(&self.db.state)
+                    .entries
+                    .iter()
+                    .filter(|(_, entry)| !entry.is_expired())
+                    .map(|(key, _)| key.clone())
+                    .collect::<Vec<String>>();
+                if !keys.is_empty() {
+                    Ok(Some(keys))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn values(&mut self) -> Result<Option<Vec<T>>, QuickKvError>
+            {
+                let values = read_lock(&self.db.state)
+                    .entries
+                    .values()
+                    .filter(|entry| !entry.is_expired())
+                    .map(|entry| entry.data.clone())
+                    .collect::<Vec<T>>();
+
+                if !values.is_empty() {
+                    Ok(Some(values))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn entries(&mut self) -> Result<Vec<(String, T)>, QuickKvError>
+            {
+                let entries = read_lock(&self.db.state)
+                    .entries
+                    .iter()
+                    .filter(|(_, entry)| !entry.is_expired())
+                    .map(|(key, entry)| (key.clone(), entry.data.clone()))
+                    .collect::<Vec<(String, T)>>();
+
+                Ok(entries)
+            }
+
+            fn snapshot(&mut self) -> Result<HashMap<String, T>, QuickKvError>
+            {
+                let snapshot = read_lock(&self.db.state)
+                    .entries
+                    .iter()
+                    .filter(|(_, entry)| !entry.is_expired())
+                    .map(|(key, entry)| (key.clone(), entry.data.clone()))
+                    .collect::<HashMap<String, T>>();
+
+                Ok(snapshot)
+            }
+
+            fn keys_ordered(&mut self) -> Result<Option<Vec<String>>, QuickKvError>
+            {
+                let state = read_lock(&self.db.state);
+
+                let keys = state
+                    .order
+                    .iter()
+                    .filter(|key| state.entries.get(*key).is_some_and(|entry| !entry.is_expired()))
+                    .cloned()
+                    .collect::<Vec<String>>();
+
+                if !keys.is_empty() {
+                    Ok(Some(keys))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn keys_with_prefix<K: AsKey + ?Sized>(&mut self, prefix: &K) -> Result<Vec<String>, QuickKvError>
+            {
+                let prefix = prefix.as_key();
+                let state = read_lock(&self.db.state);
+
+                let keys = state
+                    .entries
+                    .iter()
+                    .filter(|(key, entry)| key.starts_with(&*prefix) && !entry.is_expired())
+                    .map(|(key, _)| key.clone())
+                    .collect();
+
+                Ok(keys)
+            }
+
+            fn range<A: AsKey + ?Sized, B: AsKey + ?Sized>(
+                &mut self,
+                start: &A,
+                end: &B,
+            ) -> Result<Vec<(String, T)>, QuickKvError>
+            {
+                Ok(self.db.range(&start.as_key(), &end.as_key())?)
+            }
+
+            fn create_index<K>(&mut self, name: &str, extractor: fn(&T) -> K)
+            where
+                K: Ord + Clone + Send + Sync + 'static,
+            {
+                self.db.create_index(name, extractor)
+            }
+
+            fn query_index<K>(&mut self, name: &str, key: &K) -> Result<Vec<T>, QuickKvError>
+            where
+                K: Ord + Clone + Send + Sync + 'static,
+            {
+                let entries = self.db.query_index(name, key)?;
+
+                Ok(entries.into_iter().map(|(_, value)| value).collect())
+            }
+
+            fn values_ordered(&mut self) -> Result<Option<Vec<T>>, QuickKvError>
+            {
+                let state = read_lock(&self.db.state);
+
+                let values = state
+                    .order
+                    .iter()
+                    .filter_map(|key| state.entries.get(key))
+                    .filter(|entry| !entry.is_expired())
+                    .map(|entry| entry.data.clone())
+                    .collect::<Vec<T>>();
+
+                if !values.is_empty() {
+                    Ok(Some(values))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn for_each<F>(&mut self, mut f: F) -> Result<(), QuickKvError>
+            where
+                F: FnMut(&str, &T),
+            {
+                let state = read_lock(&self.db.state);
+
+                for (key, entry) in state.entries.iter().filter(|(_, entry)| !entry.is_expired()) {
+                    f(key, &entry.data);
+                }
+
+                Ok(())
+            }
+
+            fn filter<F>(&mut self, pred: F) -> Result<Vec<(String, T)>, QuickKvError>
+            where
+                F: Fn(&T) -> bool,
+            {
+                let entries = read_lock(&self.db.state)
+                    .entries
+                    .iter()
+                    .filter(|(_, entry)| !entry.is_expired() && pred(&entry.data))
+                    .map(|(key, entry)| (key.clone(), entry.data.clone()))
+                    .collect::<Vec<(String, T)>>();
+
+                Ok(entries)
+            }
+
+            fn export<W: Write>(&mut self, w: &mut W) -> Result<(), QuickKvError>
+            {
+                Ok(self.db.export(w)?)
+            }
+
+            fn import<R: Read>(&mut self, r: &mut R) -> Result<usize, QuickKvError>
+            {
+                Ok(self.db.import(r)?)
+            }
+
+            fn entries_expiring_before(&mut self, when: DateTime<Utc>) -> Result<Vec<(String, T)>, QuickKvError>
+            {
+                Ok(self.db.entries_expiring_before(when)?)
+            }
+
+            fn len(&mut self) -> Result<usize, QuickKvError>
+            {
+                let len = read_lock(&self.db.state)
+                    .entries
+                    .values()
+                    .filter(|entry| !entry.is_expired())
+                    .count();
+
+                Ok(len)
+            }
+
+            fn purge(&mut self) -> Result<(), QuickKvError>
+            {
+                Ok(self.db.purge()?)
+            }
+
+            fn clear(&mut self) -> Result<(), QuickKvError>
+            {
+                self.purge()
+            }
+
+            fn get_many<K: AsKey>(&mut self, keys: &[K]) -> Result<Option<Vec<T>>, QuickKvError>
+            {
+                let mut values = Vec::new();
+
+                for key in keys {
+                    if let Ok(Some(v)) = self.db.get(key.as_key().into_owned()) {
+                        values.push(v);
+                    }
+                }
+
+                if !values.is_empty() {
+                    Ok(Some(values))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn get_many_pairs<K: AsKey>(&mut self, keys: &[K]) -> Result<Vec<(String, Option<T>)>, QuickKvError>
+            {
+                let mut pairs = Vec::with_capacity(keys.len());
+
+                for key in keys {
+                    let key = key.as_key().into_owned();
+                    pairs.push((key.clone(), self.db.get(key)?));
+                }
+
+                Ok(pairs)
+            }
+
+            fn set_many<K: AsKey>(&mut self, keys: &[K], values: &[T]) -> Result<(), QuickKvError>
+            {
+                let entries = keys
+                    .iter()
+                    .zip(values.iter())
+                    .map(|(key, value)| (key.as_key().into_owned(), value.clone(), None))
+                    .collect();
+
+                Ok(self.db.set_batch(entries)?)
+            }
+
+            fn delete_many<K: AsKey>(&mut self, keys: &[K]) -> Result<(), QuickKvError>
+            {
+                for key in keys {
+                    self.db.delete(&key.as_key())?;
+                }
+
+                Ok(())
+            }
+
+            fn write_latency_stats(&self) -> LatencyStats
+            {
+                self.db.write_latency_stats()
+            }
+
+            #[cfg(feature = "metrics")]
+            fn timings(&self) -> OpTimings
+            {
+                self.db.timings()
+            }
+
+            fn stats(&mut self) -> Result<DbStats, QuickKvError>
+            {
+                Ok(self.db.stats()?)
+            }
+
+            #[cfg(feature = "signals")]
+            fn install_shutdown_handler(&self) -> Result<(), QuickKvError>
+            {
+                Ok(self.db.install_shutdown_handler()?)
+            }
+
+            fn update_many<K: AsKey>(
+                &mut self,
+                keys: &[K],
+                values: &[T],
+                upsert: Option<bool>,
+            ) -> Result<usize, QuickKvError>
+            {
+                let mut updated = 0;
+
+                for (key, value) in keys.iter().zip(values.iter()) {
+                    if self.db.update(&key.as_key(), value.clone(), None, upsert)? {
+                        updated += 1;
+                    }
+                }
+
+                Ok(updated)
+            }
+        }
+    };
+}
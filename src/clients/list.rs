@@ -0,0 +1,53 @@
+use crate::error::QuickKvError;
+use crate::types::AsKey;
+
+/// Helpers for clients that store a `Vec` per key.
+///
+/// Implemented for clients storing `Vec<V>` values.
+pub trait ListClient<V>
+{
+    /// Append `item` to the `Vec` associated with a key and return its new length.
+    ///
+    /// The read and the write happen under a single lock, so concurrent pushes can't lose
+    /// items. A missing key starts as an empty `Vec`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<Vec<String>>::new(ClientConfig::new(
+    ///     "push_to_vec.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let len = client.push_to_vec("tags", "rust".to_string()).unwrap();
+    /// ```
+    fn push_to_vec<K: AsKey + ?Sized>(&mut self, key: &K, item: V) -> Result<usize, QuickKvError>;
+}
+
+/// Implements `ListClient` for a client over `Vec<V>`.
+macro_rules! impl_list_client {
+    ($client:ident) => {
+        impl<V> $crate::clients::ListClient<V> for $client<Vec<V>>
+        where
+            V: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
+        {
+            fn push_to_vec<K: $crate::types::AsKey + ?Sized>(
+                &mut self,
+                key: &K,
+                item: V,
+            ) -> Result<usize, $crate::error::QuickKvError>
+            {
+                let items = self.db.read_modify_write(&key.as_key(), |current| {
+                    let mut items = current.cloned().unwrap_or_default();
+                    items.push(item);
+
+                    Ok(items)
+                })?;
+
+                Ok(items.len())
+            }
+        }
+    };
+}
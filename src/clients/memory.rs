@@ -1,13 +1,26 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::io::{Read, Write};
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::clients::{BaseClient, ClientConfig};
+use crate::db::batcher::Batch;
 use crate::db::config::DatabaseConfiguration;
+#[cfg(feature = "metrics")]
+use crate::db::metrics::OpTimings;
+use crate::db::metrics::{DbStats, LatencyStats};
+use crate::db::pipeline::Pipeline;
+use crate::db::recovery::LoadReport;
 use crate::db::runtime::{RunTime, RuntTimeType};
+use crate::db::watch::ChangeListener;
 use crate::db::Database;
+use crate::error::{read_lock, QuickKvError};
+use crate::types::{AsKey, EntryMeta, KeyStatus};
 
 #[derive(Debug)]
 pub struct QuickMemoryClient<T>
@@ -17,155 +30,85 @@ where
     db: Database<T>,
 }
 
-impl<T> BaseClient<T> for QuickMemoryClient<T>
+impl_base_client!(QuickMemoryClient, database_config);
+
+impl<T> QuickMemoryClient<T>
 where
     T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
 {
-    fn new(config: ClientConfig) -> Self
+    /// Save every entry to a database file at `path`, which a `QuickClient` can open.
+    ///
+    /// This lets a database run in memory and be checkpointed to disk now and then. An existing
+    /// file at `path` is replaced, and saving fails with `QuickKvError::Locked` while another
+    /// client has it open. Open the file with the same format, compression, encryption and
+    /// namespace settings the memory client uses.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let config = ClientConfig::new(
+    ///     "persist.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// );
+    ///
+    /// let mut memory = QuickMemoryClient::<String>::new(config.clone());
+    ///
+    /// memory.set("user_1", "jamal".to_string()).unwrap();
+    /// memory.persist("persist.qkv").unwrap();
+    ///
+    /// let mut disk = QuickClient::<String>::new(config);
+    ///
+    /// assert_eq!(disk.get("user_1").unwrap(), Some("jamal".to_string()));
+    /// ```
+    pub fn persist(&mut self, path: &str) -> anyhow::Result<()>
     {
-        let _config = DatabaseConfiguration::new(
+        self.db.save_to(path)
+    }
+}
+
+/// Turns the client's settings into the database's, held in memory only.
+fn database_config(config: ClientConfig) -> anyhow::Result<DatabaseConfiguration>
+{
+    Ok(DatabaseConfiguration {
+        ttl_interval: config.ttl_interval,
+        track_write_latency: config.track_write_latency,
+        format: config.format,
+        compression: config.compression,
+        namespace: config.namespace,
+        compaction_threshold: config.compaction_threshold,
+        max_entries: config.max_entries,
+        eviction: config.eviction,
+        sync_on_write: config.sync_on_write,
+        encryption: config.encryption,
+        on_corruption: config.on_corruption,
+        max_key_len: config.max_key_len,
+        key_validator: config.key_validator,
+        io_buffer_size: config.io_buffer_size,
+        initial_capacity: config.initial_capacity,
+        log_format: config.log_format,
+        flush_interval: config.flush_interval,
+        ..DatabaseConfiguration::new(
             config.path,
-            RunTime::new(RuntTimeType::Memory).into(),
+            Some(RunTime::new(RuntTimeType::Memory)),
             config.log,
             config.log_level,
             config.default_ttl,
-        )
-        .unwrap();
-
-        let db = Database::new(_config).unwrap();
-
-        Self { db }
-    }
-
-    fn get(&mut self, key: &str) -> anyhow::Result<Option<T>>
-    {
-        match self.db.get(key.to_string()) {
-            Ok(value) => Ok(value),
-            Err(e) => Err(e),
-        }
-    }
-
-    fn set(&mut self, key: &str, value: T) -> anyhow::Result<()>
-    {
-        match self.db.set(key, value, None) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
-    }
-
-    fn update(&mut self, key: &str, value: T, upsert: Option<bool>) -> anyhow::Result<()>
-    {
-        match self.db.update(key, value, None, upsert) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
-    }
-
-    fn delete(&mut self, key: &str) -> anyhow::Result<()>
-    {
-        match self.db.delete(key) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
-    }
-
-    fn exists(&mut self, key: &str) -> anyhow::Result<bool>
-    {
-        match self.db.state.lock().unwrap().entries.contains_key(key) {
-            true => Ok(true),
-            false => Ok(false),
-        }
-    }
-
-    fn keys(&mut self) -> anyhow::Result<Option<Vec<String>>>
-    {
-        let keys = self.db.state.lock().unwrap().entries.keys().cloned().collect::<Vec<String>>();
-        if !keys.is_empty() {
-            Ok(Some(keys))
-        } else {
-            Ok(None)
-        }
-    }
-
-    fn values(&mut self) -> anyhow::Result<Option<Vec<T>>>
-    {
-        let values = self.db.state.lock().unwrap().entries.values().cloned().collect::<Vec<_>>();
-
-        if !values.is_empty() {
-            let v = values.into_iter().map(|entry| entry.data).collect::<Vec<T>>();
-            Ok(Some(v))
-        } else {
-            Ok(None)
-        }
-    }
-
-    fn len(&mut self) -> anyhow::Result<usize>
-    {
-        match self.db.state.lock().unwrap().entries.len() {
-            len if len > 0 => Ok(len),
-            _ => Ok(0),
-        }
-    }
-
-    fn purge(&mut self) -> anyhow::Result<()>
-    {
-        match self.db.purge() {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
-    }
-
-    fn get_many(&mut self, keys: &[&str]) -> anyhow::Result<Option<Vec<T>>>
-    {
-        let mut values = Vec::new();
-
-        for key in keys {
-            if let Ok(Some(v)) = self.db.get(key.to_string()) {
-                values.push(v);
-            }
-        }
-
-        if !values.is_empty() {
-            Ok(Some(values))
-        } else {
-            Ok(None)
-        }
-    }
-
-    fn set_many(&mut self, keys: &[&str], values: &[T]) -> anyhow::Result<()>
-    {
-        for (key, value) in keys.iter().zip(values.iter()) {
-            self.db.set(key, value.clone(), None)?;
-        }
-
-        Ok(())
-    }
-
-    fn delete_many(&mut self, keys: &[&str]) -> anyhow::Result<()>
-    {
-        for key in keys {
-            self.db.delete(key)?;
-        }
-
-        Ok(())
-    }
-
-    fn update_many(&mut self, keys: &[&str], values: &[T], upsert: Option<bool>) -> anyhow::Result<()>
-    {
-        for (key, value) in keys.iter().zip(values.iter()) {
-            self.db.update(key, value.clone(), None, upsert)?;
-        }
-
-        Ok(())
-    }
+        )?
+    })
 }
 
+impl_numeric_client!(QuickMemoryClient: i32, i64, u64);
+impl_list_client!(QuickMemoryClient);
+
 #[cfg(test)]
 mod tests
 {
     use tempfile::tempdir;
 
     use super::*;
+    use crate::clients::normal::QuickClient;
     use crate::types::HashSet;
 
     #[test]
@@ -176,6 +119,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client: QuickMemoryClient<String> = QuickMemoryClient::<String>::new(config);
 
@@ -199,6 +143,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickMemoryClient::<String>::new(config);
 
@@ -223,6 +168,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickMemoryClient::<String>::new(config);
 
@@ -249,6 +195,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickMemoryClient::<String>::new(config);
 
@@ -276,6 +223,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickMemoryClient::<String>::new(config);
 
@@ -304,6 +252,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickMemoryClient::<String>::new(config);
 
@@ -332,6 +281,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickMemoryClient::<String>::new(config);
 
@@ -358,6 +308,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickMemoryClient::<String>::new(config);
 
@@ -381,6 +332,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickMemoryClient::<String>::new(config);
 
@@ -421,6 +373,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickMemoryClient::<String>::new(config);
 
@@ -439,4 +392,76 @@ mod tests
         let remaining_keys = client.keys().unwrap().unwrap();
         assert_eq!(remaining_keys, vec!["key3"]);
     }
+
+    #[test]
+    fn test_quick_memory_client_never_touches_disk()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let db_dir = tmp_dir.path().join("nested");
+        let tmp_file = db_dir.join("test.qkv");
+
+        let config = ClientConfig {
+            path: Some(tmp_file.to_str().unwrap().to_string()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickMemoryClient::<String>::new(config);
+
+        client.set("test_key", "test_value".to_string()).unwrap();
+
+        assert_eq!(client.get("test_key").unwrap(), Some("test_value".to_string()));
+        assert!(!tmp_file.exists());
+        assert!(!db_dir.exists());
+    }
+
+    #[test]
+    fn test_quick_memory_client_persist()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file.clone()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickMemoryClient::<String>::new(config.clone());
+
+        for i in 0..10 {
+            client.set(&format!("key{}", i), format!("value{}", i)).unwrap();
+        }
+
+        client.persist(&tmp_file).unwrap();
+
+        let mut disk = QuickClient::<String>::new(config);
+
+        assert_eq!(disk.len().unwrap(), 10);
+
+        for i in 0..10 {
+            assert_eq!(disk.get(&format!("key{}", i)).unwrap(), Some(format!("value{}", i)));
+        }
+
+        // The file is locked while the disk client has it open.
+        assert!(client.persist(&tmp_file).is_err());
+    }
+
+    #[test]
+    fn test_quick_client_path()
+    {
+        let config = ClientConfig {
+            path: Some("test_db".to_string()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let client = QuickMemoryClient::<String>::new(config);
+
+        assert_eq!(client.path(), None);
+        assert!(client.is_in_memory());
+    }
 }
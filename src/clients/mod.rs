@@ -1,14 +1,47 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use log::LevelFilter;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::db::batcher::Batch;
+use crate::db::codec::{Compression, SerializationFormat};
+use crate::db::encryption::EncryptionConfig;
+use crate::db::eviction::EvictionPolicy;
+use crate::db::logging::LogFormat;
+#[cfg(feature = "metrics")]
+use crate::db::metrics::OpTimings;
+use crate::db::metrics::{DbStats, LatencyStats};
+use crate::db::pipeline::Pipeline;
+use crate::db::recovery::{CorruptionPolicy, LoadReport};
+use crate::db::watch::ChangeListener;
+use crate::error::QuickKvError;
+use crate::types::{AsKey, EntryMeta, KeyStatus};
+
+#[macro_use]
+mod base;
+#[macro_use]
+mod list;
+#[macro_use]
+mod numeric;
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod memory;
+pub mod namespaced;
 pub mod normal;
 
+pub use self::list::ListClient;
+pub use self::numeric::NumericClient;
+
+/// A client that can be handed to several threads, for example by a server front-end.
+pub type SharedClient<T> = Arc<Mutex<normal::QuickClient<T>>>;
+
 #[derive(Debug, Clone)]
 pub struct ClientConfig
 {
@@ -28,9 +61,119 @@ pub struct ClientConfig
     ///
     /// If enabled, all entries will have a ttl by default.
     /// If disabled (None), then you will have to manually set the ttl for each entry.
+    /// A zero duration is rejected with `QuickKvError::InvalidTtl`.
     ///
     /// Default: None
     pub default_ttl: Option<Duration>,
+    /// How often expired entries are removed in the background.
+    ///
+    /// Default: 1 second
+    pub ttl_interval: Option<Duration>,
+    /// If the latency of disk writes should be recorded, see `write_latency_stats`.
+    ///
+    /// Default: false
+    pub track_write_latency: Option<bool>,
+    /// The format entries are written to the database file in.
+    ///
+    /// Default: SerializationFormat::Bincode
+    pub format: Option<SerializationFormat>,
+    /// Compress the database file.
+    ///
+    /// Compressed files are rewritten in full on every write instead of being appended to.
+    ///
+    /// Default: None
+    pub compression: Option<Compression>,
+    /// Scope every key to this namespace.
+    ///
+    /// Clients with different namespaces can share one file without seeing each other's keys.
+    /// Enumeration and `purge` only cover the client's own namespace.
+    ///
+    /// Clients opened on the same file in one process share its handle, so several namespaces
+    /// can be open at once. They must use the same format, compression and encryption. The
+    /// file stays locked against other processes, and against clients without a namespace,
+    /// until the last of them is dropped.
+    ///
+    /// Default: None
+    pub namespace: Option<String>,
+    /// Rewrite the database file once this percentage of its records are stale.
+    ///
+    /// Updates are appended rather than rewriting the file, which leaves the previous
+    /// record of the key behind until the file is compacted.
+    ///
+    /// Default: 50
+    pub compaction_threshold: Option<u8>,
+    /// The most keys the database may hold.
+    ///
+    /// Inserting a new key past the limit fails with `QuickKvError::CapacityExceeded` unless an
+    /// `eviction` policy is set. Overwriting an existing key is always allowed.
+    ///
+    /// Default: None
+    pub max_entries: Option<usize>,
+    /// What to do when a new key would pass `max_entries`.
+    ///
+    /// With `None` the insert fails, with `EvictionPolicy::Lru` the least recently used key
+    /// is removed to make room.
+    ///
+    /// Default: None
+    pub eviction: Option<EvictionPolicy>,
+    /// Sync the database file to disk after every write.
+    ///
+    /// Turning this off makes writes much faster, but they only reach the operating system's
+    /// cache. They survive the process crashing, not the machine losing power, until
+    /// `flush` is called.
+    ///
+    /// Default: true
+    pub sync_on_write: Option<bool>,
+    /// Encrypt every record in the database file.
+    ///
+    /// Opening the file with a different key fails with `QuickKvError::Decryption`.
+    ///
+    /// Default: None
+    pub encryption: Option<EncryptionConfig>,
+    /// What to do when a record in the database file can't be read on load.
+    ///
+    /// Default: CorruptionPolicy::Fail
+    pub on_corruption: Option<CorruptionPolicy>,
+    /// The longest key, in bytes, that may be written.
+    ///
+    /// Longer keys are rejected with `QuickKvError::InvalidKey`, so a runaway key can't bloat
+    /// every record it's written in.
+    ///
+    /// Default: None
+    pub max_key_len: Option<usize>,
+    /// Checked against every key before it's written, keys it returns `false` for are
+    /// rejected with `QuickKvError::InvalidKey`.
+    ///
+    /// Default: None
+    pub key_validator: Option<fn(&str) -> bool>,
+    /// The size of the buffers the database file is read and written through, in bytes.
+    ///
+    /// Larger buffers mean fewer system calls when loading or writing large files, 64 KiB is a
+    /// good start for bulk workloads.
+    ///
+    /// Default: 8 KiB
+    pub io_buffer_size: Option<usize>,
+    /// How many keys to make room for up front.
+    ///
+    /// Setting this to roughly the number of keys the database will hold saves growing the
+    /// in-memory map over and over during a bulk load.
+    ///
+    /// Default: None
+    pub initial_capacity: Option<usize>,
+    /// The format of log lines, see `LogFormat`.
+    ///
+    /// Like `log_level`, only the first client with logging enabled decides it.
+    ///
+    /// Default: LogFormat::Text
+    pub log_format: Option<LogFormat>,
+    /// Sync writes to disk on a background timer this often instead of after every write.
+    ///
+    /// Writes still reach the OS right away, only the sync is coalesced, so a crash of the
+    /// machine can lose up to one interval of writes. A write syncs right away anyway once over
+    /// a megabyte is waiting. Takes precedence over `sync_on_write`.
+    ///
+    /// Default: None
+    pub flush_interval: Option<Duration>,
 }
 
 impl ClientConfig
@@ -42,6 +185,23 @@ impl ClientConfig
             log,
             log_level,
             default_ttl: None,
+            ttl_interval: None,
+            track_write_latency: None,
+            format: None,
+            compression: None,
+            namespace: None,
+            compaction_threshold: None,
+            max_entries: None,
+            eviction: None,
+            sync_on_write: None,
+            encryption: None,
+            on_corruption: None,
+            max_key_len: None,
+            key_validator: None,
+            io_buffer_size: None,
+            initial_capacity: None,
+            log_format: None,
+            flush_interval: None,
         }
     }
 }
@@ -55,6 +215,23 @@ impl Default for ClientConfig
             log: true.into(),
             log_level: LevelFilter::Info.into(),
             default_ttl: None,
+            ttl_interval: None,
+            track_write_latency: None,
+            format: None,
+            compression: None,
+            namespace: None,
+            compaction_threshold: None,
+            max_entries: None,
+            eviction: None,
+            sync_on_write: None,
+            encryption: None,
+            on_corruption: None,
+            max_key_len: None,
+            key_validator: None,
+            io_buffer_size: None,
+            initial_capacity: None,
+            log_format: None,
+            flush_interval: None,
         }
     }
 }
@@ -67,6 +244,9 @@ where
     ///
     /// `config` is the configuration for the database. If `None`, then the default configuration will be used.
     ///
+    /// Only one client can have a database file open for writing at a time, clone the client to share it.
+    /// Opening a file another client is writing to panics with `QuickKvError::Locked`.
+    ///
     /// The client needs to know what type of data it will be storing, so it can properly serialize and deserialize it.
     /// You need to specify the type of data when creating a new client using the `client::<T>::new()` method.
     ///
@@ -93,7 +273,7 @@ where
     /// };
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "new.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
@@ -116,7 +296,7 @@ where
     /// }
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "get.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
@@ -129,19 +309,10 @@ where
     /// ```
     /// Do something with the result. After Consuming the result, you
     /// must handle the `Option<T>` that is returned.
-    fn get(&mut self, key: &str) -> anyhow::Result<Option<T>>;
-    /// Set the value associated with a key.
-    ///
-    /// If the key already exists, the database will attempt to overwrite the value.
-    ///
-    /// `key` to set the value for.
-    ///
-    /// `value` to set for the key.
+    fn get<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<Option<T>, QuickKvError>;
+    /// Get the value associated with a key, failing if it does not exist.
     ///
-    /// `ttl` the time-to-live for the key. If `None`, then the the key will not expire,
-    /// unless `default_ttl` is set in the configuration. If `default_ttl` is set, then
-    /// the key will expire after the default ttl. If ttl is set here, it will override
-    /// the default ttl set in the configuration.
+    /// Returns `QuickKvError::KeyNotFound` instead of `None` when the key is missing or expired.
     ///
     /// # Examples
     /// ```rust
@@ -154,24 +325,24 @@ where
     /// };
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "get_required.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
-    /// client.set("user_1", Schema { id: 10 }).unwrap();
+    /// match client.get_required("user_1") {
+    ///     Ok(user) => println!("{:?}", user),
+    ///     Err(QuickKvError::KeyNotFound(key)) => println!("no user {}", key),
+    ///     Err(e) => panic!("{}", e),
+    /// }
     /// ```
-    fn set(&mut self, key: &str, value: T) -> anyhow::Result<()>;
-    /// Update the value associated with a key.
-    ///
-    /// By default update will fail if the key does not exist. If you want to upsert the value, then
-    /// you can set `upsert` to `true` using `true.into()` or `Some(true)`.
-    ///
-    /// `key` to update the value for.
-    ///
-    /// `value` to update for the key.
+    fn get_required<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<T, QuickKvError>;
+    /// Get the value associated with a key, telling apart expired keys from missing ones.
     ///
-    /// `upsert` if the value should be upserted if the key does not exist.
+    /// Returns `KeyStatus::Present` with the value if the key exists, `KeyStatus::Expired` if
+    /// the key's ttl has passed, or `KeyStatus::Absent` if the key was never set. Expired keys
+    /// are reported once and then removed, after which they are `Absent`. The background task
+    /// may also remove them first.
     ///
     /// # Examples
     /// ```rust
@@ -181,24 +352,80 @@ where
     /// struct Schema
     /// {
     ///     id: u64,
-    /// };
+    /// }
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "get_status.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// match client.get_status("session_1").unwrap() {
+    ///     KeyStatus::Present(session) => { /* use the session */ }
+    ///     KeyStatus::Expired => { /* ask the user to log in again */ }
+    ///     KeyStatus::Absent => { /* unknown session */ }
+    /// }
+    /// ```
+    fn get_status<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<KeyStatus<T>, QuickKvError>;
+    /// Get the value associated with a key along with when it expires, `None` for the expiry
+    /// meaning it never does.
+    ///
+    /// Both are read under one lock, so unlike calling `get` and then `ttl` a concurrent
+    /// `touch` or `update` can't land in between. Returns `None` if the key doesn't exist or
+    /// has expired.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "get_with_meta.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
-    /// client.update("user_1", Schema { id: 10 }, None).unwrap(); // fails
     /// client
-    ///     .update("user_1", Schema { id: 20 }, true.into())
-    ///     .unwrap(); // succeeds
+    ///     .set_with_ttl("session", "token".to_string(), Duration::from_secs(60))
+    ///     .unwrap();
+    ///
+    /// if let Some((token, expires_at)) = client.get_with_meta("session").unwrap() {
+    ///     println!("{} expires at {:?}", token, expires_at);
+    /// }
     /// ```
-    fn update(&mut self, key: &str, value: T, upsert: Option<bool>) -> anyhow::Result<()>;
-
-    /// Delete the value associated with a key.
+    fn get_with_meta<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<Option<(T, Option<DateTime<Utc>>)>, QuickKvError>;
+    /// Get the value associated with a key, blocking for up to `timeout` until it is set.
     ///
-    /// `key` to delete the value for.
+    /// Meant for producer/consumer setups where another thread sets the key, the caller sleeps
+    /// until a write comes in instead of polling. Returns `None` if the key still doesn't exist
+    /// once `timeout` has passed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "get_or_wait.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let mut producer = client.clone();
+    /// thread::spawn(move || producer.set("job", "done".to_string()).unwrap());
+    ///
+    /// let result = client.get_or_wait("job", Duration::from_secs(5)).unwrap();
+    /// assert_eq!(result, Some("done".to_string()));
+    /// ```
+    fn get_or_wait<K: AsKey + ?Sized>(&mut self, key: &K, timeout: Duration) -> Result<Option<T>, QuickKvError>;
+    /// Pass a borrow of the value associated with a key to `f` and return what it returns.
+    ///
+    /// Unlike `get`, the value isn't cloned, handy to read one field out of a large value.
+    /// `f` runs while the database is locked for reading, so it must not use the client.
+    /// Returns `None` if the key doesn't exist or has expired.
     ///
     /// # Examples
     /// ```rust
@@ -207,21 +434,47 @@ where
     /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
     /// struct Schema
     /// {
-    ///     id: u64,
+    ///     name: String,
+    ///     history: Vec<u64>,
     /// };
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "with_value.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
-    /// client.delete("user_1").unwrap();
+    /// client
+    ///     .set(
+    ///         "user_1",
+    ///         Schema {
+    ///             name: "alice".to_string(),
+    ///             history: (0..10_000).collect(),
+    ///         },
+    ///     )
+    ///     .unwrap();
+    ///
+    /// let name = client
+    ///     .with_value("user_1", |user| user.name.clone())
+    ///     .unwrap();
+    /// assert_eq!(name, Some("alice".to_string()));
     /// ```
-    fn delete(&mut self, key: &str) -> anyhow::Result<()>;
-    /// Check if a key exists in the database.
+    fn with_value<K: AsKey + ?Sized, R, F>(&mut self, key: &K, f: F) -> Result<Option<R>, QuickKvError>
+    where
+        F: FnOnce(&T) -> R;
+    /// Set the value associated with a key.
     ///
-    /// `key` to check if it exists.
+    /// If the key already exists, the database will attempt to overwrite the value.
+    ///
+    /// `key` to set the value for.
+    ///
+    /// `value` to set for the key.
+    ///
+    /// `ttl` the time-to-live for the key. If `None`, then the the key will not expire,
+    /// unless `default_ttl` is set in the configuration. If `default_ttl` is set, then
+    /// the key will expire after the default ttl. If ttl is set here, it will override
+    /// the default ttl set in the configuration. A ttl of zero is rejected with
+    /// `QuickKvError::InvalidTtl` instead of storing a key that is already expired.
     ///
     /// # Examples
     /// ```rust
@@ -234,22 +487,23 @@ where
     /// };
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "set.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
-    /// if client.exists("user_1").unwrap() {
-    ///     // do something
-    /// }
+    /// client.set("user_1", Schema { id: 10 }).unwrap();
     /// ```
-    fn exists(&mut self, key: &str) -> anyhow::Result<bool>;
-    /// Get all keys in the database.
+    fn set<K: AsKey + ?Sized>(&mut self, key: &K, value: T) -> Result<(), QuickKvError>;
+    /// Set the value associated with a key, expiring it after `ttl`.
     ///
-    /// Returns `None` if there are no keys in the database or a `Vec<String>` keys.
+    /// This overrides the `default_ttl` from the configuration for this key. A ttl of zero
+    /// is rejected with `QuickKvError::InvalidTtl`.
     ///
     /// # Examples
     /// ```rust
+    /// use std::time::Duration;
+    ///
     /// use quick_kv::prelude::*;
     ///
     /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -259,17 +513,95 @@ where
     /// };
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "set_with_ttl.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
-    /// let all_keys = client.keys().unwrap();
+    /// client
+    ///     .set_with_ttl("user_1", Schema { id: 10 }, Duration::from_secs(60))
+    ///     .unwrap();
     /// ```
-    fn keys(&mut self) -> anyhow::Result<Option<Vec<String>>>;
-    /// Get all values in the database.
+    fn set_with_ttl<K: AsKey + ?Sized>(&mut self, key: &K, value: T, ttl: Duration) -> Result<(), QuickKvError>;
+    /// Set the value associated with a key, expiring it at the wall-clock time `at`.
     ///
-    /// Returns `None` if there are no values in the database or a `Vec<T>` values.
+    /// Useful for lining expiries up with a point in time, such as midnight, instead of a
+    /// duration from now. The `default_ttl` from the configuration doesn't apply, and a time
+    /// already in the past leaves the key expired straight away.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "set_with_expiry.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let in_an_hour = Utc::now() + chrono::Duration::hours(1);
+    ///
+    /// client
+    ///     .set_with_expiry("daily_quote", "hello".to_string(), in_an_hour)
+    ///     .unwrap();
+    /// ```
+    fn set_with_expiry<K: AsKey + ?Sized>(&mut self, key: &K, value: T, at: DateTime<Utc>) -> Result<(), QuickKvError>;
+    /// Set the value associated with a key and return the value it replaced.
+    ///
+    /// Returns `None` if the key didn't exist or had expired. The old value is read and the new
+    /// one written under one lock, so no other write can land in between.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "set_and_get_previous.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.set("user_1", "jamal".to_string()).unwrap();
+    ///
+    /// let previous = client
+    ///     .set_and_get_previous("user_1", "bob".to_string())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(previous, Some("jamal".to_string()));
+    /// ```
+    fn set_and_get_previous<K: AsKey + ?Sized>(&mut self, key: &K, value: T) -> Result<Option<T>, QuickKvError>;
+    /// Set the value associated with a key only if the key doesn't exist yet.
+    ///
+    /// Returns `false`, writing nothing, if the key already holds a value. Unlike `set` this
+    /// never clobbers existing data, which makes it the way to create records with unique keys.
+    /// An expired key counts as missing.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "insert.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.delete("username:jamal").unwrap();
+    ///
+    /// assert!(client
+    ///     .insert("username:jamal", "user_1".to_string())
+    ///     .unwrap());
+    /// assert!(!client
+    ///     .insert("username:jamal", "user_2".to_string())
+    ///     .unwrap());
+    /// ```
+    fn insert<K: AsKey + ?Sized>(&mut self, key: &K, value: T) -> Result<bool, QuickKvError>;
+    /// Set the value associated with a key, skipping the write if `idem` was already applied.
+    ///
+    /// Callers that may retry a request can pass the same idempotency token each time, only
+    /// the first attempt is written. The most recent 1024 tokens are remembered in memory.
+    ///
+    /// Returns `false` if the write was skipped.
     ///
     /// # Examples
     /// ```rust
@@ -282,17 +614,22 @@ where
     /// };
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "set_idempotent.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
-    /// let all_values = client.values().unwrap();
+    /// let applied = client
+    ///     .set_idempotent("user_1", Schema { id: 10 }, "request-42")
+    ///     .unwrap();
     /// ```
-    fn values(&mut self) -> anyhow::Result<Option<Vec<T>>>;
-    /// Get the number of keys in the database.
+    fn set_idempotent<K: AsKey + ?Sized>(&mut self, key: &K, value: T, idem: &str) -> Result<bool, QuickKvError>;
+    /// Get the value associated with a key, or insert the value returned by `f` if the key
+    /// does not exist.
     ///
-    /// Returns `0` if there are no keys in the database or the number of keys in the database.
+    /// The check and the insert happen under a single lock, so `f` only runs once even if
+    /// several clones of the client race for the same key. The inserted value is written to
+    /// disk just like `set`.
     ///
     /// # Examples
     /// ```rust
@@ -305,17 +642,27 @@ where
     /// };
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "get_or_insert_with.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
-    /// let num_keys = client.len().unwrap();
+    /// let user = client
+    ///     .get_or_insert_with("user_1", || Schema { id: 10 })
+    ///     .unwrap();
     /// ```
-    fn len(&mut self) -> anyhow::Result<usize>;
-    /// Clears all keys and values from the database.
+    fn get_or_insert_with<K: AsKey + ?Sized, F>(&mut self, key: &K, f: F) -> Result<T, QuickKvError>
+    where
+        F: FnOnce() -> T;
+    /// Get the time left before a key expires.
+    ///
+    /// Returns `None` if the key does not exist or has no ttl. A key that has expired but
+    /// hasn't been removed yet returns `Duration::ZERO`.
+    ///
     /// # Examples
     /// ```rust
+    /// use std::time::Duration;
+    ///
     /// use quick_kv::prelude::*;
     ///
     /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -324,16 +671,71 @@ where
     ///     id: u64,
     /// };
     ///
-    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig {
+    ///     default_ttl: Some(Duration::from_secs(60)),
+    ///     ..ClientConfig::new(
+    ///         "ttl.qkv".to_string(),
+    ///         true.into(),
+    ///         LevelFilter::Debug.into(),
+    ///     )
+    /// });
+    ///
+    /// client.set("user_1", Schema { id: 10 }).unwrap();
+    ///
+    /// let remaining = client.ttl("user_1").unwrap();
+    /// ```
+    fn ttl<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<Option<Duration>, QuickKvError>;
+    /// Make an existing key expire `new_ttl` from now, without changing its value.
+    ///
+    /// This is the usual way to keep a cache entry alive while it's still in use, and it works on
+    /// keys that had no ttl before too. Returns `false` if the key doesn't exist or has already
+    /// expired. A ttl of zero is rejected with `QuickKvError::InvalidTtl`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "touch.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
-    /// client.purge().unwrap();
+    /// client
+    ///     .set_with_ttl("session", "token".to_string(), Duration::from_secs(60))
+    ///     .unwrap();
+    ///
+    /// assert!(client.touch("session", Duration::from_secs(3600)).unwrap());
     /// ```
-    fn purge(&mut self) -> anyhow::Result<()>;
-    /// Get multiple values associated with multiple keys.
+    fn touch<K: AsKey + ?Sized>(&mut self, key: &K, new_ttl: Duration) -> Result<bool, QuickKvError>;
+    /// Get the default time-to-live the client was configured with, if any.
+    fn default_ttl(&self) -> Option<Duration>;
+    /// Get the path of the database file, or `None` if the database only lives in memory.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "path.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// assert_eq!(client.path(), Some("path.qkv"));
+    /// ```
+    fn path(&self) -> Option<&str>;
+    /// Check if the database only lives in memory, without a file behind it.
+    fn is_in_memory(&self) -> bool;
+    /// Set the value associated with a key only if its current value equals `expected`.
+    ///
+    /// Passing `None` as `expected` only inserts the value if the key does not exist yet. The
+    /// comparison and the write happen under a single lock, so there is no gap between reading
+    /// and writing like there is with `get` followed by `set`. Returns `true` if the value was
+    /// written.
+    ///
     /// # Examples
     /// ```rust
     /// use quick_kv::prelude::*;
@@ -345,15 +747,50 @@ where
     /// };
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "compare_and_swap.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
-    /// let values = client.get_many(&["user_1", "user_2"]).unwrap();
+    /// client.set("user_1", Schema { id: 10 }).unwrap();
+    ///
+    /// let swapped = client
+    ///     .compare_and_swap("user_1", Some(Schema { id: 10 }), Schema { id: 11 })
+    ///     .unwrap();
     /// ```
-    fn get_many(&mut self, keys: &[&str]) -> anyhow::Result<Option<Vec<T>>>;
-    /// Set multiple values associated with multiple keys.
+    fn compare_and_swap<K: AsKey + ?Sized>(&mut self, key: &K, expected: Option<T>, new: T) -> Result<bool, QuickKvError>;
+    /// Compute the new value of a key from its current one, in a single step.
+    ///
+    /// `f` gets the current value, or `None` if the key doesn't exist. Whatever it returns is
+    /// stored, and returning `None` deletes the key. No other write can land between the read
+    /// and the write. Returns the new value.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<u64>::new(ClientConfig::new(
+    ///     "merge.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// // Count visits, dropping the counter once it reaches 10.
+    /// let visits = client
+    ///     .merge("visits", |current| match current.unwrap_or(0) + 1 {
+    ///         10 => None,
+    ///         n => Some(n),
+    ///     })
+    ///     .unwrap();
+    /// ```
+    fn merge<K: AsKey + ?Sized, F>(&mut self, key: &K, f: F) -> Result<Option<T>, QuickKvError>
+    where
+        F: FnOnce(Option<T>) -> Option<T>;
+    /// Apply several writes at once, or none of them.
+    ///
+    /// The closure queues `set`, `update` and `delete` operations on a `Batch`. When it
+    /// returns `Ok`, every operation is applied and written to disk together. When it returns
+    /// an error, the batch is discarded and the database is left untouched.
     ///
     /// # Examples
     /// ```rust
@@ -366,20 +803,60 @@ where
     /// };
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "transaction.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
     /// client
-    ///     .set_many(
-    ///         &["user_1", "user_2"],
-    ///         &[Schema { id: 10 }, Schema { id: 20 }],
-    ///     )
+    ///     .transaction(|batch| {
+    ///         batch.set("user_1", Schema { id: 10 });
+    ///         batch.set("user_2", Schema { id: 20 });
+    ///         Ok(())
+    ///     })
     ///     .unwrap();
     /// ```
-    fn set_many(&mut self, keys: &[&str], values: &[T]) -> anyhow::Result<()>;
-    /// Delete multiple values associated with multiple keys.
+    fn transaction<F>(&mut self, f: F) -> Result<(), QuickKvError>
+    where
+        F: FnOnce(&mut Batch<T>) -> anyhow::Result<()>;
+    /// Queue `get`, `set` and `delete` operations to run together.
+    ///
+    /// Nothing runs until `execute` is called on the returned `Pipeline`, which applies every
+    /// operation in order under a single lock and returns one `PipelineResult` per operation.
+    /// Cheaper than calling each method on its own for mixed batches of reads and writes.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<u64>::new(ClientConfig::new(
+    ///     "pipeline.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let mut pipeline = client.pipeline();
+    /// pipeline.set("hits", 1).set("misses", 0).get("hits");
+    ///
+    /// let results = pipeline.execute().unwrap();
+    /// assert_eq!(results[2], PipelineResult::Get(Some(1)));
+    /// ```
+    fn pipeline(&mut self) -> Pipeline<'_, T>
+    where
+        T: Clone + 'static;
+    /// Update the value associated with a key.
+    ///
+    /// By default nothing is written if the key does not exist. If you want to upsert the value, then
+    /// you can set `upsert` to `true` using `true.into()` or `Some(true)`.
+    ///
+    /// Returns `true` if the value was updated or inserted, and `false` if the key was missing
+    /// and nothing was written.
+    ///
+    /// `key` to update the value for.
+    ///
+    /// `value` to update for the key.
+    ///
+    /// `upsert` if the value should be upserted if the key does not exist.
     ///
     /// # Examples
     /// ```rust
@@ -392,16 +869,1001 @@ where
     /// };
     ///
     /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
-    ///     "db.qkv".to_string(),
+    ///     "update.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.update("user_1", Schema { id: 10 }, None).unwrap(); // returns false
+    /// client
+    ///     .update("user_1", Schema { id: 20 }, true.into())
+    ///     .unwrap(); // returns true
+    /// ```
+    fn update<K: AsKey + ?Sized>(&mut self, key: &K, value: T, upsert: Option<bool>) -> Result<bool, QuickKvError>;
+
+    /// Delete the value associated with a key.
+    ///
+    /// `key` to delete the value for.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "delete.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.delete("user_1").unwrap();
+    /// ```
+    fn delete<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<(), QuickKvError>;
+    /// Delete a key and return the value it held.
+    ///
+    /// Returns `None` if the key does not exist. The read and the delete happen together, so
+    /// when several consumers pop the same key only one of them gets the value.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "pop.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// if let Some(job) = client.pop("job_1").unwrap() {
+    ///     // handle the job
+    /// }
+    /// ```
+    fn pop<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<Option<T>, QuickKvError>;
+    /// Move the value stored under `from` to `to`.
+    ///
+    /// The value keeps its ttl, and anything already stored under `to` is overwritten.
+    /// Returns `false` if `from` does not exist.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "rename.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.set("user_1", Schema { id: 1 }).unwrap();
+    ///
+    /// client.rename("user_1", "admin_1").unwrap(); // returns true
+    /// ```
+    fn rename<A: AsKey + ?Sized, B: AsKey + ?Sized>(&mut self, from: &A, to: &B) -> Result<bool, QuickKvError>;
+    /// Call `f` after every change to a key, with the key and what happened to it.
+    ///
+    /// Callbacks run on the thread that made the change once the database lock is released, so
+    /// they may use the client. Keys removed by the background ttl task are reported from
+    /// that task's thread. Every clone of the client shares the same callbacks.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::sync::Arc;
+    ///
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "on_change.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.on_change(Arc::new(|key, kind| println!("{} {:?}", key, kind)));
+    /// ```
+    fn on_change(&mut self, f: ChangeListener);
+    /// Sync every write made so far to disk.
+    ///
+    /// Writes are synced as they happen unless `sync_on_write` is turned off in the
+    /// `ClientConfig`, in which case this is how to make them durable. The memory client has
+    /// nothing to sync.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig {
+    ///     sync_on_write: Some(false),
+    ///     ..ClientConfig::new(
+    ///         "flush.qkv".to_string(),
+    ///         true.into(),
+    ///         LevelFilter::Debug.into(),
+    ///     )
+    /// });
+    ///
+    /// for i in 0..1000 {
+    ///     client.set(&i.to_string(), i.to_string()).unwrap();
+    /// }
+    ///
+    /// client.flush().unwrap();
+    /// ```
+    fn flush(&mut self) -> Result<(), QuickKvError>;
+    /// Get when a key was created, last updated and when it expires.
+    ///
+    /// Returns `None` if the key does not exist or has expired.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "metadata.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.set("user_1", "jamal".to_string()).unwrap();
+    ///
+    /// if let Some(meta) = client.metadata("user_1").unwrap() {
+    ///     println!("last written at {}", meta.updated_at);
+    /// }
+    /// ```
+    fn metadata<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<Option<EntryMeta>, QuickKvError>;
+    /// How many records were read from the database file when the client was created, and
+    /// how many were skipped as corrupted.
+    ///
+    /// Records are only skipped when `on_corruption` allows it.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let client = QuickClient::<String>::new(ClientConfig {
+    ///     on_corruption: Some(CorruptionPolicy::SkipAndTruncate),
+    ///     ..ClientConfig::new(
+    ///         "load_report.qkv".to_string(),
+    ///         true.into(),
+    ///         LevelFilter::Debug.into(),
+    ///     )
+    /// });
+    ///
+    /// let report = client.load_report();
+    ///
+    /// if report.skipped > 0 {
+    ///     println!("Dropped {} corrupted records", report.skipped);
+    /// }
+    /// ```
+    fn load_report(&self) -> LoadReport;
+    /// Read every record in the database file again and count the ones that can't be
+    /// decoded as `T`.
+    ///
+    /// Use it after changing the layout of `T` to find out whether existing files still load,
+    /// before a read or a restart fails on them. Nothing in the file is changed. A file that
+    /// doesn't match won't open with the default `CorruptionPolicy::Fail`, open it with
+    /// `open_read_only` and `CorruptionPolicy::SkipAndContinue` to check it without dropping
+    /// the records that fail.
+    ///
+    /// To keep old files readable when adding a field, give it `#[serde(default)]` and store
+    /// entries as `SerializationFormat::Json`, which matches fields by name. Bincode stores
+    /// fields by position, so any change to the layout breaks files written before it. Removed
+    /// fields are ignored by JSON and may go unnoticed with bincode.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "validate_schema.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.set("user_1", "alice".to_string()).unwrap();
+    ///
+    /// assert_eq!(client.validate_schema().unwrap(), 0);
+    /// ```
+    fn validate_schema(&mut self) -> Result<usize, QuickKvError>;
+    /// Delete every key that starts with `prefix`.
+    ///
+    /// The database file is rewritten once, no matter how many keys are removed.
+    ///
+    /// Returns the number of keys deleted.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "delete_prefix.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let deleted = client.delete_prefix("user:").unwrap();
+    /// ```
+    fn delete_prefix<K: AsKey + ?Sized>(&mut self, prefix: &K) -> Result<usize, QuickKvError>;
+    /// Keep only the entries for which `f` returns `true`, deleting the rest.
+    ///
+    /// The database file is rewritten once, no matter how many keys are removed.
+    ///
+    /// Returns the number of keys deleted.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "retain.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let deleted = client.retain(|_key, value| value.id > 10).unwrap();
+    /// ```
+    fn retain<F>(&mut self, f: F) -> Result<usize, QuickKvError>
+    where
+        F: Fn(&str, &T) -> bool;
+    /// Delete every entry whose ttl has already passed.
+    ///
+    /// Expired entries are also removed in the background, this forces it to happen now
+    /// and drops them from the database file as well.
+    ///
+    /// Returns the number of keys deleted.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "clear_expired.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let deleted = client.clear_expired().unwrap();
+    /// ```
+    fn clear_expired(&mut self) -> Result<usize, QuickKvError>;
+    /// Rewrite the database file so it only holds the current entries.
+    ///
+    /// Writes are appended to the file, so overwritten values leave stale records behind.
+    /// Compacting removes them.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "compact.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.compact().unwrap();
+    /// ```
+    fn compact(&mut self) -> Result<(), QuickKvError>;
+    /// Check if a key exists in the database.
+    ///
+    /// `key` to check if it exists.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "exists.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// if client.exists("user_1").unwrap() {
+    ///     // do something
+    /// }
+    /// ```
+    fn exists<K: AsKey + ?Sized>(&mut self, key: &K) -> Result<bool, QuickKvError>;
+    /// Check if several keys exist in the database.
+    ///
+    /// Returns one `bool` per key, in the same order as `keys`. All keys are checked under a
+    /// single lock, which is cheaper than calling `exists` for each one.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "exists_many.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let found = client.exists_many(&["user_1", "user_2"]).unwrap();
+    /// ```
+    fn exists_many<K: AsKey>(&mut self, keys: &[K]) -> Result<Vec<bool>, QuickKvError>;
+    /// Get all keys in the database.
+    ///
+    /// Returns `None` if there are no keys in the database or a `Vec<String>` keys.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "keys.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let all_keys = client.keys().unwrap();
+    /// ```
+    fn keys(&mut self) -> Result<Option<Vec<String>>, QuickKvError>;
+    /// Get all values in the database.
+    ///
+    /// Returns `None` if there are no values in the database or a `Vec<T>` values.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "values.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let all_values = client.values().unwrap();
+    /// ```
+    fn values(&mut self) -> Result<Option<Vec<T>>, QuickKvError>;
+    /// Get every key in the database along with its value.
+    ///
+    /// The pairs are read under one lock, so unlike calling `keys` and then `values` they always
+    /// match up. Returns an empty `Vec` if there are no keys in the database.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "entries.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// for (key, value) in client.entries().unwrap() {
+    ///     println!("{} = {}", key, value);
+    /// }
+    /// ```
+    fn entries(&mut self) -> Result<Vec<(String, T)>, QuickKvError>;
+    /// Get a copy of every key and value in the database as of one point in time.
+    ///
+    /// The copy is taken under one lock, so it never shows half of a `transaction` or of a
+    /// concurrent `set_many`, which calling `get` key by key can.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "snapshot.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.set("user_1", "jamal".to_string()).unwrap();
+    ///
+    /// let snapshot = client.snapshot().unwrap();
+    ///
+    /// assert_eq!(snapshot.get("user_1"), Some(&"jamal".to_string()));
+    /// ```
+    fn snapshot(&mut self) -> Result<HashMap<String, T>, QuickKvError>;
+    /// Get all keys in the database in the order they were first inserted.
+    ///
+    /// Updating an existing key does not change its position.
+    ///
+    /// Returns `None` if there are no keys in the database or a `Vec<String>` keys.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "keys_ordered.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let ordered_keys = client.keys_ordered().unwrap();
+    /// ```
+    fn keys_ordered(&mut self) -> Result<Option<Vec<String>>, QuickKvError>;
+    /// Get all keys that start with `prefix`.
+    ///
+    /// Useful for namespaced keys like `user:1` and `user:2`. Unlike `keys`, an empty `Vec`
+    /// is returned when nothing matches.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "keys_with_prefix.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let user_keys = client.keys_with_prefix("user:").unwrap();
+    /// ```
+    fn keys_with_prefix<K: AsKey + ?Sized>(&mut self, prefix: &K) -> Result<Vec<String>, QuickKvError>;
+    /// Get all entries whose key falls between `start` (inclusive) and `end` (exclusive),
+    /// sorted by key.
+    ///
+    /// Keys are compared lexically, so `"user:10"` sorts before `"user:2"`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "range.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let users = client.range("user:", "user;").unwrap();
+    /// ```
+    fn range<A: AsKey + ?Sized, B: AsKey + ?Sized>(&mut self, start: &A, end: &B) -> Result<Vec<(String, T)>, QuickKvError>;
+    /// Index every value by what `extractor` returns for it, so `query_index` can find values
+    /// by a field without scanning the database.
+    ///
+    /// The index is kept up to date by every write after it is created. Creating an index with
+    /// a name already in use replaces it. Indexes only live in memory, so they have to be
+    /// created again each time the database is opened.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct User
+    /// {
+    ///     name: String,
+    ///     age: u8,
+    /// };
+    ///
+    /// let mut client = QuickClient::<User>::new(ClientConfig::new(
+    ///     "create_index.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.create_index("age", |user: &User| user.age);
+    /// ```
+    fn create_index<K>(&mut self, name: &str, extractor: fn(&T) -> K)
+    where
+        K: Ord + Clone + Send + Sync + 'static;
+    /// Get every value whose index key in the index `name` equals `key`, ordered by their keys.
+    ///
+    /// Fails with `QuickKvError::IndexNotFound` if no index called `name` was created with
+    /// `create_index`, or it was created for another key type.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct User
+    /// {
+    ///     name: String,
+    ///     age: u8,
+    /// };
+    ///
+    /// let mut client = QuickClient::<User>::new(ClientConfig::new(
+    ///     "query_index.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.create_index("age", |user: &User| user.age);
+    ///
+    /// let user = User {
+    ///     name: "jamal".to_string(),
+    ///     age: 30,
+    /// };
+    ///
+    /// client.set("user_1", user).unwrap();
+    ///
+    /// let thirty = client.query_index("age", &30u8).unwrap();
+    /// ```
+    fn query_index<K>(&mut self, name: &str, key: &K) -> Result<Vec<T>, QuickKvError>
+    where
+        K: Ord + Clone + Send + Sync + 'static;
+    /// Get all values in the database in the order their keys were first inserted.
+    ///
+    /// Returns `None` if there are no values in the database or a `Vec<T>` values.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "values_ordered.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let ordered_values = client.values_ordered().unwrap();
+    /// ```
+    fn values_ordered(&mut self) -> Result<Option<Vec<T>>, QuickKvError>;
+    /// Call `f` with every live entry in the database.
+    ///
+    /// Unlike `keys` and `values`, nothing is cloned, which makes this the cheaper way to scan
+    /// or export a large database. Entries are visited in no particular order and the database
+    /// is locked until `f` has seen every entry.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<u64>::new(ClientConfig::new(
+    ///     "for_each.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let mut total = 0;
+    ///
+    /// client.for_each(|_key, value| total += value).unwrap();
+    /// ```
+    fn for_each<F>(&mut self, f: F) -> Result<(), QuickKvError>
+    where
+        F: FnMut(&str, &T);
+    /// Get every live entry whose value `pred` returns `true` for.
+    ///
+    /// The entries are checked under one lock and only the matches are cloned, which is cheaper
+    /// than filtering the result of `entries` or `values`. They come back in no particular order.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<u64>::new(ClientConfig::new(
+    ///     "filter.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.set("score_1", 50).unwrap();
+    /// client.set("score_2", 90).unwrap();
+    ///
+    /// let high_scores = client.filter(|score| *score > 75).unwrap();
+    /// ```
+    fn filter<F>(&mut self, pred: F) -> Result<Vec<(String, T)>, QuickKvError>
+    where
+        F: Fn(&T) -> bool;
+    /// Write a backup of every live entry to `w`.
+    ///
+    /// The backup is independent of the database file, so it can be sent anywhere a `Write`
+    /// goes and restored later with `import`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "export.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let mut backup = Vec::new();
+    ///
+    /// client.export(&mut backup).unwrap();
+    /// ```
+    fn export<W: Write>(&mut self, w: &mut W) -> Result<(), QuickKvError>;
+    /// Restore a backup written by `export`, merging it into the database.
+    ///
+    /// Existing keys are overwritten by the backup. Returns the number of entries imported.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "import.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let mut backup = Vec::new();
+    /// client.export(&mut backup).unwrap();
+    ///
+    /// let imported = client.import(&mut backup.as_slice()).unwrap();
+    /// ```
+    fn import<R: Read>(&mut self, r: &mut R) -> Result<usize, QuickKvError>;
+    /// Get the entries that will expire before `when`, ordered by when they expire.
+    ///
+    /// Entries without a ttl and entries that have already expired are left out. This is
+    /// useful for refreshing values before they disappear.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "entries_expiring_before.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let soon = Utc::now() + chrono::Duration::seconds(30);
+    ///
+    /// let expiring = client.entries_expiring_before(soon).unwrap();
+    /// ```
+    fn entries_expiring_before(&mut self, when: DateTime<Utc>) -> Result<Vec<(String, T)>, QuickKvError>;
+    /// Get the number of keys in the database.
+    ///
+    /// Returns `0` if there are no keys in the database or the number of keys in the database.
+    /// Expired keys are not counted, even before the background task has removed them.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "len.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let num_keys = client.len().unwrap();
+    /// ```
+    fn len(&mut self) -> Result<usize, QuickKvError>;
+    /// Clears all keys and values from the database.
+    ///
+    /// The file is truncated to zero bytes, so nothing that was purged comes back when it is
+    /// opened again. Namespaces sharing the file keep their records. `clear` does the same.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "purge.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.purge().unwrap();
+    /// ```
+    fn purge(&mut self) -> Result<(), QuickKvError>;
+    /// Clears all keys and values from the database, the same as `purge`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "clear.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.clear().unwrap();
+    /// ```
+    fn clear(&mut self) -> Result<(), QuickKvError>;
+    /// Get multiple values associated with multiple keys.
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "get_many.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let values = client.get_many(&["user_1", "user_2"]).unwrap();
+    /// ```
+    fn get_many<K: AsKey>(&mut self, keys: &[K]) -> Result<Option<Vec<T>>, QuickKvError>;
+    /// Get the values of multiple keys, paired with the key they belong to.
+    ///
+    /// Unlike `get_many`, missing keys are kept as `None`, so the result always has one
+    /// pair per key in the same order as `keys`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "get_many_pairs.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// for (key, value) in client.get_many_pairs(&["user_1", "user_2"]).unwrap() {
+    ///     // `value` is `None` if `key` does not exist
+    /// }
+    /// ```
+    fn get_many_pairs<K: AsKey>(&mut self, keys: &[K]) -> Result<Vec<(String, Option<T>)>, QuickKvError>;
+    /// Set multiple values associated with multiple keys.
+    ///
+    /// Every entry is written to the database file in a single write.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "set_many.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client
+    ///     .set_many(
+    ///         &["user_1", "user_2"],
+    ///         &[Schema { id: 10 }, Schema { id: 20 }],
+    ///     )
+    ///     .unwrap();
+    /// ```
+    fn set_many<K: AsKey>(&mut self, keys: &[K], values: &[T]) -> Result<(), QuickKvError>;
+    /// Delete multiple values associated with multiple keys.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new(
+    ///     "delete_many.qkv".to_string(),
     ///     true.into(),
     ///     LevelFilter::Debug.into(),
     /// ));
     ///
     /// client.delete_many(&["user_1", "user_2"]).unwrap();
     /// ```
-    fn delete_many(&mut self, keys: &[&str]) -> anyhow::Result<()>;
+    fn delete_many<K: AsKey>(&mut self, keys: &[K]) -> Result<(), QuickKvError>;
+    /// Get a summary of how long disk writes have taken.
+    ///
+    /// Tracking is off by default, enable it with `track_write_latency` in the configuration.
+    /// Otherwise every field is zero.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    /// struct Schema
+    /// {
+    ///     id: u64,
+    /// };
+    ///
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig {
+    ///     track_write_latency: true.into(),
+    ///     ..ClientConfig::new(
+    ///         "write_latency_stats.qkv".to_string(),
+    ///         true.into(),
+    ///         LevelFilter::Debug.into(),
+    ///     )
+    /// });
+    ///
+    /// client.set("user_1", Schema { id: 10 }).unwrap();
+    ///
+    /// let stats = client.write_latency_stats();
+    /// println!("p99 write latency: {:?}", stats.p99);
+    /// ```
+    fn write_latency_stats(&self) -> LatencyStats;
+    /// Get how long `get`, `set`, `update` and `delete` have taken since the client was created.
+    ///
+    /// Only available with the `metrics` feature, so clients that don't need it don't pay for
+    /// the timing.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "timings.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.set("user_1", "alice".to_string()).unwrap();
+    ///
+    /// let timings = client.timings();
+    /// println!("{} sets, p99 {:?}", timings.set.count, timings.set.p99);
+    /// ```
+    #[cfg(feature = "metrics")]
+    fn timings(&self) -> OpTimings;
+    /// Get the number of entries in the database and how much of the file they take up.
+    ///
+    /// A high `stale_record_ratio` means the file is mostly records that were overwritten or
+    /// deleted since, a good time to call `compact`.
+    ///
+    /// Every entry is encoded again, and encrypted if encryption is on, to estimate the live
+    /// bytes. Writers wait for the whole pass, so check this now and then rather than on every write.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "stats.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.set("user_1", "jamal".to_string()).unwrap();
+    ///
+    /// if client.stats().unwrap().stale_record_ratio > 0.5 {
+    ///     client.compact().unwrap();
+    /// }
+    /// ```
+    fn stats(&mut self) -> Result<DbStats, QuickKvError>;
+    /// Flush the database and stop its background task when the process receives SIGINT or
+    /// SIGTERM, then exit.
+    ///
+    /// The process exits with code 130 for either signal, not 143 for SIGTERM, because the
+    /// handler isn't told which one arrived. Only one handler can be installed per process,
+    /// calling this twice returns an error.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// use quick_kv::prelude::*;
+    ///
+    /// let client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "install_shutdown_handler.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// client.install_shutdown_handler().unwrap();
+    /// ```
+    #[cfg(feature = "signals")]
+    fn install_shutdown_handler(&self) -> Result<(), QuickKvError>;
     /// Update multiple values associated with multiple keys.
     ///
+    /// Each key follows the same rules as `update`. Returns how many keys were updated or inserted.
+    ///
     /// # Examples
     /// ```rust
     /// use quick_kv::prelude::*;
@@ -411,8 +1873,8 @@ where
     ///  id: u64,
     /// };
     ///
-    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new("db.qkv".to_string(), true.into(), LevelFilter::Debug.into()));
+    /// let mut client = QuickClient::<Schema>::new(ClientConfig::new("update_many.qkv".to_string(), true.into(), LevelFilter::Debug.into()));
     ///
     /// client.update_many(&["user_1", "user_2"], &[Schema { id: 10 }, Schema { id: 20 }], true.into()).unwrap();
-    fn update_many(&mut self, keys: &[&str], values: &[T], upsert: Option<bool>) -> anyhow::Result<()>;
+    fn update_many<K: AsKey>(&mut self, keys: &[K], values: &[T], upsert: Option<bool>) -> Result<usize, QuickKvError>;
 }
@@ -0,0 +1,172 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::clients::normal::QuickClient;
+use crate::clients::BaseClient;
+use crate::db::NAMESPACE_SEPARATOR;
+use crate::error::QuickKvError;
+
+/// A view of one namespace of a `QuickClient`, returned by `QuickClient::namespace`.
+///
+/// Every key passed in is stored as `name + '\0' + key`, and the prefix is stripped again from
+/// the keys handed back, so datasets in different namespaces can share a file without
+/// colliding. The keys are the same ones a client configured with `namespace: Some(name)` uses.
+#[derive(Debug)]
+pub struct NamespacedClient<'a, T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
+{
+    client: &'a mut QuickClient<T>,
+    prefix: String,
+}
+
+impl<'a, T> NamespacedClient<'a, T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
+{
+    pub(crate) fn new(client: &'a mut QuickClient<T>, name: &str) -> Self
+    {
+        Self {
+            client,
+            prefix: format!("{}{}", name, NAMESPACE_SEPARATOR),
+        }
+    }
+
+    fn key(&self, key: &str) -> String
+    {
+        format!("{}{}", self.prefix, key)
+    }
+
+    /// Get the value associated with a key in this namespace.
+    pub fn get(&mut self, key: &str) -> Result<Option<T>, QuickKvError>
+    {
+        let key = self.key(key);
+        self.client.get(&key)
+    }
+
+    /// Set the value associated with a key in this namespace.
+    pub fn set(&mut self, key: &str, value: T) -> Result<(), QuickKvError>
+    {
+        let key = self.key(key);
+        self.client.set(&key, value)
+    }
+
+    /// Set the value associated with a key in this namespace, expiring it after `ttl`.
+    pub fn set_with_ttl(&mut self, key: &str, value: T, ttl: Duration) -> Result<(), QuickKvError>
+    {
+        let key = self.key(key);
+        self.client.set_with_ttl(&key, value, ttl)
+    }
+
+    /// Update the value associated with a key in this namespace, see `BaseClient::update`.
+    pub fn update(&mut self, key: &str, value: T, upsert: Option<bool>) -> Result<bool, QuickKvError>
+    {
+        let key = self.key(key);
+        self.client.update(&key, value, upsert)
+    }
+
+    /// Delete a key in this namespace.
+    pub fn delete(&mut self, key: &str) -> Result<(), QuickKvError>
+    {
+        let key = self.key(key);
+        self.client.delete(&key)
+    }
+
+    /// Check if a key exists in this namespace.
+    pub fn exists(&mut self, key: &str) -> Result<bool, QuickKvError>
+    {
+        let key = self.key(key);
+        self.client.exists(&key)
+    }
+
+    /// Get every key in this namespace, without the namespace prefix.
+    pub fn keys(&mut self) -> Result<Vec<String>, QuickKvError>
+    {
+        let keys = self.client.keys_with_prefix(&self.prefix)?;
+
+        Ok(keys.into_iter().map(|key| self.strip(key)).collect())
+    }
+
+    /// Get every key from `start` up to but not including `end` in this namespace, along with
+    /// its value, in key order.
+    pub fn range(&mut self, start: &str, end: &str) -> Result<Vec<(String, T)>, QuickKvError>
+    {
+        let (start, end) = (self.key(start), self.key(end));
+        let entries = self.client.range(&start, &end)?;
+
+        Ok(entries.into_iter().map(|(key, value)| (self.strip(key), value)).collect())
+    }
+
+    /// Get the number of keys in this namespace.
+    pub fn len(&mut self) -> Result<usize, QuickKvError>
+    {
+        Ok(self.client.keys_with_prefix(&self.prefix)?.len())
+    }
+
+    /// Check if this namespace holds no keys.
+    pub fn is_empty(&mut self) -> Result<bool, QuickKvError>
+    {
+        Ok(self.len()? == 0)
+    }
+
+    fn strip(&self, key: String) -> String
+    {
+        match key.strip_prefix(self.prefix.as_str()) {
+            Some(key) => key.to_string(),
+            None => key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use tempfile::tempdir;
+
+    use crate::clients::normal::QuickClient;
+    use crate::clients::{BaseClient, ClientConfig};
+
+    #[test]
+    fn test_namespaced_client()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.namespace("users").set("1", "jamal".to_string()).unwrap();
+        client.namespace("users").set("2", "bob".to_string()).unwrap();
+        client.namespace("posts").set("1", "hello world".to_string()).unwrap();
+
+        assert_eq!(client.namespace("users").get("1").unwrap(), Some("jamal".to_string()));
+        assert_eq!(client.namespace("posts").get("1").unwrap(), Some("hello world".to_string()));
+        assert_eq!(client.get("1").unwrap(), None);
+
+        let mut users = client.namespace("users").keys().unwrap();
+        users.sort();
+
+        assert_eq!(users, vec!["1".to_string(), "2".to_string()]);
+        assert_eq!(client.namespace("posts").keys().unwrap(), vec!["1".to_string()]);
+
+        assert_eq!(
+            client.namespace("users").range("1", "2").unwrap(),
+            vec![("1".to_string(), "jamal".to_string())]
+        );
+
+        client.namespace("users").delete("1").unwrap();
+
+        assert_eq!(client.namespace("users").len().unwrap(), 1);
+        assert!(client.namespace("posts").exists("1").unwrap());
+    }
+}
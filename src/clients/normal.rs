@@ -1,13 +1,27 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::io::{Read, Write};
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::clients::namespaced::NamespacedClient;
 use crate::clients::{BaseClient, ClientConfig};
+use crate::db::batcher::Batch;
 use crate::db::config::DatabaseConfiguration;
+#[cfg(feature = "metrics")]
+use crate::db::metrics::OpTimings;
+use crate::db::metrics::{DbStats, LatencyStats};
+use crate::db::pipeline::Pipeline;
+use crate::db::recovery::LoadReport;
 use crate::db::runtime::{RunTime, RuntTimeType};
+use crate::db::watch::ChangeListener;
 use crate::db::Database;
+use crate::error::{read_lock, QuickKvError};
+use crate::types::{AsKey, EntryMeta, KeyStatus};
 
 #[derive(Debug, Clone)]
 pub struct QuickClient<T>
@@ -17,155 +31,160 @@ where
     db: Database<T>,
 }
 
-impl<T> BaseClient<T> for QuickClient<T>
+impl_base_client!(QuickClient, database_config);
+
+impl<T> QuickClient<T>
 where
     T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
 {
-    fn new(config: ClientConfig) -> Self
-    {
-        let _config = DatabaseConfiguration::new(
-            config.path,
-            Some(RunTime::new(RuntTimeType::Disk)),
-            config.log,
-            config.log_level,
-            config.default_ttl,
-        )
-        .unwrap();
-
-        let db = Database::new(_config).unwrap();
-
-        Self { db }
-    }
-
-    fn get(&mut self, key: &str) -> anyhow::Result<Option<T>>
-    {
-        match self.db.get(key.to_string()) {
-            Ok(value) => Ok(value),
-            Err(e) => Err(e),
-        }
-    }
-
-    fn set(&mut self, key: &str, value: T) -> anyhow::Result<()>
-    {
-        match self.db.set(key, value, None) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
-    }
-
-    fn update(&mut self, key: &str, value: T, upsert: Option<bool>) -> anyhow::Result<()>
-    {
-        match self.db.update(key, value, None, upsert) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
-    }
-
-    fn delete(&mut self, key: &str) -> anyhow::Result<()>
-    {
-        match self.db.delete(key) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
-    }
-
-    fn exists(&mut self, key: &str) -> anyhow::Result<bool>
-    {
-        match self.db.state.lock().unwrap().entries.contains_key(key) {
-            true => Ok(true),
-            false => Ok(false),
-        }
-    }
-
-    fn keys(&mut self) -> anyhow::Result<Option<Vec<String>>>
-    {
-        let keys = self.db.state.lock().unwrap().entries.keys().cloned().collect::<Vec<String>>();
-        if !keys.is_empty() {
-            Ok(Some(keys))
-        } else {
-            Ok(None)
-        }
-    }
-
-    fn values(&mut self) -> anyhow::Result<Option<Vec<T>>>
-    {
-        let values = self.db.state.lock().unwrap().entries.values().cloned().collect::<Vec<_>>();
-
-        if !values.is_empty() {
-            let v = values.into_iter().map(|entry| entry.data).collect::<Vec<T>>();
-            Ok(Some(v))
-        } else {
-            Ok(None)
-        }
-    }
-
-    fn len(&mut self) -> anyhow::Result<usize>
+    /// Open an existing database without write access.
+    ///
+    /// The file is opened with read permission only and loaded like `new` would, every method
+    /// that writes fails with `QuickKvError::ReadOnly`. Several processes can inspect a
+    /// database this way while one other process owns it. Entries written after the file is
+    /// opened aren't picked up.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let config = ClientConfig::new(
+    ///     "open_read_only.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// );
+    ///
+    /// QuickClient::<String>::new(config.clone())
+    ///     .set("user_1", "jamal".to_string())
+    ///     .unwrap();
+    ///
+    /// let mut reader = QuickClient::<String>::open_read_only(config).unwrap();
+    ///
+    /// assert!(reader.get("user_1").unwrap().is_some());
+    /// assert!(reader.set("user_2", "bob".to_string()).is_err());
+    /// ```
+    pub fn open_read_only(config: ClientConfig) -> anyhow::Result<Self>
     {
-        match self.db.state.lock().unwrap().entries.len() {
-            len if len > 0 => Ok(len),
-            _ => Ok(0),
-        }
-    }
+        let config = DatabaseConfiguration {
+            read_only: Some(true),
+            ..database_config(config)?
+        };
 
-    fn purge(&mut self) -> anyhow::Result<()>
-    {
-        match self.db.purge() {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
+        Ok(Self {
+            db: Database::new(config)?,
+        })
     }
 
-    fn get_many(&mut self, keys: &[&str]) -> anyhow::Result<Option<Vec<T>>>
+    /// Create a client and write every entry from `iter` into it in a single pass.
+    ///
+    /// All entries go out in one write followed by one sync, instead of one of each per `set`,
+    /// which makes this the fastest way to seed a fresh database. Later entries for the same
+    /// key overwrite earlier ones.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let config = ClientConfig::new(
+    ///     "from_iter.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// );
+    ///
+    /// let users = (0..100).map(|i| (format!("user_{}", i), i));
+    ///
+    /// let mut client = QuickClient::<u64>::from_iter(config, users).unwrap();
+    ///
+    /// assert_eq!(client.get("user_42").unwrap(), Some(42));
+    /// ```
+    pub fn from_iter<I>(config: ClientConfig, iter: I) -> Result<Self, QuickKvError>
+    where
+        I: IntoIterator<Item = (String, T)>,
     {
-        let mut values = Vec::new();
-
-        for key in keys {
-            if let Ok(Some(v)) = self.db.get(key.to_string()) {
-                values.push(v);
-            }
-        }
+        let mut db = Database::new(database_config(config)?)?;
 
-        if !values.is_empty() {
-            Ok(Some(values))
-        } else {
-            Ok(None)
-        }
-    }
-
-    fn set_many(&mut self, keys: &[&str], values: &[T]) -> anyhow::Result<()>
-    {
-        for (key, value) in keys.iter().zip(values.iter()) {
-            self.db.set(key, value.clone(), None)?;
-        }
+        db.set_batch(iter.into_iter().map(|(key, value)| (key, value, None)).collect())?;
 
-        Ok(())
+        Ok(Self { db })
     }
 
-    fn delete_many(&mut self, keys: &[&str]) -> anyhow::Result<()>
+    /// Get a handle that keeps every key it touches inside the namespace `name`.
+    ///
+    /// The same key can hold different values in different namespaces, and `keys` or `range`
+    /// on the handle only see its own namespace.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<String>::new(ClientConfig::new(
+    ///     "namespace.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let mut users = client.namespace("users");
+    /// users.set("1", "jamal".to_string()).unwrap();
+    ///
+    /// assert_eq!(users.get("1").unwrap(), Some("jamal".to_string()));
+    /// assert_eq!(client.namespace("posts").get("1").unwrap(), None);
+    /// ```
+    pub fn namespace(&mut self, name: &str) -> NamespacedClient<'_, T>
     {
-        for key in keys {
-            self.db.delete(key)?;
-        }
-
-        Ok(())
+        NamespacedClient::new(self, name)
     }
+}
 
-    fn update_many(&mut self, keys: &[&str], values: &[T], upsert: Option<bool>) -> anyhow::Result<()>
-    {
-        for (key, value) in keys.iter().zip(values.iter()) {
-            self.db.update(key, value.clone(), None, upsert)?;
-        }
-
-        Ok(())
-    }
+/// Turns the client's settings into the database's, backed by a file.
+fn database_config(config: ClientConfig) -> anyhow::Result<DatabaseConfiguration>
+{
+    Ok(DatabaseConfiguration {
+        ttl_interval: config.ttl_interval,
+        track_write_latency: config.track_write_latency,
+        format: config.format,
+        compression: config.compression,
+        namespace: config.namespace,
+        compaction_threshold: config.compaction_threshold,
+        max_entries: config.max_entries,
+        eviction: config.eviction,
+        sync_on_write: config.sync_on_write,
+        encryption: config.encryption,
+        on_corruption: config.on_corruption,
+        max_key_len: config.max_key_len,
+        key_validator: config.key_validator,
+        io_buffer_size: config.io_buffer_size,
+        initial_capacity: config.initial_capacity,
+        log_format: config.log_format,
+        flush_interval: config.flush_interval,
+        ..DatabaseConfiguration::new(
+            config.path,
+            Some(RunTime::new(RuntTimeType::Disk)),
+            config.log,
+            config.log_level,
+            config.default_ttl,
+        )?
+    })
 }
 
+impl_numeric_client!(QuickClient: i32, i64, u64);
+impl_list_client!(QuickClient);
+
 #[cfg(test)]
 mod tests
 {
+    use std::borrow::Cow;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
     use tempfile::tempdir;
 
     use super::*;
+    use crate::clients::{ListClient, NumericClient};
+    use crate::db::codec::Compression;
+    use crate::db::header::{HEADER_LEN, MAGIC};
+    use crate::db::pipeline::PipelineResult;
+    use crate::db::watch::ChangeKind;
     use crate::types::HashSet;
 
     #[test]
@@ -179,6 +198,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickClient::<String>::new(config);
 
@@ -202,6 +222,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickClient::<String>::new(config);
 
@@ -226,6 +247,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickClient::<String>::new(config);
 
@@ -252,6 +274,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickClient::<String>::new(config);
 
@@ -279,6 +302,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickClient::<String>::new(config);
 
@@ -307,6 +331,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickClient::<String>::new(config);
 
@@ -324,6 +349,40 @@ mod tests
         assert_eq!(retrieved_values, expected_values);
     }
 
+    #[test]
+    fn test_quick_client_entries()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        assert!(client.entries().unwrap().is_empty());
+
+        client.set("key1", "value1".to_string()).unwrap();
+        client.set("key2", "value2".to_string()).unwrap();
+        client.set("key3", "value3".to_string()).unwrap();
+
+        let mut entries = client.entries().unwrap();
+        entries.sort();
+
+        assert_eq!(
+            entries,
+            vec![
+                ("key1".to_string(), "value1".to_string()),
+                ("key2".to_string(), "value2".to_string()),
+                ("key3".to_string(), "value3".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn test_quick_client_len()
     {
@@ -335,6 +394,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickClient::<String>::new(config);
 
@@ -361,6 +421,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickClient::<String>::new(config);
 
@@ -373,6 +434,73 @@ mod tests
         assert_eq!(client.len().unwrap(), 0);
     }
 
+    #[test]
+    fn test_quick_client_purge_empties_file()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file.clone()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        for i in 0..10 {
+            client.set(&format!("key{}", i), "value".to_string()).unwrap();
+        }
+
+        client.purge().unwrap();
+
+        assert_eq!(std::fs::metadata(&tmp_file).unwrap().len(), 0);
+
+        client.set("key0", "value".to_string()).unwrap();
+        client.clear().unwrap();
+
+        assert_eq!(std::fs::metadata(&tmp_file).unwrap().len(), 0);
+        assert_eq!(client.len().unwrap(), 0);
+
+        // The empty file opens as a fresh database and gets its header back on the next write.
+        client.set("key1", "value".to_string()).unwrap();
+        drop(client);
+
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert_eq!(reopened.get("key1").unwrap(), Some("value".to_string()));
+        assert!(std::fs::read(&tmp_file).unwrap().starts_with(MAGIC));
+    }
+
+    #[test]
+    fn test_quick_client_purge_persists()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        for i in 0..10 {
+            client.set(&format!("key{}", i), "value".to_string()).unwrap();
+        }
+
+        client.purge().unwrap();
+        drop(client);
+
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert_eq!(reopened.len().unwrap(), 0);
+        assert_eq!(reopened.get("key0").unwrap(), None);
+    }
+
     #[test]
     fn test_quick_client_update_many()
     {
@@ -381,6 +509,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
 
         let mut client = QuickClient::<String>::new(config);
@@ -422,6 +551,7 @@ mod tests
             log: None,
             log_level: None,
             default_ttl: None,
+            ..Default::default()
         };
         let mut client = QuickClient::<String>::new(config);
 
@@ -440,4 +570,1874 @@ mod tests
         let remaining_keys = client.keys().unwrap().unwrap();
         assert_eq!(remaining_keys, vec!["key3"]);
     }
+
+    #[test]
+    fn test_quick_client_keys_ordered()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        let keys = vec!["key3", "key1", "key2"];
+        let values = vec!["value3", "value1", "value2"]
+            .iter()
+            .map(|&s| s.to_string())
+            .collect::<Vec<String>>();
+
+        client.set_many(&keys, &values).unwrap();
+
+        // Updating an existing key should not move it to the back.
+        client.update("key1", "new_value1".to_string(), None).unwrap();
+
+        let ordered_keys = client.keys_ordered().unwrap().unwrap();
+        assert_eq!(ordered_keys, vec!["key3", "key1", "key2"]);
+
+        let ordered_values = client.values_ordered().unwrap().unwrap();
+        assert_eq!(ordered_values, vec!["value3", "new_value1", "value2"]);
+    }
+
+    #[test]
+    fn test_quick_client_ttl_sweeper()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: Some(Duration::from_secs(1)),
+            ttl_interval: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("test_key", "test_value".to_string()).unwrap();
+        assert_eq!(client.keys().unwrap(), Some(vec!["test_key".to_string()]));
+
+        thread::sleep(Duration::from_secs(2));
+
+        // The background task should have removed the key without any reads in between.
+        assert_eq!(client.keys().unwrap(), None);
+    }
+
+    #[test]
+    fn test_quick_client_lazy_expiry()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        // The background task won't run during the test, so reads have to catch the expiry.
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: Some(Duration::from_millis(50)),
+            ttl_interval: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("key1", "value1".to_string()).unwrap();
+        client.set("key2", "value2".to_string()).unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+
+        // Each read path has to notice the expiry on its own.
+        assert_eq!(client.get("key1").unwrap(), None);
+        assert!(!client.exists("key2").unwrap());
+    }
+
+    #[test]
+    fn test_quick_client_increment_concurrent()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<i64>::new(config);
+
+        let handles = (0..8)
+            .map(|_| {
+                let mut client = client.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        client.increment("counter", 1).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(client.get("counter").unwrap(), Some(8000));
+        assert_eq!(client.decrement("counter", 8001).unwrap(), -1);
+    }
+
+    #[test]
+    fn test_quick_client_increment_many()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<i64>::new(config.clone());
+
+        client.set("views", 10).unwrap();
+        client.set("clicks", 5).unwrap();
+
+        let counts = client
+            .increment_many(&[("views", 1), ("new", 4), ("clicks", -2), ("views", 2)])
+            .unwrap();
+
+        assert_eq!(counts, vec![11, 4, 3, 13]);
+
+        // An overflow anywhere leaves every counter untouched.
+        assert!(client.increment_many(&[("views", 1), ("new", i64::MAX)]).is_err());
+        assert_eq!(client.get("views").unwrap(), Some(13));
+
+        drop(client);
+        let mut reopened = QuickClient::<i64>::new(config);
+
+        assert_eq!(reopened.get("views").unwrap(), Some(13));
+        assert_eq!(reopened.get("new").unwrap(), Some(4));
+        assert_eq!(reopened.get("clicks").unwrap(), Some(3));
+    }
+
+    #[test]
+    fn test_quick_client_from_iter()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+
+        let entries = (0..50_000).map(|i| (format!("key_{}", i), i));
+        let mut client = QuickClient::<u64>::from_iter(config.clone(), entries).unwrap();
+
+        assert_eq!(client.len().unwrap(), 50_000);
+        assert_eq!(client.get("key_49999").unwrap(), Some(49_999));
+
+        drop(client);
+        let mut reopened = QuickClient::<u64>::new(config.clone());
+
+        assert_eq!(reopened.len().unwrap(), 50_000);
+        drop(reopened);
+
+        // Errors come back as `QuickKvError`, like the trait methods.
+        let full = ClientConfig {
+            path: Some(tmp_dir.path().join("full.qkv").to_str().unwrap().to_string()),
+            max_entries: Some(10),
+            ..config
+        };
+        let entries = (0..20).map(|i| (format!("key_{}", i), i));
+
+        assert!(matches!(
+            QuickClient::<u64>::from_iter(full, entries),
+            Err(QuickKvError::CapacityExceeded { max_entries: 10 })
+        ));
+    }
+
+    #[test]
+    fn test_quick_client_path()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file.clone()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let client = QuickClient::<String>::new(config);
+
+        assert_eq!(client.path(), Some(tmp_file.as_str()));
+        assert!(!client.is_in_memory());
+    }
+
+    #[test]
+    fn test_quick_client_snapshot()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            sync_on_write: Some(false),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<i64>::new(config);
+
+        client.set_many(&["from", "to"], &[100, 0]).unwrap();
+
+        // Moves one unit at a time from one key to the other, the total never changes.
+        let mut writer = client.clone();
+        let handle = thread::spawn(move || {
+            for i in 1..=100 {
+                writer
+                    .transaction(|batch| {
+                        batch.set("from", 100 - i);
+                        batch.set("to", i);
+                        Ok(())
+                    })
+                    .unwrap();
+            }
+        });
+
+        for _ in 0..100 {
+            let snapshot = client.snapshot().unwrap();
+            assert_eq!(snapshot["from"] + snapshot["to"], 100);
+        }
+
+        handle.join().unwrap();
+
+        let snapshot = client.snapshot().unwrap();
+
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot["to"], 100);
+    }
+
+    #[test]
+    fn test_quick_client_write_latency_stats()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            track_write_latency: Some(true),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        assert_eq!(client.write_latency_stats().count, 0);
+
+        for i in 0..20 {
+            client.set(&format!("key{}", i), "value".to_string()).unwrap();
+        }
+
+        let stats = client.write_latency_stats();
+
+        assert_eq!(stats.count, 20);
+        assert!(stats.min <= stats.p50);
+        assert!(stats.p50 <= stats.p99);
+        assert!(stats.p99 <= stats.max);
+        assert!(stats.max > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_quick_client_stats()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        let empty = client.stats().unwrap();
+
+        for i in 0..10 {
+            client.set(&format!("key{}", i), "value".to_string()).unwrap();
+        }
+
+        let stats = client.stats().unwrap();
+
+        assert_eq!(stats.entry_count, client.len().unwrap());
+        assert_eq!(stats.expired_pending, 0);
+        assert!(stats.file_size_bytes > empty.file_size_bytes);
+        assert!(stats.live_bytes_estimate > 0);
+        assert_eq!(stats.stale_record_ratio, 0.0);
+
+        for i in 0..5 {
+            client.set(&format!("key{}", i), "updated".to_string()).unwrap();
+        }
+
+        assert!(client.stats().unwrap().stale_record_ratio > 0.0);
+    }
+
+    #[test]
+    fn test_quick_client_get_or_insert_with()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        let mut calls = 0;
+
+        let first = client
+            .get_or_insert_with("test_key", || {
+                calls += 1;
+                "first".to_string()
+            })
+            .unwrap();
+
+        let second = client
+            .get_or_insert_with("test_key", || {
+                calls += 1;
+                "second".to_string()
+            })
+            .unwrap();
+
+        assert_eq!(first, "first");
+        assert_eq!(second, "first");
+        assert_eq!(calls, 1);
+        assert_eq!(client.get("test_key").unwrap(), Some("first".to_string()));
+    }
+
+    #[test]
+    fn test_quick_client_compare_and_swap()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file.clone()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        // Insert-if-absent only succeeds while the key does not exist.
+        assert!(client.compare_and_swap("test_key", None, "first".to_string()).unwrap());
+        assert!(!client.compare_and_swap("test_key", None, "other".to_string()).unwrap());
+
+        // Matching value swaps.
+        assert!(client
+            .compare_and_swap("test_key", Some("first".to_string()), "second".to_string())
+            .unwrap());
+
+        // Stale value is rejected and the stored value is untouched.
+        assert!(!client
+            .compare_and_swap("test_key", Some("first".to_string()), "third".to_string())
+            .unwrap());
+
+        assert_eq!(client.get("test_key").unwrap(), Some("second".to_string()));
+
+        // Successful swaps are persisted like `set`.
+        drop(client);
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert_eq!(reopened.get("test_key").unwrap(), Some("second".to_string()));
+    }
+
+    #[test]
+    fn test_quick_client_gzip_round_trip()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file.clone()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            compression: Some(Compression::Gzip),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        for i in 0..500 {
+            client
+                .set(&format!("key_{}", i), format!("value {} {}", i, "lorem ipsum ".repeat(20)))
+                .unwrap();
+        }
+
+        // Past the header, the file holds a single gzip stream.
+        let bytes = std::fs::read(&tmp_file).unwrap();
+        assert_eq!(&bytes[HEADER_LEN..HEADER_LEN + 2], &[0x1f, 0x8b]);
+
+        drop(client);
+        let mut reopened = QuickClient::<String>::new(config);
+
+        for i in 0..500 {
+            assert_eq!(
+                reopened.get(&format!("key_{}", i)).unwrap(),
+                Some(format!("value {} {}", i, "lorem ipsum ".repeat(20)))
+            );
+        }
+    }
+
+    #[test]
+    fn test_quick_client_keys_with_prefix()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("user:1", "alice".to_string()).unwrap();
+        client.set("user:2", "bob".to_string()).unwrap();
+        client.set("post:1", "hello".to_string()).unwrap();
+
+        let mut keys = client.keys_with_prefix("user:").unwrap();
+        keys.sort();
+
+        assert_eq!(keys, vec!["user:1".to_string(), "user:2".to_string()]);
+        assert!(client.keys_with_prefix("comment:").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_quick_client_merge()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        // Insert
+        let merged = client.merge("greeting", |current| {
+            assert_eq!(current, None);
+            Some("hello".to_string())
+        });
+        assert_eq!(merged.unwrap(), Some("hello".to_string()));
+
+        // Update
+        let merged = client.merge("greeting", |current| current.map(|value| value + " world"));
+        assert_eq!(merged.unwrap(), Some("hello world".to_string()));
+        assert_eq!(client.get("greeting").unwrap(), Some("hello world".to_string()));
+
+        // Delete
+        client.set("other", "kept".to_string()).unwrap();
+
+        assert_eq!(client.merge("greeting", |_| None).unwrap(), None);
+        assert!(!client.exists("greeting").unwrap());
+
+        drop(client);
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert_eq!(reopened.get("greeting").unwrap(), None);
+        assert_eq!(reopened.get("other").unwrap(), Some("kept".to_string()));
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, serde::Deserialize)]
+    struct User
+    {
+        name: String,
+        age: u8,
+    }
+
+    #[test]
+    fn test_quick_client_secondary_index()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<User>::new(config);
+
+        let user = |name: &str, age| User {
+            name: name.to_string(),
+            age,
+        };
+
+        // Entries stored before the index is created are indexed too.
+        client.set("user_1", user("jamal", 30)).unwrap();
+        client.create_index("age", |user: &User| user.age);
+
+        client.set("user_2", user("bob", 30)).unwrap();
+        client.set("user_3", user("alice", 25)).unwrap();
+
+        assert_eq!(
+            client.query_index("age", &30u8).unwrap(),
+            vec![user("jamal", 30), user("bob", 30)]
+        );
+        assert_eq!(client.query_index("age", &25u8).unwrap(), vec![user("alice", 25)]);
+
+        // Moves between buckets.
+        client.update("user_2", user("bob", 31), None).unwrap();
+
+        assert_eq!(client.query_index("age", &30u8).unwrap(), vec![user("jamal", 30)]);
+        assert_eq!(client.query_index("age", &31u8).unwrap(), vec![user("bob", 31)]);
+
+        // Stays in its bucket when another field changes.
+        client.set("user_1", user("jamal k", 30)).unwrap();
+
+        assert_eq!(client.query_index("age", &30u8).unwrap(), vec![user("jamal k", 30)]);
+
+        client.delete("user_3").unwrap();
+
+        assert!(client.query_index("age", &25u8).unwrap().is_empty());
+        assert!(matches!(
+            client.query_index("name", &30u8),
+            Err(QuickKvError::IndexNotFound(_))
+        ));
+        assert!(matches!(
+            client.query_index("age", &30u32),
+            Err(QuickKvError::IndexNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_quick_client_transaction()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        let result = client.transaction(|batch| {
+            batch.set("first", "one".to_string());
+            batch.set("second", "two".to_string());
+            anyhow::bail!("abort")
+        });
+
+        assert!(result.is_err());
+        assert!(!client.exists("first").unwrap());
+        assert!(!client.exists("second").unwrap());
+
+        client.set("stale", "old".to_string()).unwrap();
+
+        client
+            .transaction(|batch| {
+                batch.set("first", "one".to_string());
+                batch.set("second", "two".to_string());
+                batch.delete("stale");
+                Ok(())
+            })
+            .unwrap();
+
+        drop(client);
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert_eq!(reopened.get("first").unwrap(), Some("one".to_string()));
+        assert_eq!(reopened.get("second").unwrap(), Some("two".to_string()));
+        assert!(!reopened.exists("stale").unwrap());
+    }
+
+    #[test]
+    fn test_quick_client_ttl()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: Some(Duration::from_secs(10)),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("expiring", "value".to_string()).unwrap();
+
+        let remaining = client.ttl("expiring").unwrap().unwrap();
+
+        assert!(remaining > Duration::from_secs(9) && remaining <= Duration::from_secs(10));
+        assert_eq!(client.default_ttl(), Some(Duration::from_secs(10)));
+
+        let config = ClientConfig {
+            path: Some(tmp_dir.path().join("no_ttl.qkv").to_str().unwrap().to_string()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("forever", "value".to_string()).unwrap();
+
+        assert_eq!(client.ttl("forever").unwrap(), None);
+        assert_eq!(client.ttl("missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_quick_client_set_idempotent()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        assert!(client.set_idempotent("test_key", "first".to_string(), "req-1").unwrap());
+        assert!(!client.set_idempotent("test_key", "retry".to_string(), "req-1").unwrap());
+
+        assert_eq!(client.get("test_key").unwrap(), Some("first".to_string()));
+
+        assert!(client.set_idempotent("test_key", "second".to_string(), "req-2").unwrap());
+        assert_eq!(client.get("test_key").unwrap(), Some("second".to_string()));
+    }
+
+    #[test]
+    fn test_quick_client_set_and_get_previous()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        assert_eq!(client.set_and_get_previous("test_key", "first".to_string()).unwrap(), None);
+        assert_eq!(
+            client.set_and_get_previous("test_key", "second".to_string()).unwrap(),
+            Some("first".to_string())
+        );
+
+        assert_eq!(client.get("test_key").unwrap(), Some("second".to_string()));
+    }
+
+    #[test]
+    fn test_quick_client_set_with_ttl()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        // A long sweep interval leaves removal to the lazy check in `get`.
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ttl_interval: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client
+            .set_with_ttl("expiring", "value".to_string(), Duration::from_secs(1))
+            .unwrap();
+        client.set("forever", "value".to_string()).unwrap();
+
+        assert_eq!(client.get("expiring").unwrap(), Some("value".to_string()));
+
+        thread::sleep(Duration::from_millis(1100));
+
+        assert_eq!(client.get("expiring").unwrap(), None);
+        assert_eq!(client.get("forever").unwrap(), Some("value".to_string()));
+    }
+
+    #[test]
+    fn test_quick_client_insert_new_key()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        assert!(client.insert("user_1", "jamal".to_string()).unwrap());
+        assert_eq!(client.get("user_1").unwrap(), Some("jamal".to_string()));
+
+        // An expired key can be inserted again.
+        client
+            .set_with_expiry("expired", "old".to_string(), Utc::now() - chrono::Duration::seconds(1))
+            .unwrap();
+
+        assert!(client.insert("expired", "new".to_string()).unwrap());
+
+        drop(client);
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert_eq!(reopened.get("user_1").unwrap(), Some("jamal".to_string()));
+        assert_eq!(reopened.get("expired").unwrap(), Some("new".to_string()));
+    }
+
+    #[test]
+    fn test_quick_client_insert_existing_key()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file.clone()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("user_1", "jamal".to_string()).unwrap();
+
+        let file_len = std::fs::metadata(&tmp_file).unwrap().len();
+
+        assert!(!client.insert("user_1", "bob".to_string()).unwrap());
+        assert_eq!(client.get("user_1").unwrap(), Some("jamal".to_string()));
+        assert_eq!(std::fs::metadata(&tmp_file).unwrap().len(), file_len);
+    }
+
+    #[test]
+    fn test_quick_client_get_with_meta()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        let expires_at = Utc::now() + chrono::Duration::hours(1);
+
+        client.set_with_expiry("session", "token".to_string(), expires_at).unwrap();
+        client.set("forever", "value".to_string()).unwrap();
+
+        assert_eq!(
+            client.get_with_meta("session").unwrap(),
+            Some(("token".to_string(), Some(expires_at)))
+        );
+        assert_eq!(client.get_with_meta("forever").unwrap(), Some(("value".to_string(), None)));
+        assert_eq!(client.get_with_meta("missing").unwrap(), None);
+
+        client
+            .set_with_expiry("expired", "value".to_string(), Utc::now() - chrono::Duration::seconds(1))
+            .unwrap();
+
+        assert_eq!(client.get_with_meta("expired").unwrap(), None);
+    }
+
+    #[test]
+    fn test_quick_client_get_or_wait()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        let mut producer = client.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            producer.set("job", "done".to_string()).unwrap();
+        });
+
+        assert_eq!(
+            client.get_or_wait("job", Duration::from_secs(1)).unwrap(),
+            Some("done".to_string())
+        );
+
+        handle.join().unwrap();
+
+        assert_eq!(client.get_or_wait("missing", Duration::from_millis(50)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_quick_client_with_value()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<User>::new(config);
+
+        client
+            .set(
+                "user_1",
+                User {
+                    name: "alice".to_string(),
+                    age: 30,
+                },
+            )
+            .unwrap();
+
+        let borrowed = client.with_value("user_1", |user| user.name.clone()).unwrap();
+        let cloned = client.get("user_1").unwrap().map(|user| user.name);
+
+        assert_eq!(borrowed, Some("alice".to_string()));
+        assert_eq!(borrowed, cloned);
+        assert_eq!(client.with_value("missing", |user| user.age).unwrap(), None);
+    }
+
+    #[test]
+    fn test_quick_client_pipeline()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<u64>::new(config.clone());
+
+        let mut pipeline = client.pipeline();
+        assert!(pipeline.is_empty());
+
+        pipeline.set("first", 1).set("second", 2).get("first");
+
+        assert_eq!(pipeline.len(), 3);
+        assert!(!pipeline.is_empty());
+        assert_eq!(
+            pipeline.execute().unwrap(),
+            vec![PipelineResult::Set, PipelineResult::Set, PipelineResult::Get(Some(1))]
+        );
+
+        let mut pipeline = client.pipeline();
+        pipeline.delete("first").delete("missing").get("first").get("second");
+
+        assert_eq!(
+            pipeline.execute().unwrap(),
+            vec![
+                PipelineResult::Delete(true),
+                PipelineResult::Delete(false),
+                PipelineResult::Get(None),
+                PipelineResult::Get(Some(2)),
+            ]
+        );
+
+        drop(client);
+        let mut reopened = QuickClient::<u64>::new(config);
+
+        assert_eq!(reopened.get("first").unwrap(), None);
+        assert_eq!(reopened.get("second").unwrap(), Some(2));
+    }
+
+    #[test]
+    fn test_quick_client_path_and_cow_keys()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<u64>::new(config);
+
+        let path = PathBuf::from("assets").join("logo.png");
+        let cow: Cow<str> = Cow::Owned("user_1".to_string());
+
+        client.set(&path, 1).unwrap();
+        client.set(&cow, 2).unwrap();
+
+        let path_key = path.to_str().unwrap();
+
+        assert_eq!(client.get(path_key).unwrap(), Some(1));
+        assert_eq!(client.get("user_1").unwrap(), Some(2));
+        assert_eq!(client.get(path.as_path()).unwrap(), Some(1));
+        assert!(client.exists(&cow).unwrap());
+
+        // Methods taking several keys, prefixes or ranges accept them too.
+        let owned = vec![path_key.to_string(), "user_1".to_string()];
+
+        assert_eq!(client.exists_many(&owned).unwrap(), vec![true, true]);
+        assert_eq!(client.get_many(&owned).unwrap(), Some(vec![1, 2]));
+        assert_eq!(
+            client.keys_with_prefix(&PathBuf::from("assets")).unwrap(),
+            vec![path_key.to_string()]
+        );
+        assert_eq!(client.increment(&cow, 1).unwrap(), 3);
+        assert!(client.rename(&path, &Cow::Borrowed("renamed")).unwrap());
+        assert_eq!(client.get("renamed").unwrap(), Some(1));
+
+        client.delete_many(&[cow]).unwrap();
+        assert!(!client.exists("user_1").unwrap());
+    }
+
+    #[test]
+    fn test_quick_client_touch()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        client
+            .set_with_ttl("session", "token".to_string(), Duration::from_secs(2))
+            .unwrap();
+
+        assert!(client.ttl("session").unwrap().unwrap() <= Duration::from_secs(2));
+
+        assert!(client.touch("session", Duration::from_secs(60)).unwrap());
+        assert!(!client.touch("missing", Duration::from_secs(60)).unwrap());
+
+        assert!(client.ttl("session").unwrap().unwrap() > Duration::from_secs(50));
+        assert_eq!(client.get("session").unwrap(), Some("token".to_string()));
+
+        // Outlives the original ttl.
+        thread::sleep(Duration::from_millis(2100));
+
+        assert_eq!(client.get("session").unwrap(), Some("token".to_string()));
+
+        drop(client);
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert!(reopened.ttl("session").unwrap().unwrap() > Duration::from_secs(50));
+    }
+
+    #[test]
+    fn test_quick_client_set_with_expiry()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ttl_interval: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        let past = Utc::now() - chrono::Duration::seconds(1);
+        let future = Utc::now() + chrono::Duration::hours(1);
+
+        client.set_with_expiry("past", "value".to_string(), past).unwrap();
+        client.set_with_expiry("future", "value".to_string(), future).unwrap();
+
+        assert_eq!(client.get("past").unwrap(), None);
+        assert_eq!(client.get("future").unwrap(), Some("value".to_string()));
+        assert_eq!(client.metadata("future").unwrap().unwrap().expires_at, Some(future));
+    }
+
+    #[test]
+    fn test_quick_client_namespaces()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = |namespace: &str| ClientConfig {
+            path: Some(tmp_file.clone()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            namespace: Some(namespace.to_string()),
+            ..Default::default()
+        };
+
+        {
+            let mut users = QuickClient::<String>::new(config("users"));
+            users.set("1", "alice".to_string()).unwrap();
+        }
+
+        {
+            let mut posts = QuickClient::<String>::new(config("posts"));
+            posts.set("1", "hello".to_string()).unwrap();
+            posts.set("2", "world".to_string()).unwrap();
+
+            assert_eq!(posts.get("1").unwrap(), Some("hello".to_string()));
+            assert_eq!(posts.len().unwrap(), 2);
+
+            let mut keys = posts.keys().unwrap().unwrap();
+            keys.sort();
+            assert_eq!(keys, vec!["1".to_string(), "2".to_string()]);
+
+            posts.purge().unwrap();
+            assert_eq!(posts.len().unwrap(), 0);
+        }
+
+        let mut users = QuickClient::<String>::new(config("users"));
+        assert_eq!(users.get("1").unwrap(), Some("alice".to_string()));
+        assert_eq!(users.keys().unwrap(), Some(vec!["1".to_string()]));
+
+        // Both namespaces open at once, each only sees its own keys.
+        let mut posts = QuickClient::<String>::new(config("posts"));
+        assert_eq!(posts.get("1").unwrap(), None);
+
+        posts.set("1", "hello".to_string()).unwrap();
+        users.set("2", "bob".to_string()).unwrap();
+        posts.delete("1").unwrap();
+        posts.set("3", "again".to_string()).unwrap();
+
+        assert_eq!(users.get("1").unwrap(), Some("alice".to_string()));
+        assert_eq!(users.get("3").unwrap(), None);
+        assert_eq!(posts.get("2").unwrap(), None);
+
+        // Purging one namespace leaves the other's keys, in memory and in the file.
+        posts.purge().unwrap();
+        users.set("4", "carol".to_string()).unwrap();
+
+        let mut keys = users.keys().unwrap().unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["1".to_string(), "2".to_string(), "4".to_string()]);
+        assert_eq!(posts.len().unwrap(), 0);
+
+        posts.set("5", "last".to_string()).unwrap();
+
+        drop(users);
+        drop(posts);
+
+        let mut users = QuickClient::<String>::new(config("users"));
+        let mut keys = users.keys().unwrap().unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["1".to_string(), "2".to_string(), "4".to_string()]);
+        drop(users);
+
+        let mut posts = QuickClient::<String>::new(config("posts"));
+        assert_eq!(posts.keys().unwrap(), Some(vec!["5".to_string()]));
+        drop(posts);
+
+        // Opened once without a namespace, both are available together.
+        let mut client = QuickClient::<String>::new(ClientConfig {
+            namespace: None,
+            ..config("")
+        });
+
+        client.namespace("posts").set("1", "again".to_string()).unwrap();
+
+        assert_eq!(client.namespace("users").get("1").unwrap(), Some("alice".to_string()));
+        assert_eq!(client.namespace("posts").get("1").unwrap(), Some("again".to_string()));
+
+        drop(client);
+        let mut users = QuickClient::<String>::new(config("users"));
+        let mut keys = users.keys().unwrap().unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["1".to_string(), "2".to_string(), "4".to_string()]);
+    }
+
+    #[test]
+    fn test_quick_client_update_missing_key()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        assert!(!client.update("missing", "value".to_string(), None).unwrap());
+        assert!(client.update("missing", "value".to_string(), Some(true)).unwrap());
+        assert_eq!(client.get("missing").unwrap(), Some("value".to_string()));
+
+        assert_eq!(
+            client
+                .update_many(&["missing", "other"], &["a".to_string(), "b".to_string()], None)
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_quick_client_key_not_found()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        match client.get_required("missing") {
+            Err(QuickKvError::KeyNotFound(key)) => assert_eq!(key, "missing"),
+            other => panic!("expected KeyNotFound, got {:?}", other),
+        }
+
+        client.set("present", "value".to_string()).unwrap();
+
+        assert_eq!(client.get_required("present").unwrap(), "value");
+    }
+
+    /// A value that can never be serialized.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Deserialize)]
+    struct Unserializable;
+
+    impl Serialize for Unserializable
+    {
+        fn serialize<S>(&self, _serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            Err(serde::ser::Error::custom("refusing to serialize"))
+        }
+    }
+
+    #[test]
+    fn test_quick_client_serialize_error()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<Unserializable>::new(config);
+
+        assert!(matches!(client.set("test", Unserializable), Err(QuickKvError::Serialize(_))));
+    }
+
+    #[test]
+    fn test_quick_client_recovers_poisoned_lock()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("test", "value".to_string()).unwrap();
+
+        // Panic while holding the state lock to poison it.
+        let state = client.db.state.clone();
+        let _ = thread::spawn(move || {
+            let _guard = state.write().unwrap();
+            panic!("poisoning the state lock");
+        })
+        .join();
+
+        assert!(client.db.state.is_poisoned());
+        assert_eq!(client.get("test").unwrap(), Some("value".to_string()));
+
+        client.set("other", "value".to_string()).unwrap();
+        assert_eq!(client.len().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_quick_client_for_each()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<u64>::new(config);
+
+        for i in 0..10 {
+            client.set(&format!("key_{}", i), i).unwrap();
+        }
+
+        let mut total = 0;
+        let mut visited = 0;
+
+        client
+            .for_each(|_, value| {
+                total += value;
+                visited += 1;
+            })
+            .unwrap();
+
+        assert_eq!(visited, 10);
+        assert_eq!(total, client.values().unwrap().unwrap().iter().sum::<u64>());
+    }
+
+    #[test]
+    fn test_quick_client_initial_capacity()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            initial_capacity: Some(10_000),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<u64>::new(config.clone());
+
+        for i in 0..100 {
+            client.set(&format!("key_{}", i), i).unwrap();
+        }
+
+        client.delete("key_0").unwrap();
+
+        assert_eq!(client.len().unwrap(), 99);
+        assert_eq!(client.keys_ordered().unwrap().unwrap()[0], "key_1");
+
+        drop(client);
+        let mut reopened = QuickClient::<u64>::new(config);
+
+        assert_eq!(reopened.len().unwrap(), 99);
+        assert_eq!(reopened.get("key_99").unwrap(), Some(99));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_quick_client_timings()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<u64>::new(config);
+
+        for i in 0..5 {
+            client.set(&format!("key_{}", i), i).unwrap();
+        }
+
+        client.get("key_0").unwrap();
+
+        let timings = client.timings();
+
+        assert_eq!(timings.set.count, 5);
+        assert!(timings.set.total > Duration::ZERO);
+        assert_eq!(timings.get.count, 1);
+        assert_eq!(timings.delete.count, 0);
+    }
+
+    #[cfg(feature = "ahash")]
+    #[test]
+    fn test_quick_client_ahash()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<u64>::new(config.clone());
+
+        let keys = (0..1000).map(|i| format!("key_{}", i)).collect::<Vec<_>>();
+        let key_refs = keys.iter().map(String::as_str).collect::<Vec<_>>();
+
+        client.set_many(&key_refs, &(0..1000).collect::<Vec<_>>()).unwrap();
+        client.delete("key_0").unwrap();
+
+        drop(client);
+        let mut reopened = QuickClient::<u64>::new(config);
+
+        assert_eq!(reopened.len().unwrap(), 999);
+        assert_eq!(reopened.get("key_0").unwrap(), None);
+
+        for i in 1..1000 {
+            assert_eq!(reopened.get(&format!("key_{}", i)).unwrap(), Some(i));
+        }
+    }
+
+    #[test]
+    fn test_quick_client_filter()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ttl_interval: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<u64>::new(config);
+
+        for i in 0..10 {
+            client.set(&format!("key_{}", i), i).unwrap();
+        }
+
+        // Expired entries are left out even when their value matches.
+        client
+            .set_with_expiry("expired", 100, Utc::now() - chrono::Duration::seconds(1))
+            .unwrap();
+
+        let mut matches = client.filter(|value| *value > 6).unwrap();
+        matches.sort();
+
+        assert_eq!(
+            matches,
+            vec![("key_7".to_string(), 7), ("key_8".to_string(), 8), ("key_9".to_string(), 9)]
+        );
+        assert!(client.filter(|value| *value > 100).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_quick_client_export_import()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+
+        let config = ClientConfig {
+            path: Some(tmp_dir.path().join("source.qkv").to_str().unwrap().to_string()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut source = QuickClient::<String>::new(config);
+
+        for i in 0..10 {
+            source.set(&format!("key_{}", i), format!("value_{}", i)).unwrap();
+        }
+
+        let mut backup = Vec::new();
+        source.export(&mut backup).unwrap();
+
+        let config = ClientConfig {
+            path: Some(tmp_dir.path().join("restored.qkv").to_str().unwrap().to_string()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut restored = QuickClient::<String>::new(config.clone());
+
+        assert_eq!(restored.import(&mut backup.as_slice()).unwrap(), 10);
+
+        for i in 0..10 {
+            let key = format!("key_{}", i);
+            assert_eq!(restored.get(&key).unwrap(), source.get(&key).unwrap());
+        }
+
+        // The import was flushed to the new file.
+        drop(restored);
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert_eq!(reopened.len().unwrap(), 10);
+        assert_eq!(reopened.get("key_3").unwrap(), Some("value_3".to_string()));
+    }
+
+    #[test]
+    fn test_quick_client_len_skips_expired()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("kept", "value".to_string()).unwrap();
+        client
+            .set_with_ttl("expired_1", "value".to_string(), Duration::from_millis(50))
+            .unwrap();
+        client
+            .set_with_ttl("expired_2", "value".to_string(), Duration::from_millis(50))
+            .unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+
+        assert_eq!(client.len().unwrap(), 1);
+        assert_eq!(client.keys().unwrap(), Some(vec!["kept".to_string()]));
+        assert_eq!(client.values().unwrap(), Some(vec!["value".to_string()]));
+    }
+
+    #[test]
+    fn test_quick_client_exists_many()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("user:1", "alice".to_string()).unwrap();
+        client.set("user:3", "carol".to_string()).unwrap();
+
+        assert_eq!(
+            client.exists_many(&["user:1", "user:2", "user:3", "user:4"]).unwrap(),
+            vec![true, false, true, false]
+        );
+        assert!(client.exists_many::<&str>(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_quick_client_get_many_pairs()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("a", "first".to_string()).unwrap();
+        client.set("b", "second".to_string()).unwrap();
+
+        assert_eq!(
+            client.get_many_pairs(&["a", "missing", "b"]).unwrap(),
+            vec![
+                ("a".to_string(), Some("first".to_string())),
+                ("missing".to_string(), None),
+                ("b".to_string(), Some("second".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quick_client_pop()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        client.set("job", "work".to_string()).unwrap();
+
+        assert_eq!(client.pop("job").unwrap(), Some("work".to_string()));
+        assert_eq!(client.get("job").unwrap(), None);
+        assert_eq!(client.pop("job").unwrap(), None);
+
+        // The delete reached the file.
+        drop(client);
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert_eq!(reopened.get("job").unwrap(), None);
+    }
+
+    #[test]
+    fn test_quick_client_rename()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        client.set("old", "value".to_string()).unwrap();
+        client.set("taken", "overwritten".to_string()).unwrap();
+
+        assert!(client.rename("old", "new").unwrap());
+        assert_eq!(client.get("old").unwrap(), None);
+        assert_eq!(client.get("new").unwrap(), Some("value".to_string()));
+
+        assert!(client.rename("new", "taken").unwrap());
+        assert_eq!(client.get("taken").unwrap(), Some("value".to_string()));
+        assert_eq!(client.len().unwrap(), 1);
+
+        drop(client);
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert_eq!(reopened.keys().unwrap(), Some(vec!["taken".to_string()]));
+    }
+
+    #[test]
+    fn test_quick_client_rename_missing_key()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        assert!(!client.rename("missing", "new").unwrap());
+        assert_eq!(client.get("new").unwrap(), None);
+    }
+
+    #[test]
+    fn test_quick_client_rename_keeps_ttl()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client
+            .set_with_ttl("old", "value".to_string(), Duration::from_secs(60))
+            .unwrap();
+        let before = client.ttl("old").unwrap().unwrap();
+
+        assert!(client.rename("old", "new").unwrap());
+
+        let after = client.ttl("new").unwrap().unwrap();
+
+        assert!(after <= before);
+        assert!(after > Duration::from_secs(55));
+    }
+
+    #[test]
+    fn test_quick_client_concurrent_readers()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<u64>::new(config);
+
+        for i in 0..100 {
+            client.set(&format!("key_{}", i), i).unwrap();
+        }
+
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                let mut client = client.clone();
+
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        for i in 0..100 {
+                            assert_eq!(client.get(&format!("key_{}", i)).unwrap(), Some(i));
+                        }
+
+                        assert_eq!(client.len().unwrap(), 100);
+                        assert!(client.exists("key_0").unwrap());
+                    }
+                })
+            })
+            .collect();
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_quick_client_push_to_vec()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<Vec<String>>::new(config);
+
+        assert_eq!(client.push_to_vec("tags", "rust".to_string()).unwrap(), 1);
+        assert_eq!(client.push_to_vec("tags", "database".to_string()).unwrap(), 2);
+        assert_eq!(client.push_to_vec("tags", "cache".to_string()).unwrap(), 3);
+
+        assert_eq!(
+            client.get("tags").unwrap(),
+            Some(vec!["rust".to_string(), "database".to_string(), "cache".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_quick_client_default_ttl_applies_to_set()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        let before = Utc::now();
+        client.set("test_key", "test_value".to_string()).unwrap();
+        let after = Utc::now();
+
+        let expires_at = client.db.state.read().unwrap().entries["test_key"].expires_at.unwrap();
+
+        assert!(expires_at >= before + chrono::Duration::seconds(1));
+        assert!(expires_at <= after + chrono::Duration::seconds(1));
+    }
+
+    #[test]
+    fn test_quick_client_on_change()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+
+        client.on_change(Arc::new(move |key, kind| {
+            recorded.lock().unwrap().push((key.to_string(), kind));
+        }));
+
+        client.set("key", "value".to_string()).unwrap();
+        client.set("key", "new value".to_string()).unwrap();
+        client.delete("key").unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                ("key".to_string(), ChangeKind::Set),
+                ("key".to_string(), ChangeKind::Updated),
+                ("key".to_string(), ChangeKind::Deleted),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quick_client_flush_without_sync_on_write()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            sync_on_write: Some(false),
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config.clone());
+
+        for i in 0..10 {
+            client.set(&format!("key_{}", i), i.to_string()).unwrap();
+        }
+
+        client.flush().unwrap();
+        drop(client);
+
+        let mut client = QuickClient::<String>::new(config);
+
+        for i in 0..10 {
+            assert_eq!(client.get(&format!("key_{}", i)).unwrap(), Some(i.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_quick_client_delete_prefix()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+        let mut client = QuickClient::<String>::new(config);
+
+        client.set("cache:a", "a".to_string()).unwrap();
+        client.set("cache:b", "b".to_string()).unwrap();
+        client.set("keep:c", "c".to_string()).unwrap();
+
+        assert_eq!(client.delete_prefix("cache:").unwrap(), 2);
+        assert_eq!(client.keys().unwrap(), Some(vec!["keep:c".to_string()]));
+    }
+
+    #[test]
+    fn test_quick_client_open_read_only()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file.clone()),
+            log: None,
+            log_level: None,
+            default_ttl: None,
+            ..Default::default()
+        };
+
+        let mut writer = QuickClient::<String>::new(config.clone());
+        writer.set("key", "value".to_string()).unwrap();
+
+        let mut reader = QuickClient::<String>::open_read_only(config.clone()).unwrap();
+
+        assert_eq!(reader.get("key").unwrap(), Some("value".to_string()));
+        assert!(matches!(
+            reader.set("other", "value".to_string()),
+            Err(QuickKvError::ReadOnly)
+        ));
+        assert!(matches!(reader.delete("key"), Err(QuickKvError::ReadOnly)));
+        assert!(matches!(reader.purge(), Err(QuickKvError::ReadOnly)));
+
+        // Nothing was written behind the writer's back.
+        drop(writer);
+        let mut reopened = QuickClient::<String>::new(config);
+
+        assert_eq!(reopened.get("key").unwrap(), Some("value".to_string()));
+        assert_eq!(reopened.get("other").unwrap(), None);
+
+        // There is nothing to open read-only if the file doesn't exist.
+        let missing = ClientConfig {
+            path: Some(tmp_dir.path().join("missing.qkv").to_str().unwrap().to_string()),
+            ..Default::default()
+        };
+
+        assert!(QuickClient::<String>::open_read_only(missing).is_err());
+    }
 }
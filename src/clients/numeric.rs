@@ -0,0 +1,120 @@
+use crate::error::QuickKvError;
+use crate::types::AsKey;
+
+/// Atomic arithmetic for clients that store integers.
+///
+/// Implemented for clients storing `i32`, `i64` and `u64` values.
+pub trait NumericClient<N>
+{
+    /// Add `by` to the value associated with a key and return the new value.
+    ///
+    /// The read and the write happen under a single lock, so concurrent callers can't lose
+    /// updates. A missing key starts at `0`. Fails with `QuickKvError::Other` if the result would overflow.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<i64>::new(ClientConfig::new(
+    ///     "increment.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let visits = client.increment("visits", 1).unwrap();
+    /// ```
+    fn increment<K: AsKey + ?Sized>(&mut self, key: &K, by: N) -> Result<N, QuickKvError>;
+    /// Subtract `by` from the value associated with a key and return the new value.
+    ///
+    /// The read and the write happen under a single lock, so concurrent callers can't lose
+    /// updates. A missing key starts at `0`. Fails with `QuickKvError::Other` if the result would overflow.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<i64>::new(ClientConfig::new(
+    ///     "decrement.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let stock = client.decrement("stock", 1).unwrap();
+    /// ```
+    fn decrement<K: AsKey + ?Sized>(&mut self, key: &K, by: N) -> Result<N, QuickKvError>;
+    /// Add each delta to the value associated with its key and return the new values, in the
+    /// same order as `deltas`.
+    ///
+    /// Every delta is applied under a single lock and written to the file at once, which is
+    /// much faster than calling `increment` in a loop. Missing keys start at `0`, and a key
+    /// listed twice gets both deltas. If any result would overflow nothing is changed and the
+    /// call fails with `QuickKvError::Other`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use quick_kv::prelude::*;
+    ///
+    /// let mut client = QuickClient::<i64>::new(ClientConfig::new(
+    ///     "increment_many.qkv".to_string(),
+    ///     true.into(),
+    ///     LevelFilter::Debug.into(),
+    /// ));
+    ///
+    /// let counts = client
+    ///     .increment_many(&[("page_views", 1), ("clicks", 3)])
+    ///     .unwrap();
+    /// ```
+    fn increment_many<K: AsKey>(&mut self, deltas: &[(K, N)]) -> Result<Vec<N>, QuickKvError>;
+}
+
+/// Implements `NumericClient` for a client over each of the given integer types.
+macro_rules! impl_numeric_client {
+    ($client:ident: $($num:ty),+) => {
+        $(
+            impl $crate::clients::NumericClient<$num> for $client<$num>
+            {
+                fn increment<K: $crate::types::AsKey + ?Sized>(&mut self, key: &K, by: $num) -> Result<$num, $crate::error::QuickKvError>
+                {
+                    let key = key.as_key();
+
+                    Ok(self.db.read_modify_write(&key, |current| {
+                        current
+                            .copied()
+                            .unwrap_or(0)
+                            .checked_add(by)
+                            .ok_or_else(|| anyhow::anyhow!("Incrementing \"{}\" by {} would overflow", key, by))
+                    })?)
+                }
+
+                fn decrement<K: $crate::types::AsKey + ?Sized>(&mut self, key: &K, by: $num) -> Result<$num, $crate::error::QuickKvError>
+                {
+                    let key = key.as_key();
+
+                    Ok(self.db.read_modify_write(&key, |current| {
+                        current
+                            .copied()
+                            .unwrap_or(0)
+                            .checked_sub(by)
+                            .ok_or_else(|| anyhow::anyhow!("Decrementing \"{}\" by {} would overflow", key, by))
+                    })?)
+                }
+
+                fn increment_many<K: $crate::types::AsKey>(&mut self, deltas: &[(K, $num)]) -> Result<Vec<$num>, $crate::error::QuickKvError>
+                {
+                    let keys = deltas.iter().map(|(key, _)| key.as_key()).collect::<Vec<_>>();
+                    let keys = keys.iter().map(|key| &**key).collect::<Vec<_>>();
+
+                    Ok(self.db.read_modify_write_many(&keys, |i, current| {
+                        let (key, by) = (keys[i], deltas[i].1);
+
+                        current
+                            .copied()
+                            .unwrap_or(0)
+                            .checked_add(by)
+                            .ok_or_else(|| anyhow::anyhow!("Incrementing \"{}\" by {} would overflow", key, by))
+                    })?)
+                }
+            }
+        )+
+    };
+}
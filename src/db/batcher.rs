@@ -1,10 +1,70 @@
-#![allow(dead_code)]
-pub(crate) struct Batcher {}
+/// Operations collected by a transaction, see `BaseClient::transaction`.
+///
+/// Nothing recorded here touches the database until the transaction closure returns `Ok`.
+#[derive(Debug)]
+pub struct Batch<T>
+{
+    pub(crate) ops: Vec<BatchOp<T>>,
+}
+
+#[derive(Debug)]
+pub(crate) enum BatchOp<T>
+{
+    Set
+    {
+        key: String, value: T
+    },
+    Update
+    {
+        key: String, value: T, upsert: Option<bool>
+    },
+    Delete
+    {
+        key: String
+    },
+}
 
-impl Batcher
+impl<T> Batch<T>
 {
     pub(crate) fn new() -> Self
     {
-        Self {}
+        Self { ops: Vec::new() }
+    }
+
+    /// Queues setting `key` to `value`.
+    pub fn set(&mut self, key: &str, value: T)
+    {
+        self.ops.push(BatchOp::Set {
+            key: key.to_string(),
+            value,
+        });
+    }
+
+    /// Queues an update of `key`, following the same rules as `BaseClient::update`.
+    pub fn update(&mut self, key: &str, value: T, upsert: Option<bool>)
+    {
+        self.ops.push(BatchOp::Update {
+            key: key.to_string(),
+            value,
+            upsert,
+        });
+    }
+
+    /// Queues deleting `key`.
+    pub fn delete(&mut self, key: &str)
+    {
+        self.ops.push(BatchOp::Delete { key: key.to_string() });
+    }
+
+    /// The number of queued operations.
+    pub fn len(&self) -> usize
+    {
+        self.ops.len()
+    }
+
+    /// Whether nothing has been queued yet.
+    pub fn is_empty(&self) -> bool
+    {
+        self.ops.is_empty()
     }
 }
@@ -0,0 +1,134 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Bits reserved per expected key, together with `HASHES` this gives roughly a 1% false positive rate.
+const BITS_PER_KEY: usize = 10;
+/// Number of bit positions set for every key.
+const HASHES: u64 = 7;
+/// How many keys a new filter is sized for before it has to grow.
+pub(crate) const DEFAULT_CAPACITY: usize = 1024;
+
+/// A Bloom filter over the keys in the database.
+///
+/// The filter can say a key is definitely absent, which lets lookups for missing keys skip
+/// the rest of the read path. Bits can't be cleared, so deleted keys keep reporting as
+/// possibly present until the filter is rebuilt.
+#[derive(Debug, Clone)]
+pub(crate) struct BloomFilter
+{
+    bits: Vec<u64>,
+    len: usize,
+    capacity: usize,
+}
+
+impl BloomFilter
+{
+    pub(crate) fn with_capacity(capacity: usize) -> Self
+    {
+        let capacity = capacity.max(1);
+        let words = (capacity * BITS_PER_KEY).div_ceil(64);
+
+        Self {
+            bits: vec![0; words],
+            len: 0,
+            capacity,
+        }
+    }
+
+    /// Adds `key` to the filter.
+    pub(crate) fn insert(&mut self, key: &str)
+    {
+        for bit in self.bit_positions(key) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+
+        self.len += 1;
+    }
+
+    /// Returns `false` if `key` was never inserted, `true` if it might have been.
+    pub(crate) fn may_contain(&self, key: &str) -> bool
+    {
+        self.bit_positions(key)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Whether the filter holds as many keys as it was sized for.
+    ///
+    /// Past this point the false positive rate climbs, so the owner should rebuild it larger.
+    pub(crate) fn is_full(&self) -> bool
+    {
+        self.len >= self.capacity
+    }
+
+    pub(crate) fn capacity(&self) -> usize
+    {
+        self.capacity
+    }
+
+    /// Double hashing, the `i`th position is `h1 + i * h2`.
+    fn bit_positions(&self, key: &str) -> impl Iterator<Item = usize>
+    {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let h1 = hasher.finish();
+
+        // Hashing again on top of the first state gives an independent enough second hash.
+        h1.hash(&mut hasher);
+        let h2 = hasher.finish() | 1;
+
+        let total_bits = (self.bits.len() * 64) as u64;
+
+        (0..HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % total_bits) as usize)
+    }
+}
+
+impl Default for BloomFilter
+{
+    fn default() -> Self
+    {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::db::entry::Entry;
+    use crate::db::state::State;
+
+    #[test]
+    fn test_bloom_no_false_negatives()
+    {
+        let mut bloom = BloomFilter::with_capacity(1000);
+
+        for i in 0..1000 {
+            bloom.insert(&format!("key_{}", i));
+        }
+
+        for i in 0..1000 {
+            assert!(bloom.may_contain(&format!("key_{}", i)));
+        }
+
+        // With the filter at capacity most absent keys should still be ruled out.
+        let false_positives = (1000..2000).filter(|i| bloom.may_contain(&format!("key_{}", i))).count();
+
+        assert!(false_positives < 100, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_bloom_grows_with_state()
+    {
+        let mut state = State::<String>::new();
+
+        for i in 0..DEFAULT_CAPACITY * 4 {
+            state.insert(Entry::new(format!("key_{}", i), "value".to_string(), None));
+        }
+
+        assert!(state.bloom.capacity() > DEFAULT_CAPACITY);
+
+        for i in 0..DEFAULT_CAPACITY * 4 {
+            assert!(state.bloom.may_contain(&format!("key_{}", i)));
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::io::{self, BufRead, Read};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::db::entry::Entry;
+
+/// The format entries are written to the database file in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SerializationFormat
+{
+    /// Compact binary records, this is the format older databases were written in.
+    #[default]
+    Bincode,
+    /// One JSON object per line, handy when you need to inspect or edit the file by hand.
+    Json,
+}
+
+/// How the database file is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression
+{
+    /// Gzip the whole file. Every write rewrites the file, so this suits databases that are
+    /// read far more often than they are written.
+    Gzip,
+}
+
+impl SerializationFormat
+{
+    /// Returns the codec used to read and write entries in this format.
+    pub(crate) fn codec<T>(self) -> &'static dyn EntryCodec<T>
+    where
+        T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + 'static,
+    {
+        match self {
+            SerializationFormat::Bincode => &BincodeCodec { checksum: true },
+            SerializationFormat::Json => &JsonCodec,
+        }
+    }
+}
+
+/// Turns entries into bytes and back.
+pub(crate) trait EntryCodec<T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync,
+{
+    /// Encodes a single entry into a record that can be appended to the file.
+    fn encode(&self, entry: &Entry<T>) -> anyhow::Result<Vec<u8>>;
+
+    /// Decodes the next record from `reader`, returning `None` once the end of the data is reached.
+    fn decode(&self, reader: &mut dyn BufRead) -> anyhow::Result<Option<Entry<T>>>;
+}
+
+/// Bincode records, each prefixed with its length as a little-endian `u32`.
+///
+/// The length marks where the next record starts without decoding this one, so a record that
+/// fails to decode can be skipped. With `checksum` the length is followed by a CRC32 of the
+/// record, which catches bit flips that would still decode into a wrong value.
+pub(crate) struct BincodeCodec
+{
+    pub(crate) checksum: bool,
+}
+
+impl<T> EntryCodec<T> for BincodeCodec
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync,
+{
+    fn encode(&self, entry: &Entry<T>) -> anyhow::Result<Vec<u8>>
+    {
+        let bytes = bincode::serialize(entry)?;
+        let len = u32::try_from(bytes.len())?;
+
+        let mut record = Vec::with_capacity(8 + bytes.len());
+        record.extend_from_slice(&len.to_le_bytes());
+
+        if self.checksum {
+            record.extend_from_slice(&crc32fast::hash(&bytes).to_le_bytes());
+        }
+
+        record.extend_from_slice(&bytes);
+
+        Ok(record)
+    }
+
+    fn decode(&self, reader: &mut dyn BufRead) -> anyhow::Result<Option<Entry<T>>>
+    {
+        let mut len = [0; 4];
+
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            // Reached the end of the serialized data
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let len = u32::from_le_bytes(len) as usize;
+
+        let mut checksum = [0; 4];
+
+        if self.checksum {
+            reader
+                .read_exact(&mut checksum)
+                .map_err(|_| anyhow::anyhow!("the file ends part way through a record"))?;
+        }
+
+        // Read through `take` so a corrupted length can't ask for a huge allocation up front.
+        let mut record = Vec::new();
+        (&mut *reader).take(len as u64).read_to_end(&mut record)?;
+
+        if record.len() != len {
+            return Err(anyhow::anyhow!("the file ends part way through a record"));
+        }
+
+        if self.checksum && crc32fast::hash(&record) != u32::from_le_bytes(checksum) {
+            return Err(anyhow::anyhow!("the record's checksum doesn't match its contents"));
+        }
+
+        Ok(Some(bincode::deserialize(&record)?))
+    }
+}
+
+pub(crate) struct JsonCodec;
+
+impl<T> EntryCodec<T> for JsonCodec
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync,
+{
+    fn encode(&self, entry: &Entry<T>) -> anyhow::Result<Vec<u8>>
+    {
+        let mut bytes = serde_json::to_vec(entry)?;
+        bytes.push(b'\n');
+
+        Ok(bytes)
+    }
+
+    fn decode(&self, reader: &mut dyn BufRead) -> anyhow::Result<Option<Entry<T>>>
+    {
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+
+            // Blank lines are allowed so hand edited files still load.
+            if !line.trim().is_empty() {
+                return Ok(Some(serde_json::from_str(&line)?));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::io::Cursor;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    fn round_trip(format: SerializationFormat) -> Result<()>
+    {
+        let codec = format.codec::<String>();
+        let first = Entry::new("first".to_string(), "one".to_string(), None);
+        let second = Entry::new("second".to_string(), "two".to_string(), None);
+
+        let mut bytes = codec.encode(&first)?;
+        bytes.extend(codec.encode(&second)?);
+
+        let mut reader = Cursor::new(bytes);
+
+        let decoded = codec.decode(&mut reader)?.unwrap();
+        assert_eq!(decoded.key, "first");
+        assert_eq!(decoded.data, "one");
+
+        let decoded = codec.decode(&mut reader)?.unwrap();
+        assert_eq!(decoded.key, "second");
+        assert_eq!(decoded.data, "two");
+
+        assert!(codec.decode(&mut reader)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_bincode_round_trip() -> Result<()>
+    {
+        round_trip(SerializationFormat::Bincode)
+    }
+
+    #[test]
+    fn test_codec_json_round_trip() -> Result<()>
+    {
+        round_trip(SerializationFormat::Json)
+    }
+
+    #[test]
+    fn test_codec_bincode_framing() -> Result<()>
+    {
+        let codec = SerializationFormat::Bincode.codec::<String>();
+        let mut bytes = Vec::new();
+
+        for i in 0..3 {
+            let record = codec.encode(&Entry::new(format!("key_{}", i), i.to_string(), None))?;
+
+            let len = u32::from_le_bytes(record[..4].try_into()?) as usize;
+            assert_eq!(len, record.len() - 8);
+
+            bytes.extend(record);
+        }
+
+        let mut reader = Cursor::new(&bytes);
+
+        for i in 0..3 {
+            let decoded = codec.decode(&mut reader)?.unwrap();
+            assert_eq!(decoded.key, format!("key_{}", i));
+            assert_eq!(decoded.data, i.to_string());
+        }
+
+        assert!(codec.decode(&mut reader)?.is_none());
+
+        // A record cut short is an error rather than the end of the data.
+        let mut reader = Cursor::new(&bytes[..bytes.len() - 1]);
+
+        codec.decode(&mut reader)?;
+        codec.decode(&mut reader)?;
+        assert!(codec.decode(&mut reader).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_codec_bincode_checksum() -> Result<()>
+    {
+        let codec = SerializationFormat::Bincode.codec::<String>();
+
+        let mut bytes = codec.encode(&Entry::new("key".to_string(), "value".to_string(), None))?;
+
+        // Turn "value" into "valud", which still decodes fine without the checksum.
+        let value = bytes.windows(5).position(|window| window == b"value").unwrap();
+        bytes[value + 4] ^= 1;
+
+        let err = codec.decode(&mut Cursor::new(&bytes)).unwrap_err();
+        assert!(err.to_string().contains("checksum"));
+
+        let unchecked = BincodeCodec { checksum: false };
+        let without_checksum = [&bytes[..4], &bytes[8..]].concat();
+        let decoded: Entry<String> = unchecked.decode(&mut Cursor::new(without_checksum))?.unwrap();
+        assert_eq!(decoded.data, "valud");
+
+        Ok(())
+    }
+}
@@ -4,7 +4,13 @@ use std::time::Duration;
 use anyhow::Ok;
 use log::LevelFilter;
 
+use super::codec::{Compression, SerializationFormat};
+use super::encryption::EncryptionConfig;
+use super::eviction::EvictionPolicy;
+use super::logging::LogFormat;
+use super::recovery::CorruptionPolicy;
 use super::runtime::{RunTime, RuntTimeType};
+use crate::error::QuickKvError;
 
 /// The configuration for the database.
 #[derive(Debug, Clone)]
@@ -30,9 +36,119 @@ pub(crate) struct DatabaseConfiguration
     ///
     /// If enabled, all entries will have a ttl by default.
     /// If disabled (None), then you will have to manually set the ttl for each entry.
+    /// A zero duration is rejected with `QuickKvError::InvalidTtl`.
     ///
     /// Default: None
     pub default_ttl: Option<Duration>,
+    /// How often the background task removes expired entries.
+    ///
+    /// Default: 1 second
+    pub ttl_interval: Option<Duration>,
+    /// If the latency of disk writes should be recorded.
+    ///
+    /// Default: false
+    pub track_write_latency: Option<bool>,
+    /// The format entries are written to the database file in.
+    ///
+    /// Default: SerializationFormat::Bincode
+    pub format: Option<SerializationFormat>,
+    /// Compress the database file.
+    ///
+    /// Compressed files are rewritten in full on every write instead of being appended to.
+    ///
+    /// Default: None
+    pub compression: Option<Compression>,
+    /// Scope every key to this namespace.
+    ///
+    /// Clients with different namespaces can share one file without seeing each other's keys.
+    /// Enumeration and `purge` only cover the client's own namespace.
+    ///
+    /// Default: None
+    pub namespace: Option<String>,
+    /// Rewrite the database file once this percentage of its records are stale.
+    ///
+    /// Updates are appended rather than rewriting the file, which leaves the previous
+    /// record of the key behind until the file is compacted.
+    ///
+    /// Default: 50
+    pub compaction_threshold: Option<u8>,
+    /// The most keys the database may hold.
+    ///
+    /// Inserting a new key past the limit fails with `QuickKvError::CapacityExceeded` unless an
+    /// `eviction` policy is set. Overwriting an existing key is always allowed.
+    ///
+    /// Default: None
+    pub max_entries: Option<usize>,
+    /// What to do when a new key would pass `max_entries`.
+    ///
+    /// With `None` the insert fails, with `EvictionPolicy::Lru` the least recently used key
+    /// is removed to make room.
+    ///
+    /// Default: None
+    pub eviction: Option<EvictionPolicy>,
+    /// Sync the database file to disk after every write.
+    ///
+    /// Turning this off makes writes much faster, but they only reach the operating system's
+    /// cache. They survive the process crashing, not the machine losing power, until
+    /// `flush` is called.
+    ///
+    /// Default: true
+    pub sync_on_write: Option<bool>,
+    /// Encrypt every record in the database file.
+    ///
+    /// Opening the file with a different key fails with `QuickKvError::Decryption`.
+    ///
+    /// Default: None
+    pub encryption: Option<EncryptionConfig>,
+    /// What to do when a record in the database file can't be read on load.
+    ///
+    /// Default: CorruptionPolicy::Fail
+    pub on_corruption: Option<CorruptionPolicy>,
+    /// The longest key, in bytes, that may be written.
+    ///
+    /// Longer keys are rejected with `QuickKvError::InvalidKey`, so a runaway key can't bloat
+    /// every record it's written in.
+    ///
+    /// Default: None
+    pub max_key_len: Option<usize>,
+    /// Checked against every key before it's written, keys it returns `false` for are
+    /// rejected with `QuickKvError::InvalidKey`.
+    ///
+    /// Default: None
+    pub key_validator: Option<fn(&str) -> bool>,
+    /// The size of the buffers the database file is read and written through, in bytes.
+    ///
+    /// Larger buffers mean fewer system calls when loading or writing large files, 64 KiB is a
+    /// good start for bulk workloads.
+    ///
+    /// Default: 8 KiB
+    pub io_buffer_size: Option<usize>,
+    /// How many keys to make room for up front.
+    ///
+    /// Setting this to roughly the number of keys the database will hold saves growing the
+    /// in-memory map over and over during a bulk load.
+    ///
+    /// Default: None
+    pub initial_capacity: Option<usize>,
+    /// The format of log lines, see `LogFormat`.
+    ///
+    /// Like `log_level`, only the first client with logging enabled decides it.
+    ///
+    /// Default: LogFormat::Text
+    pub log_format: Option<LogFormat>,
+    /// Sync writes to disk on a background timer this often instead of after every write.
+    ///
+    /// Writes still reach the OS right away, only the sync is coalesced, so a crash of the
+    /// machine can lose up to one interval of writes. A write syncs right away anyway once over
+    /// a megabyte is waiting. Takes precedence over `sync_on_write`.
+    ///
+    /// Default: None
+    pub flush_interval: Option<Duration>,
+    /// Open the database file without write access, every write fails with
+    /// `QuickKvError::ReadOnly`.
+    ///
+    /// Default: false
+    pub read_only: Option<bool>,
 }
 
 impl DatabaseConfiguration
@@ -46,15 +162,32 @@ impl DatabaseConfiguration
     ) -> anyhow::Result<Self>
     {
         let vp = match path {
-            Some(p) => validate_path(p.as_str()),
+            Some(p) => {
+                // A trailing slash asks for the default file inside that directory, any other
+                // existing directory would otherwise get mangled into a file name by `validate_path`.
+                if !p.ends_with('/') {
+                    ensure_not_directory(&p)?;
+                }
+
+                validate_path(p.as_str())
+            }
             None => "db.qkv".to_string(),
         };
 
+        ensure_not_directory(&vp)?;
+
+        if default_ttl.is_some_and(|ttl| ttl.is_zero()) {
+            return Err(QuickKvError::InvalidTtl.into());
+        }
+
+        // A memory runtime never opens the file, so it shouldn't leave directories behind either.
+        let is_disk = !runtime.as_ref().is_some_and(|rt| rt._type == RuntTimeType::Memory);
+
         // Extract the directory part from the path
         let dir_path = Path::new(&vp).parent().unwrap_or_else(|| Path::new(""));
 
         // Create the parent directories if they don't exist
-        if !dir_path.exists() {
+        if is_disk && !dir_path.exists() {
             std::fs::create_dir_all(dir_path)?;
         }
 
@@ -64,10 +197,41 @@ impl DatabaseConfiguration
             log,
             log_level,
             default_ttl,
+            ttl_interval: None,
+            track_write_latency: None,
+            format: None,
+            compression: None,
+            namespace: None,
+            compaction_threshold: None,
+            max_entries: None,
+            eviction: None,
+            sync_on_write: None,
+            encryption: None,
+            on_corruption: None,
+            max_key_len: None,
+            key_validator: None,
+            io_buffer_size: None,
+            initial_capacity: None,
+            log_format: None,
+            flush_interval: None,
+            read_only: None,
         })
     }
 }
 
+/// Fails with `QuickKvError::InvalidPath` if `path` is an existing directory.
+pub(crate) fn ensure_not_directory(path: &str) -> anyhow::Result<()>
+{
+    if Path::new(path).is_dir() {
+        return Err(QuickKvError::InvalidPath {
+            reason: format!("\"{}\" is a directory", path),
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
 /// Used to validate if the database path is valid.
 /// If not it will apply the appropriate changes to make it valid.
 fn validate_path(input: &str) -> String
@@ -99,6 +263,24 @@ impl Default for DatabaseConfiguration
             log: true.into(),
             log_level: LevelFilter::Info.into(),
             default_ttl: None,
+            ttl_interval: None,
+            track_write_latency: None,
+            format: None,
+            compression: None,
+            namespace: None,
+            compaction_threshold: None,
+            max_entries: None,
+            eviction: None,
+            sync_on_write: None,
+            encryption: None,
+            on_corruption: None,
+            max_key_len: None,
+            key_validator: None,
+            io_buffer_size: None,
+            initial_capacity: None,
+            log_format: None,
+            flush_interval: None,
+            read_only: None,
         }
     }
 }
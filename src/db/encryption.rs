@@ -0,0 +1,123 @@
+use std::fmt;
+use std::io::{self, Read};
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+use crate::error::QuickKvError;
+
+/// The length of the random nonce stored in front of every encrypted record.
+const NONCE_LEN: usize = 12;
+
+/// Encrypts every record in the database file with AES-256-GCM.
+///
+/// Each record gets a fresh random nonce, and is authenticated, so a wrong key or a
+/// modified file fails with `QuickKvError::Decryption` instead of loading garbage.
+#[derive(Clone)]
+pub struct EncryptionConfig
+{
+    key: [u8; 32],
+}
+
+impl EncryptionConfig
+{
+    /// Uses `key` to encrypt and decrypt the database file.
+    ///
+    /// Keep the key somewhere other than next to the database, anyone holding both can read it.
+    pub fn new(key: [u8; 32]) -> Self
+    {
+        Self { key }
+    }
+
+    fn cipher(&self) -> Aes256Gcm
+    {
+        Aes256Gcm::new(&self.key.into())
+    }
+
+    /// Encrypts `plaintext` into a record: a little-endian `u32` length, the nonce, then the
+    /// ciphertext.
+    pub(crate) fn seal(&self, plaintext: &[u8]) -> anyhow::Result<Vec<u8>>
+    {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt a record"))?;
+
+        let len = u32::try_from(NONCE_LEN + ciphertext.len())?;
+
+        let mut record = Vec::with_capacity(4 + len as usize);
+        record.extend_from_slice(&len.to_le_bytes());
+        record.extend_from_slice(&nonce);
+        record.extend_from_slice(&ciphertext);
+
+        Ok(record)
+    }
+
+    /// Reads and decrypts the next record from `reader`, returning `None` once the end of the
+    /// data is reached.
+    pub(crate) fn open_next<R: Read + ?Sized>(&self, reader: &mut R) -> anyhow::Result<Option<Vec<u8>>>
+    {
+        let mut len = [0; 4];
+
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let len = u32::from_le_bytes(len) as usize;
+
+        // Read through `take` so a corrupted length can't ask for a huge allocation up front.
+        let mut record = Vec::new();
+        (&mut *reader).take(len as u64).read_to_end(&mut record)?;
+
+        if record.len() != len || len < NONCE_LEN {
+            return Err(QuickKvError::Decryption.into());
+        }
+
+        let (nonce, ciphertext) = record.split_at(NONCE_LEN);
+
+        let plaintext = self
+            .cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| QuickKvError::Decryption)?;
+
+        Ok(Some(plaintext))
+    }
+}
+
+impl fmt::Debug for EncryptionConfig
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.debug_struct("EncryptionConfig").field("key", &"<redacted>").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_encryption_round_trip()
+    {
+        let encryption = EncryptionConfig::new([7; 32]);
+
+        let mut bytes = encryption.seal(b"first").unwrap();
+        bytes.extend(encryption.seal(b"second").unwrap());
+
+        let mut reader = bytes.as_slice();
+
+        assert_eq!(encryption.open_next(&mut reader).unwrap(), Some(b"first".to_vec()));
+        assert_eq!(encryption.open_next(&mut reader).unwrap(), Some(b"second".to_vec()));
+        assert_eq!(encryption.open_next(&mut reader).unwrap(), None);
+
+        let wrong_key = EncryptionConfig::new([8; 32]);
+        let err = wrong_key.open_next(&mut bytes.as_slice()).unwrap_err();
+
+        assert!(matches!(QuickKvError::from(err), QuickKvError::Decryption));
+    }
+}
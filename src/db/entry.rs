@@ -17,6 +17,10 @@ where
     /// Instant at which the entry expires and should be removed from the
     /// database.
     pub(crate) expires_at: Option<DateTime<Utc>>,
+    /// When the key was first stored.
+    pub(crate) created_at: DateTime<Utc>,
+    /// When the key's value was last written.
+    pub(crate) updated_at: DateTime<Utc>,
 }
 
 impl<T> Entry<T>
@@ -25,7 +29,21 @@ where
 {
     pub(crate) fn new(key: String, data: T, expires_at: Option<DateTime<Utc>>) -> Self
     {
-        Self { key, data, expires_at }
+        let now = Utc::now();
+
+        Self {
+            key,
+            data,
+            expires_at,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Checks if the entry has a ttl that has already passed.
+    pub(crate) fn is_expired(&self) -> bool
+    {
+        self.expires_at.is_some_and(|expires_at| expires_at <= Utc::now())
     }
 }
 
@@ -43,6 +61,11 @@ where
             key: String,
             data: T,
             expires_at: Option<DateTime<Utc>>,
+            // Missing from records written before timestamps were tracked.
+            #[serde(default)]
+            created_at: DateTime<Utc>,
+            #[serde(default)]
+            updated_at: DateTime<Utc>,
         }
 
         let helper = EntryHelper::<T>::deserialize(deserializer)?;
@@ -51,6 +74,40 @@ where
             key: helper.key,
             data: helper.data,
             expires_at: helper.expires_at,
+            created_at: helper.created_at,
+            updated_at: helper.updated_at,
         })
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_entry_round_trip()
+    {
+        let entry = Entry::new("key".to_string(), vec![1u64, 2, 3], Some(Utc::now()));
+
+        let json: Entry<Vec<u64>> = serde_json::from_str(&serde_json::to_string(&entry).unwrap()).unwrap();
+        let bincode: Entry<Vec<u64>> = bincode::deserialize(&bincode::serialize(&entry).unwrap()).unwrap();
+
+        for decoded in [json, bincode] {
+            assert_eq!(decoded.key, entry.key);
+            assert_eq!(decoded.data, entry.data);
+            assert_eq!(decoded.expires_at, entry.expires_at);
+            assert_eq!(decoded.created_at, entry.created_at);
+            assert_eq!(decoded.updated_at, entry.updated_at);
+        }
+    }
+
+    #[test]
+    fn test_entry_without_timestamps()
+    {
+        let entry: Entry<String> = serde_json::from_str(r#"{"key":"key","data":"value","expires_at":null}"#).unwrap();
+
+        assert_eq!(entry.data, "value");
+        assert_eq!(entry.created_at, DateTime::<Utc>::default());
+    }
+}
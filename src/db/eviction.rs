@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::HashMap;
+
+/// What to do when a new key would take the database past `max_entries`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EvictionPolicy
+{
+    /// Remove the least recently used key to make room.
+    ///
+    /// `get` and every write count as a use. The write that evicts rewrites the database file,
+    /// so evicted keys stay gone after a restart.
+    Lru,
+}
+
+/// Tracks the order keys were last used in, for `EvictionPolicy::Lru`.
+///
+/// Removed keys aren't forgotten right away, `pop_least_recent` can hand out keys that no
+/// longer exist and callers are expected to skip them.
+#[derive(Debug, Default)]
+pub(crate) struct AccessOrder
+{
+    next: u64,
+    ticks: HashMap<String, u64>,
+    by_tick: BTreeMap<u64, String>,
+}
+
+impl AccessOrder
+{
+    /// Marks `key` as the most recently used.
+    pub(crate) fn touch(&mut self, key: &str)
+    {
+        if let Some(previous) = self.ticks.insert(key.to_string(), self.next) {
+            self.by_tick.remove(&previous);
+        }
+
+        self.by_tick.insert(self.next, key.to_string());
+        self.next += 1;
+    }
+
+    /// Removes and returns the least recently used key.
+    pub(crate) fn pop_least_recent(&mut self) -> Option<String>
+    {
+        let (_, key) = self.by_tick.pop_first()?;
+        self.ticks.remove(&key);
+
+        Some(key)
+    }
+
+    /// Forgets every key rejected by `keep`.
+    pub(crate) fn retain(&mut self, keep: impl Fn(&str) -> bool)
+    {
+        self.by_tick.retain(|_, key| keep(key));
+        self.ticks.retain(|key, _| keep(key));
+    }
+
+    pub(crate) fn len(&self) -> usize
+    {
+        self.ticks.len()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_access_order_least_recent()
+    {
+        let mut order = AccessOrder::default();
+
+        order.touch("a");
+        order.touch("b");
+        order.touch("c");
+        order.touch("a");
+
+        assert_eq!(order.pop_least_recent(), Some("b".to_string()));
+        assert_eq!(order.pop_least_recent(), Some("c".to_string()));
+        assert_eq!(order.pop_least_recent(), Some("a".to_string()));
+        assert_eq!(order.pop_least_recent(), None);
+        assert_eq!(order.len(), 0);
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::lock;
+
+/// How many unsynced bytes make a write sync right away instead of waiting for the next tick.
+pub(crate) const FLUSH_THRESHOLD: usize = 1024 * 1024;
+
+/// A signal sent to the background flush task.
+#[allow(dead_code)]
+#[derive(Debug)]
+pub(super) enum FlushSignal
+{
+    Flush,
+    Exit,
+}
+
+/// Handle to the background task that syncs coalesced writes, see `flush_interval`.
+///
+/// Writes only mark how many bytes are waiting, the task syncs the file once per interval if
+/// anything was written. Like `TTLManager`, the handle is shared between clones of a `Database`
+/// and the task is told to exit once the last one is dropped.
+#[derive(Debug)]
+pub(crate) struct FlushManager
+{
+    sender: Sender<FlushSignal>,
+    unsynced: Arc<AtomicUsize>,
+}
+
+impl FlushManager
+{
+    /// Spawns the background task, it wakes up every `interval` or whenever a
+    /// `FlushSignal::Flush` is received. Every sync it makes is counted in `syncs`.
+    pub(crate) fn spawn(
+        writer: Arc<Mutex<BufWriter<File>>>,
+        syncs: Arc<AtomicUsize>,
+        interval: Duration,
+    ) -> anyhow::Result<Self>
+    {
+        let (sender, receiver) = mpsc::channel::<FlushSignal>();
+        let unsynced = Arc::new(AtomicUsize::new(0));
+        let pending = unsynced.clone();
+
+        thread::Builder::new()
+            .name("quick-kv-flush".to_string())
+            .spawn(move || loop {
+                match receiver.recv_timeout(interval) {
+                    Ok(FlushSignal::Flush) | Err(RecvTimeoutError::Timeout) => {
+                        if pending.swap(0, Ordering::Relaxed) == 0 {
+                            continue;
+                        }
+
+                        if let Err(e) = sync_writer(&writer, &syncs) {
+                            log::error!("[FLUSH] Failed to sync database file: {}", e);
+                        }
+                    }
+                    Ok(FlushSignal::Exit) | Err(RecvTimeoutError::Disconnected) => {
+                        log::debug!("[FLUSH] Background task exiting");
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(Self { sender, unsynced })
+    }
+
+    /// Records `bytes` written since the last sync.
+    ///
+    /// Returns `true` once more than `FLUSH_THRESHOLD` bytes are waiting, the caller should sync
+    /// right away then rather than let them pile up until the next tick.
+    pub(crate) fn wrote(&self, bytes: usize) -> bool
+    {
+        let unsynced = self.unsynced.fetch_add(bytes, Ordering::Relaxed) + bytes;
+
+        if unsynced >= FLUSH_THRESHOLD {
+            self.unsynced.store(0, Ordering::Relaxed);
+            return true;
+        }
+
+        false
+    }
+
+    /// Asks the background task to sync right away.
+    #[allow(dead_code)]
+    pub(crate) fn flush(&self)
+    {
+        // The task only stops receiving once it has exited, so there is nothing to do on error.
+        let _ = self.sender.send(FlushSignal::Flush);
+    }
+
+    /// Tells the background task to exit, sending it again later is harmless.
+    pub(crate) fn stop(&self)
+    {
+        let _ = self.sender.send(FlushSignal::Exit);
+    }
+}
+
+impl Drop for FlushManager
+{
+    fn drop(&mut self)
+    {
+        self.stop();
+    }
+}
+
+/// Flushes `writer` and syncs the file underneath it, counting the sync in `syncs`.
+pub(crate) fn sync_writer(writer: &Mutex<BufWriter<File>>, syncs: &AtomicUsize) -> anyhow::Result<()>
+{
+    let mut w = lock(writer);
+
+    w.flush()?;
+    w.get_ref().sync_all()?;
+
+    syncs.fetch_add(1, Ordering::Relaxed);
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::db::codec::SerializationFormat;
+use crate::error::QuickKvError;
+
+/// The bytes every database file starts with.
+pub(crate) const MAGIC: &[u8; 4] = b"QKV1";
+
+/// The layout of the records that follow the header.
+///
+/// Bump this whenever the layout of a record changes, older versions are migrated on load.
+/// Files without a header predate versioning and are handled by `migrate`.
+///
+/// 1. Entries carry `created_at` and `updated_at`.
+/// 2. Bincode records are prefixed with their length.
+/// 3. Bincode records carry a CRC32 after their length.
+pub(crate) const FORMAT_VERSION: u8 = 3;
+
+/// The magic, then one byte for the serialization format and one for the version.
+pub(crate) const HEADER_LEN: usize = MAGIC.len() + 2;
+
+/// The header written at the start of a database file holding records in `format`.
+pub(crate) fn encode(format: SerializationFormat) -> [u8; HEADER_LEN]
+{
+    let mut header = [0; HEADER_LEN];
+
+    header[..MAGIC.len()].copy_from_slice(MAGIC);
+    header[MAGIC.len()] = format_byte(format);
+    header[MAGIC.len() + 1] = FORMAT_VERSION;
+
+    header
+}
+
+/// Checks that `header` belongs to a file of records in `format` this version can read, and
+/// returns the format version the file was written with.
+///
+/// Fails with `QuickKvError::BadFormat` otherwise.
+pub(crate) fn validate(header: &[u8], format: SerializationFormat) -> anyhow::Result<u8>
+{
+    if header.len() < HEADER_LEN || &header[..MAGIC.len()] != MAGIC {
+        return Err(bad_format("the file is not a quick-kv database"));
+    }
+
+    if header[MAGIC.len()] != format_byte(format) {
+        return Err(bad_format(&format!("the file was not written as {:?}", format)));
+    }
+
+    let version = header[MAGIC.len() + 1];
+
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(bad_format(&format!(
+            "unsupported version {}, expected at most {}",
+            version, FORMAT_VERSION
+        )));
+    }
+
+    Ok(version)
+}
+
+fn format_byte(format: SerializationFormat) -> u8
+{
+    match format {
+        SerializationFormat::Bincode => b'B',
+        SerializationFormat::Json => b'J',
+    }
+}
+
+fn bad_format(reason: &str) -> anyhow::Error
+{
+    QuickKvError::BadFormat {
+        reason: reason.to_string(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip()
+    {
+        let header = encode(SerializationFormat::Bincode);
+
+        assert_eq!(validate(&header, SerializationFormat::Bincode).unwrap(), FORMAT_VERSION);
+        assert!(validate(&header, SerializationFormat::Json).is_err());
+        assert!(validate(&header[..3], SerializationFormat::Bincode).is_err());
+
+        let mut newer = header;
+        newer[HEADER_LEN - 1] = FORMAT_VERSION + 1;
+
+        assert!(validate(&newer, SerializationFormat::Bincode).is_err());
+
+        let mut older = header;
+        older[HEADER_LEN - 1] = 1;
+
+        assert_eq!(validate(&older, SerializationFormat::Bincode).unwrap(), 1);
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::VecDeque;
+
+use crate::types::HashSet;
+
+/// How many idempotency tokens are remembered before the oldest ones are forgotten.
+pub(crate) const DEFAULT_TOKEN_CAPACITY: usize = 1024;
+
+/// A bounded record of recently applied idempotency tokens.
+///
+/// Tokens are only kept in memory, so a retry that arrives after a restart is applied again.
+#[derive(Debug)]
+pub(crate) struct RecentTokens
+{
+    capacity: usize,
+    order: VecDeque<String>,
+    seen: HashSet<String>,
+}
+
+impl RecentTokens
+{
+    pub(crate) fn with_capacity(capacity: usize) -> Self
+    {
+        Self {
+            capacity: capacity.max(1),
+            order: VecDeque::new(),
+            seen: HashSet::default(),
+        }
+    }
+
+    pub(crate) fn contains(&self, token: &str) -> bool
+    {
+        self.seen.contains(token)
+    }
+
+    /// Records `token`, forgetting the oldest token once the capacity is reached.
+    pub(crate) fn insert(&mut self, token: &str)
+    {
+        if !self.seen.insert(token.to_string()) {
+            return;
+        }
+
+        self.order.push_back(token.to_string());
+
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+    }
+}
+
+impl Default for RecentTokens
+{
+    fn default() -> Self
+    {
+        Self::with_capacity(DEFAULT_TOKEN_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_recent_tokens_evicts_oldest()
+    {
+        let mut tokens = RecentTokens::with_capacity(2);
+
+        tokens.insert("a");
+        tokens.insert("b");
+        tokens.insert("c");
+
+        assert!(!tokens.contains("a"));
+        assert!(tokens.contains("b"));
+        assert!(tokens.contains("c"));
+    }
+}
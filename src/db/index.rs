@@ -0,0 +1,221 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::types::{HashMap, HashSet};
+
+/// A secondary index over the values of a database, see `create_index`.
+///
+/// The key type is erased so indexes with different key types can live side by side, `get`
+/// recovers it.
+trait SecondaryIndex<T>: Send + Sync
+{
+    fn insert(&mut self, key: &str, value: &T);
+
+    fn remove(&mut self, key: &str, value: &T);
+
+    fn clear(&mut self);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn box_clone(&self) -> Box<dyn SecondaryIndex<T>>;
+}
+
+/// The keys of every entry, grouped by what `extractor` returns for its value.
+struct Index<T, K>
+{
+    extractor: fn(&T) -> K,
+    buckets: BTreeMap<K, HashSet<String>>,
+}
+
+impl<T, K> Index<T, K>
+where
+    T: 'static,
+    K: Ord + Clone + Send + Sync + 'static,
+{
+    /// The keys whose values map to `index_key`.
+    fn keys(&self, index_key: &K) -> Vec<String>
+    {
+        self.buckets
+            .get(index_key)
+            .map(|keys| keys.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+impl<T, K> SecondaryIndex<T> for Index<T, K>
+where
+    T: 'static,
+    K: Ord + Clone + Send + Sync + 'static,
+{
+    fn insert(&mut self, key: &str, value: &T)
+    {
+        self.buckets
+            .entry((self.extractor)(value))
+            .or_default()
+            .insert(key.to_string());
+    }
+
+    fn remove(&mut self, key: &str, value: &T)
+    {
+        let index_key = (self.extractor)(value);
+
+        if let Some(keys) = self.buckets.get_mut(&index_key) {
+            keys.remove(key);
+
+            if keys.is_empty() {
+                self.buckets.remove(&index_key);
+            }
+        }
+    }
+
+    fn clear(&mut self)
+    {
+        self.buckets.clear();
+    }
+
+    fn as_any(&self) -> &dyn Any
+    {
+        self
+    }
+
+    fn box_clone(&self) -> Box<dyn SecondaryIndex<T>>
+    {
+        Box::new(Index {
+            extractor: self.extractor,
+            buckets: self.buckets.clone(),
+        })
+    }
+}
+
+/// The secondary indexes of a database by name, kept in sync with its entries by `State`.
+///
+/// Indexes only live in memory and have to be created again after the database is reopened.
+pub(crate) struct Indexes<T>
+{
+    indexes: HashMap<String, Box<dyn SecondaryIndex<T>>>,
+}
+
+impl<T> Indexes<T>
+where
+    T: 'static,
+{
+    /// Adds an index named `name` over `entries`, replacing any index with the same name.
+    pub(crate) fn create<'a, K>(
+        &mut self,
+        name: &str,
+        extractor: fn(&T) -> K,
+        entries: impl Iterator<Item = (&'a str, &'a T)>,
+    ) where
+        K: Ord + Clone + Send + Sync + 'static,
+    {
+        let mut index = Index {
+            extractor,
+            buckets: BTreeMap::new(),
+        };
+
+        for (key, value) in entries {
+            index.insert(key, value);
+        }
+
+        self.indexes.insert(name.to_string(), Box::new(index));
+    }
+
+    /// The keys in index `name` whose values map to `index_key`.
+    ///
+    /// Returns `None` if there is no index called `name` or it was created with another key type.
+    pub(crate) fn get<K>(&self, name: &str, index_key: &K) -> Option<Vec<String>>
+    where
+        K: Ord + Clone + Send + Sync + 'static,
+    {
+        let index = self.indexes.get(name)?.as_any().downcast_ref::<Index<T, K>>()?;
+
+        Some(index.keys(index_key))
+    }
+}
+
+impl<T> Indexes<T>
+{
+    pub(crate) fn insert(&mut self, key: &str, value: &T)
+    {
+        for index in self.indexes.values_mut() {
+            index.insert(key, value);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &str, value: &T)
+    {
+        for index in self.indexes.values_mut() {
+            index.remove(key, value);
+        }
+    }
+
+    pub(crate) fn clear(&mut self)
+    {
+        for index in self.indexes.values_mut() {
+            index.clear();
+        }
+    }
+}
+
+impl<T> Default for Indexes<T>
+{
+    fn default() -> Self
+    {
+        Self {
+            indexes: HashMap::default(),
+        }
+    }
+}
+
+impl<T> Clone for Indexes<T>
+{
+    fn clone(&self) -> Self
+    {
+        Self {
+            indexes: self
+                .indexes
+                .iter()
+                .map(|(name, index)| (name.clone(), index.box_clone()))
+                .collect(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Indexes<T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.debug_struct("Indexes")
+            .field("names", &self.indexes.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_indexes_track_values()
+    {
+        let mut indexes = Indexes::<String>::default();
+        let values = [("a", "one".to_string()), ("b", "two".to_string())];
+
+        indexes.create("len", |value: &String| value.len(), values.iter().map(|(k, v)| (*k, v)));
+
+        let mut keys = indexes.get("len", &3usize).unwrap();
+        keys.sort();
+
+        assert_eq!(keys, vec!["a".to_string(), "b".to_string()]);
+
+        indexes.remove("a", &"one".to_string());
+        indexes.insert("a", &"three".to_string());
+
+        assert_eq!(indexes.get("len", &3usize).unwrap(), vec!["b".to_string()]);
+        assert_eq!(indexes.get("len", &5usize).unwrap(), vec!["a".to_string()]);
+        assert_eq!(indexes.get::<usize>("missing", &3), None);
+        assert_eq!(indexes.get::<u8>("len", &3), None);
+    }
+}
@@ -0,0 +1,214 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// How log lines are written when `log` is enabled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogFormat
+{
+    /// Colored lines meant to be read by people.
+    #[default]
+    Text,
+    /// One JSON object per line, for log aggregators.
+    ///
+    /// Every line has `timestamp`, `level`, `target` and `message`. Lines for finished operations
+    /// also have `operation`, `key`, `duration_us` and `outcome`.
+    Json,
+}
+
+/// The target finished operations are logged under, at debug level.
+pub(crate) const OPERATION_TARGET: &str = "quick_kv::operation";
+
+/// The fields of the operation currently being logged on this thread.
+///
+/// `log` only carries a formatted message, so the fields are handed to `JsonLogger` on the side.
+/// Loggers run on the thread that logs, so the logger always sees the operation it belongs to.
+#[derive(Debug, Clone)]
+struct Operation
+{
+    operation: &'static str,
+    key: String,
+    duration: Duration,
+    outcome: &'static str,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Operation>> = RefCell::new(None);
+}
+
+/// Logs an operation on `key` when it is dropped, with how long it took and its `outcome`.
+///
+/// The outcome starts as `"error"` so an early return through `?` is reported as one, set it
+/// before returning normally.
+pub(crate) struct OperationLog
+{
+    operation: &'static str,
+    key: String,
+    started: Instant,
+    pub(crate) outcome: &'static str,
+}
+
+impl OperationLog
+{
+    pub(crate) fn start(operation: &'static str, key: &str) -> Self
+    {
+        Self {
+            operation,
+            key: key.to_string(),
+            started: Instant::now(),
+            outcome: "error",
+        }
+    }
+}
+
+impl Drop for OperationLog
+{
+    fn drop(&mut self)
+    {
+        if !log::log_enabled!(target: OPERATION_TARGET, Level::Debug) {
+            return;
+        }
+
+        let operation = Operation {
+            operation: self.operation,
+            key: std::mem::take(&mut self.key),
+            duration: self.started.elapsed(),
+            outcome: self.outcome,
+        };
+
+        CURRENT.with(|current| *current.borrow_mut() = Some(operation.clone()));
+
+        log::debug!(
+            target: OPERATION_TARGET,
+            "[{}] {} in {:?}: {}",
+            operation.operation,
+            operation.outcome,
+            operation.duration,
+            operation.key
+        );
+
+        CURRENT.with(|current| current.borrow_mut().take());
+    }
+}
+
+/// Writes every record to stdout as a single line of JSON, see `LogFormat::Json`.
+pub(crate) struct JsonLogger
+{
+    level: LevelFilter,
+}
+
+impl JsonLogger
+{
+    pub(crate) fn init(level: LevelFilter) -> Result<(), log::SetLoggerError>
+    {
+        log::set_boxed_logger(Box::new(Self { level }))?;
+        log::set_max_level(level);
+
+        Ok(())
+    }
+
+    fn format(record: &Record) -> Value
+    {
+        let mut line = json!({
+            "timestamp": Utc::now().to_rfc3339(),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+        });
+
+        if record.target() == OPERATION_TARGET {
+            CURRENT.with(|current| {
+                if let Some(operation) = &*current.borrow() {
+                    line["operation"] = json!(operation.operation);
+                    line["key"] = json!(operation.key);
+                    line["duration_us"] = json!(operation.duration.as_micros() as u64);
+                    line["outcome"] = json!(operation.outcome);
+                }
+            });
+        }
+
+        line
+    }
+}
+
+impl Log for JsonLogger
+{
+    fn enabled(&self, metadata: &Metadata) -> bool
+    {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record)
+    {
+        if self.enabled(record.metadata()) {
+            let _ = writeln!(std::io::stdout().lock(), "{}", Self::format(record));
+        }
+    }
+
+    fn flush(&self)
+    {
+        let _ = std::io::stdout().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_json_logger_operation_fields()
+    {
+        CURRENT.with(|current| {
+            *current.borrow_mut() = Some(Operation {
+                operation: "SET",
+                key: "user_1".to_string(),
+                duration: Duration::from_micros(42),
+                outcome: "ok",
+            })
+        });
+
+        let line = JsonLogger::format(
+            &Record::builder()
+                .level(Level::Debug)
+                .target(OPERATION_TARGET)
+                .args(format_args!("[SET] ok in 42µs: user_1"))
+                .build(),
+        )
+        .to_string();
+
+        CURRENT.with(|current| current.borrow_mut().take());
+
+        let parsed: Value = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(parsed["level"], "DEBUG");
+        assert_eq!(parsed["operation"], "SET");
+        assert_eq!(parsed["key"], "user_1");
+        assert_eq!(parsed["duration_us"], 42);
+        assert_eq!(parsed["outcome"], "ok");
+        assert!(parsed["timestamp"].is_string());
+    }
+
+    #[test]
+    fn test_json_logger_plain_record()
+    {
+        let line = JsonLogger::format(
+            &Record::builder()
+                .level(Level::Info)
+                .target("quick_kv::db")
+                .args(format_args!("[Bootstrap] Building Database State"))
+                .build(),
+        )
+        .to_string();
+
+        let parsed: Value = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(parsed["message"], "[Bootstrap] Building Database State");
+        assert!(parsed.get("operation").is_none());
+    }
+}
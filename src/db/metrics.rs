@@ -0,0 +1,183 @@
+#[cfg(feature = "metrics")]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(feature = "metrics")]
+use std::time::Instant;
+
+#[cfg(feature = "metrics")]
+use crate::error::lock;
+
+/// Number of histogram buckets, bucket `i` counts durations under `2^i` microseconds.
+const BUCKETS: usize = 32;
+
+/// A summary of recorded latencies.
+///
+/// Percentiles are approximate, they are reported as the upper bound of the
+/// power-of-two bucket they fall into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyStats
+{
+    /// How many operations were recorded.
+    pub count: u64,
+    /// The fastest recorded operation.
+    pub min: Duration,
+    /// The slowest recorded operation.
+    pub max: Duration,
+    /// All recorded operations added together.
+    pub total: Duration,
+    /// The median latency.
+    pub p50: Duration,
+    /// The 99th percentile latency.
+    pub p99: Duration,
+}
+
+/// A snapshot of the database's size, as returned by `stats`.
+///
+/// Useful to decide when to call `compact`, a high `stale_record_ratio` means most of the file
+/// is taken up by records that have since been overwritten or deleted.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DbStats
+{
+    /// Keys that exist and haven't expired.
+    pub entry_count: usize,
+    /// Keys whose ttl has passed but that haven't been removed yet.
+    pub expired_pending: usize,
+    /// The size of the database file, zero for in-memory databases.
+    pub file_size_bytes: u64,
+    /// Roughly how many bytes the live entries take up once serialized, found by encoding each
+    /// of them again.
+    pub live_bytes_estimate: u64,
+    /// The share of records in the file that are no longer live, from `0.0` to `1.0`.
+    pub stale_record_ratio: f64,
+}
+
+/// A lightweight latency histogram with power-of-two microsecond buckets.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LatencyHistogram
+{
+    buckets: [u64; BUCKETS],
+    count: u64,
+    min: Option<Duration>,
+    max: Duration,
+    total: Duration,
+}
+
+impl LatencyHistogram
+{
+    pub(crate) fn record(&mut self, latency: Duration)
+    {
+        let micros = latency.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = (u64::BITS - micros.leading_zeros()) as usize;
+
+        self.buckets[bucket.min(BUCKETS - 1)] += 1;
+        self.count += 1;
+        self.min = Some(self.min.map_or(latency, |min| min.min(latency)));
+        self.max = self.max.max(latency);
+        self.total += latency;
+    }
+
+    pub(crate) fn stats(&self) -> LatencyStats
+    {
+        let min = self.min.unwrap_or_default();
+
+        LatencyStats {
+            count: self.count,
+            min,
+            max: self.max,
+            total: self.total,
+            p50: self.percentile(0.50).clamp(min, self.max),
+            p99: self.percentile(0.99).clamp(min, self.max),
+        }
+    }
+
+    /// Returns the upper bound of the bucket holding the given percentile.
+    fn percentile(&self, percentile: f64) -> Duration
+    {
+        let target = ((self.count as f64) * percentile).ceil() as u64;
+        let mut seen = 0;
+
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+
+            if seen >= target.max(1) {
+                return Duration::from_micros(1 << bucket);
+            }
+        }
+
+        self.max
+    }
+}
+
+/// How long each kind of operation took, as returned by `timings`.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpTimings
+{
+    pub get: LatencyStats,
+    pub set: LatencyStats,
+    pub update: LatencyStats,
+    pub delete: LatencyStats,
+}
+
+/// The operations `OpTimers` keeps a histogram for.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum TimedOp
+{
+    Get,
+    Set,
+    Update,
+    Delete,
+}
+
+/// A latency histogram per `TimedOp`, shared by every clone of a database.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+pub(crate) struct OpTimers
+{
+    histograms: Mutex<[LatencyHistogram; 4]>,
+}
+
+#[cfg(feature = "metrics")]
+impl OpTimers
+{
+    /// Starts timing `op`, the time is recorded when the returned guard is dropped.
+    pub(crate) fn start(self: &Arc<Self>, op: TimedOp) -> OpTimer
+    {
+        OpTimer {
+            timers: self.clone(),
+            op,
+            started: Instant::now(),
+        }
+    }
+
+    pub(crate) fn timings(&self) -> OpTimings
+    {
+        let histograms = lock(&self.histograms);
+
+        OpTimings {
+            get: histograms[TimedOp::Get as usize].stats(),
+            set: histograms[TimedOp::Set as usize].stats(),
+            update: histograms[TimedOp::Update as usize].stats(),
+            delete: histograms[TimedOp::Delete as usize].stats(),
+        }
+    }
+}
+
+/// Records how long an operation took when dropped, see `OpTimers::start`.
+#[cfg(feature = "metrics")]
+pub(crate) struct OpTimer
+{
+    timers: Arc<OpTimers>,
+    op: TimedOp,
+    started: Instant,
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for OpTimer
+{
+    fn drop(&mut self)
+    {
+        lock(&self.timers.histograms)[self.op as usize].record(self.started.elapsed());
+    }
+}
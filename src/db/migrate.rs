@@ -0,0 +1,105 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::io::{self, BufRead, Cursor};
+
+use bincode::Options;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::db::codec::{EntryCodec, SerializationFormat};
+use crate::db::entry::Entry;
+use crate::error::QuickKvError;
+
+/// An entry as it was written before files had a header, without `created_at` and `updated_at`.
+#[derive(Deserialize)]
+struct LegacyEntry<T>
+{
+    key: String,
+    data: T,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+/// Decodes the records of a file written before the header was added.
+///
+/// Their creation and update times were never recorded, so they're set to the Unix epoch.
+/// Fails with `QuickKvError::BadFormat` if `bytes` aren't legacy records either.
+pub(crate) fn decode_legacy<T>(bytes: &[u8], format: SerializationFormat) -> anyhow::Result<Vec<Entry<T>>>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + 'static,
+{
+    let bad_format = |e: &dyn std::fmt::Display| QuickKvError::BadFormat {
+        reason: format!("the file has no header and isn't a legacy database either ({})", e),
+    };
+
+    match format {
+        SerializationFormat::Bincode => {
+            let mut cursor = Cursor::new(bytes);
+            let mut entries = Vec::new();
+
+            while (cursor.position() as usize) < bytes.len() {
+                // Without the limit a garbage length prefix could ask for an enormous allocation.
+                let remaining = bytes.len() as u64 - cursor.position();
+
+                let legacy: LegacyEntry<T> = bincode::DefaultOptions::new()
+                    .with_fixint_encoding()
+                    .allow_trailing_bytes()
+                    .with_limit(remaining)
+                    .deserialize_from(&mut cursor)
+                    .map_err(|e| bad_format(&e))?;
+
+                entries.push(Entry {
+                    key: legacy.key,
+                    data: legacy.data,
+                    expires_at: legacy.expires_at,
+                    created_at: DateTime::default(),
+                    updated_at: DateTime::default(),
+                });
+            }
+
+            Ok(entries)
+        }
+        // JSON records name their fields, the missing timestamps are filled in by `Entry`'s
+        // `Deserialize` impl.
+        SerializationFormat::Json => {
+            let codec = format.codec::<T>();
+            let mut cursor = Cursor::new(bytes);
+            let mut entries = Vec::new();
+
+            while let Some(entry) = codec.decode(&mut cursor).map_err(|e| bad_format(&e))? {
+                entries.push(entry);
+            }
+
+            Ok(entries)
+        }
+    }
+}
+
+/// Bincode records as written by format version 1, one after the other without a length.
+pub(crate) struct UnframedBincodeCodec;
+
+impl<T> EntryCodec<T> for UnframedBincodeCodec
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync,
+{
+    fn encode(&self, entry: &Entry<T>) -> anyhow::Result<Vec<u8>>
+    {
+        Ok(bincode::serialize(entry)?)
+    }
+
+    fn decode(&self, reader: &mut dyn BufRead) -> anyhow::Result<Option<Entry<T>>>
+    {
+        match bincode::deserialize_from::<_, Entry<T>>(reader) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(e) => {
+                if let bincode::ErrorKind::Io(io_err) = e.as_ref() {
+                    if io_err.kind() == io::ErrorKind::UnexpectedEof {
+                        return Ok(None);
+                    }
+                }
+
+                Err(e.into())
+            }
+        }
+    }
+}
@@ -1,27 +1,90 @@
+use std::borrow::Cow;
 use std::fmt::Debug;
 use std::fs::{File, OpenOptions};
 use std::hash::Hash;
-use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::ops::Bound;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Once, RwLock};
+use std::time::{Duration, Instant};
 
 use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use fs2::FileExt;
 use log::LevelFilter;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use simple_logger::SimpleLogger;
 use time::macros::format_description;
 
+use self::batcher::{Batch, BatchOp};
+use self::codec::{BincodeCodec, Compression, EntryCodec, SerializationFormat};
 use self::config::DatabaseConfiguration;
+use self::eviction::{AccessOrder, EvictionPolicy};
+use self::flush::{sync_writer, FlushManager};
+use self::idempotency::RecentTokens;
+use self::logging::{JsonLogger, LogFormat, OperationLog};
+use self::metrics::{DbStats, LatencyHistogram, LatencyStats};
+#[cfg(feature = "metrics")]
+use self::metrics::{OpTimers, OpTimings, TimedOp};
+use self::pipeline::{PipelineOp, PipelineResult};
+use self::recovery::{CorruptionPolicy, LoadReport, LoadedFile};
 use self::runtime::RuntTimeType;
+use self::shared::{OpenFiles, SharedFile};
+use self::ttl::{TTLManager, DEFAULT_TTL_INTERVAL};
+use self::watch::{ChangeListener, Listeners, NotifyOnDrop};
 use crate::db::entry::Entry;
 use crate::db::state::State;
+use crate::error::{lock, read_lock, write_lock, QuickKvError};
+use crate::types::{AsKey, EntryMeta, HashMap, KeyStatus};
 
 pub(crate) mod batcher;
+#[cfg(feature = "bloom")]
+pub(super) mod bloom;
+pub(crate) mod codec;
 pub(crate) mod config;
+pub(crate) mod encryption;
 pub(super) mod entry;
+pub(crate) mod eviction;
+pub(super) mod flush;
+pub(super) mod header;
+pub(super) mod idempotency;
+pub(super) mod index;
+pub(crate) mod logging;
+pub(crate) mod metrics;
+pub(super) mod migrate;
+pub(crate) mod pipeline;
+pub(crate) mod recovery;
 pub(super) mod runtime;
+mod shared;
 pub(super) mod state;
+pub(super) mod ttl;
+pub(crate) mod watch;
+
+/// The percentage of stale records that triggers a rewrite of the database file.
+pub(crate) const DEFAULT_COMPACTION_THRESHOLD: u8 = 50;
+
+/// The size of the file buffers when `io_buffer_size` isn't set, the same as `std::io`'s.
+pub(crate) const DEFAULT_IO_BUFFER_SIZE: usize = 8 * 1024;
+
+/// The largest record `import` accepts, larger length prefixes are treated as a corrupt stream.
+pub(crate) const MAX_IMPORT_RECORD_LEN: u64 = 512 * 1024 * 1024;
+
+/// Separates a namespace from the key in the stored key, `"{namespace}\0{key}"`.
+pub(crate) const NAMESPACE_SEPARATOR: char = '\0';
+
+/// How one operation of a batch changes which keys exist, see `check_capacity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyChange
+{
+    /// Stores the key, adding it if it is missing.
+    Write,
+    /// Stores the key only if it already exists.
+    Overwrite,
+    /// Removes the key.
+    Remove,
+}
 
 /// A signal sent to the background task.
 #[allow(dead_code)]
@@ -40,10 +103,44 @@ pub(crate) struct Database<T>
 where
     T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
 {
-    pub(super) state: Arc<Mutex<State<T>>>,
+    pub(super) state: Arc<RwLock<State<T>>>,
     pub(super) config: DatabaseConfiguration,
     pub(super) writer: Option<Arc<Mutex<BufWriter<File>>>>,
     pub(super) reader: Option<Arc<Mutex<BufReader<File>>>>,
+    pub(super) ttl_manager: Arc<TTLManager>,
+    /// Syncs coalesced writes in the background, only running when `flush_interval` is set.
+    pub(super) flush_manager: Option<Arc<FlushManager>>,
+    /// How many times writes synced the database file, including syncs by `flush_manager`.
+    pub(super) syncs: Arc<AtomicUsize>,
+    /// Disk write latencies, only tracked when enabled in the configuration.
+    pub(super) write_latency: Option<Arc<Mutex<LatencyHistogram>>>,
+    /// How long `get`, `set`, `update` and `delete` took, see `timings`.
+    #[cfg(feature = "metrics")]
+    pub(super) timers: Arc<OpTimers>,
+    /// Idempotency tokens already applied by `set_idempotent`.
+    pub(super) idempotency_tokens: Arc<Mutex<RecentTokens>>,
+    /// Every live record in the file with its stored key, only kept when a namespace is set.
+    ///
+    /// Shared with the other namespaces open on the file, so a rewrite by one of them keeps
+    /// the others' records. Updated under the writer lock along with the file.
+    pub(super) namespaces: Arc<Mutex<State<T>>>,
+    /// How many records the database file holds, including stale ones left behind by appends.
+    pub(super) file_records: Arc<AtomicUsize>,
+    /// Set once eviction removed keys whose records are still live in the file, the next write
+    /// rewrites the file so they don't come back on reload.
+    pub(super) evicted: Arc<AtomicBool>,
+    /// When keys were last used, only tracked when `max_entries` is set with `EvictionPolicy::Lru`.
+    pub(super) access_order: Option<Arc<Mutex<AccessOrder>>>,
+    /// Callbacks registered with `on_change`.
+    pub(super) listeners: Listeners,
+    /// How many records were read and skipped when the database file was loaded.
+    pub(super) load_report: LoadReport,
+    /// Flushes the file and stops the background task once the last clone drops.
+    #[allow(dead_code)]
+    pub(super) flush_on_drop: Arc<FlushOnDrop>,
+    /// The lock on `<path>.lock` keeping other writers out, released when the last clone drops.
+    #[allow(dead_code)]
+    pub(super) file_lock: Option<Arc<File>>,
 }
 
 impl<T> Database<T>
@@ -55,64 +152,138 @@ where
         let config_clone = config.clone();
 
         if config.log.unwrap_or_default() {
-            SimpleLogger::new()
-                .with_colors(true)
-                .with_level(config.log_level.unwrap_or(LevelFilter::Info))
-                .with_timestamp_format(format_description!("[year]-[month]-[day] [hour]:[minute]:[second]"))
-                .init()?;
+            init_logger(
+                config.log_level.unwrap_or(LevelFilter::Info),
+                config.log_format.unwrap_or_default(),
+            );
         }
 
         log::info!("[Bootstrap] Building Database State");
 
-        // Create file as an Option<File> based on runtime
-        let file = if config
+        let disk = config
             .runtime
             .as_ref()
             .map(|rt| rt._type == RuntTimeType::Disk)
-            .unwrap_or(false)
-        {
+            .unwrap_or_default();
+
+        // A read-only database must already exist, there is nothing to create it with.
+        let writable = !config.read_only.unwrap_or_default();
+
+        let path = config.path.clone().unwrap_or_default();
+
+        if disk {
+            config::ensure_not_directory(&path)?;
+        }
+
+        // Namespaces opened on a file this process already has open share its handles rather
+        // than locking it again. The registry stays locked until this one is registered.
+        let mut open_files = (disk && writable && config.namespace.is_some()).then(OpenFiles::lock);
+
+        let shared = match open_files {
+            Some(ref mut open_files) => {
+                open_files.join::<T>(&path, config.format.unwrap_or_default(), config.compression)?
+            }
+            None => None,
+        };
+
+        let mut file_lock = shared.as_ref().map(|shared| shared.file_lock.clone());
+
+        // Create file as an Option<File> based on runtime
+        let file = if disk && shared.is_none() {
+            if writable {
+                file_lock = Some(Arc::new(lock_database(&path)?));
+            }
+
             log::debug!("[Bootstrap] Database file created or opened!");
-            Some(
-                OpenOptions::new()
-                    .read(true)
-                    .write(true)
-                    .create(true)
-                    .open(config.path.clone().unwrap_or_default())?,
-            )
+            Some(OpenOptions::new().read(true).write(writable).create(writable).open(&path)?)
         } else {
             None
         };
 
-        // let (sender, receiver) = mpsc::channel::<TTLSignal>();
+        let state = Arc::new(RwLock::new(State::with_capacity(config.initial_capacity.unwrap_or_default())));
+
+        let buffer_size = config.io_buffer_size.unwrap_or(DEFAULT_IO_BUFFER_SIZE);
+
+        let listeners = Listeners::default();
+
+        let ttl_manager = TTLManager::spawn(
+            state.clone(),
+            listeners.clone(),
+            config.ttl_interval.unwrap_or(DEFAULT_TTL_INTERVAL),
+        )?;
+
+        let writer = match (&shared, &file) {
+            (Some(shared), _) => Some(shared.writer.clone()),
+            (None, Some(file)) if writable => {
+                Some(Arc::new(Mutex::new(BufWriter::with_capacity(buffer_size, file.try_clone()?))))
+            }
+            _ => None,
+        };
+
+        let reader = match (&shared, &file) {
+            (Some(shared), _) => Some(shared.reader.clone()),
+            (None, Some(file)) => Some(Arc::new(Mutex::new(BufReader::with_capacity(buffer_size, file.try_clone()?)))),
+            _ => None,
+        };
+
+        let ttl_manager = Arc::new(ttl_manager);
+
+        let syncs = match shared {
+            Some(ref shared) => shared.syncs.clone(),
+            None => Arc::new(AtomicUsize::new(0)),
+        };
+
+        let flush_manager = match (&shared, &writer, config.flush_interval) {
+            (Some(shared), _, _) => shared.flush_manager.clone(),
+            (None, Some(writer), Some(interval)) => {
+                Some(Arc::new(FlushManager::spawn(writer.clone(), syncs.clone(), interval)?))
+            }
+            _ => None,
+        };
 
         let mut output = Self {
-            state: Arc::new(Mutex::new(State::new())),
+            state,
             config: config_clone.clone(),
-            writer: if config_clone
-                .runtime
-                .as_ref()
-                .map(|rt| rt._type == RuntTimeType::Disk)
-                .unwrap_or_default()
-            {
-                let file_clone = file.as_ref().map(|f| f.try_clone()).transpose()?;
-                Some(Arc::new(Mutex::new(BufWriter::new(file_clone.unwrap()))))
+            flush_on_drop: Arc::new(FlushOnDrop {
+                writer: writer.clone(),
+                ttl_manager: ttl_manager.clone(),
+            }),
+            writer,
+            reader,
+            ttl_manager,
+            flush_manager,
+            syncs,
+            write_latency: if config.track_write_latency.unwrap_or_default() {
+                Some(Arc::new(Mutex::new(LatencyHistogram::default())))
             } else {
                 None
             },
-            reader: if config
-                .runtime
-                .as_ref()
-                .map(|rt| rt._type == RuntTimeType::Disk)
-                .unwrap_or_default()
-            {
-                let file_clone2 = file.as_ref().map(|f| f.try_clone()).transpose()?;
-                Some(Arc::new(Mutex::new(BufReader::new(file_clone2.unwrap()))))
-            } else {
-                None
+            #[cfg(feature = "metrics")]
+            timers: Arc::default(),
+            idempotency_tokens: Arc::new(Mutex::new(RecentTokens::default())),
+            namespaces: match shared {
+                Some(ref shared) => shared.namespaces.clone(),
+                None => Arc::new(Mutex::new(State::new())),
+            },
+            file_records: match shared {
+                Some(ref shared) => shared.file_records.clone(),
+                None => Arc::new(AtomicUsize::new(0)),
             },
+            evicted: Arc::new(AtomicBool::new(false)),
+            access_order: match (config.max_entries, config.eviction) {
+                (Some(_), Some(EvictionPolicy::Lru)) => Some(Arc::new(Mutex::new(AccessOrder::default()))),
+                _ => None,
+            },
+            listeners,
+            load_report: LoadReport::default(),
+            file_lock,
         };
 
-        output.load_db_into_cache()?;
+        output.load_db_into_cache(shared.is_some())?;
+
+        if let (Some(open_files), None) = (open_files.as_mut(), shared) {
+            output.register(open_files, &path)?;
+        }
 
         log::info!("[Bootstrap] QuickKVClient Initialized!");
 
@@ -121,381 +292,3691 @@ where
 
     pub(crate) fn get(&mut self, key: String) -> anyhow::Result<Option<T>>
     {
+        let mut op = OperationLog::start("GET", &key);
+        #[cfg(feature = "metrics")]
+        let _timer = self.timers.start(TimedOp::Get);
+        let _notify = self.notify_on_drop();
+        op.outcome = "miss";
+
         log::debug!("[GET] Searching for key: {}", key);
 
-        // self.ttl_manager.send(TTLSignal::Check)?;
+        {
+            let state = read_lock(&self.state);
+
+            if !state.may_contain(&key) {
+                log::debug!("[GET] Key not found: {}", key);
+                return Ok(None);
+            }
 
-        let state = self.state.lock().unwrap();
+            match state.entries.get(&key) {
+                Some(entry) if !entry.is_expired() => {
+                    log::debug!("[GET] Found key: {}", key);
+                    op.outcome = "hit";
+                    self.touch(&key);
+                    return Ok(Some(entry.data.clone()));
+                }
+                Some(_) => {}
+                None => return Ok(None),
+            }
+        }
 
-        if let Some(entry) = state.entries.get(&key) {
-            log::debug!("[GET] Found key: {}", key);
-            return Ok(Some(entry.data.clone()));
+        // Don't hand out values the background task hasn't gotten to yet. Evicting needs the
+        // write lock, so the entry is checked again in case it was replaced in between.
+        if write_lock(&self.state).remove_if_expired(&key) {
+            log::debug!("[GET] Key expired: {}", key);
         }
 
         Ok(None)
-
-        // Maybe we will check file, if no cache is found. Although for now this should
-        // Never happen so we will just return None if nothing is found.
     }
 
-    pub(crate) fn set(&mut self, key: &str, value: T, ttl: Option<Duration>) -> anyhow::Result<()>
+    /// Returns the value of `key` along with when it expires, read under one lock so the two
+    /// always belong together.
+    pub(crate) fn get_with_meta(&mut self, key: &str) -> anyhow::Result<Option<(T, Option<DateTime<Utc>>)>>
     {
-        log::debug!("[SET] Attempting set: {}", key);
+        log::debug!("[GET] Searching for key: {}", key);
 
-        // First check if the data already exists; if so, update it instead
-        let mut state = self.state.lock().unwrap();
+        let state = read_lock(&self.state);
 
-        let expires_at: Option<DateTime<Utc>> = self.get_ttl(ttl)?;
+        let found = state
+            .entries
+            .get(key)
+            .filter(|entry| !entry.is_expired())
+            .map(|entry| (entry.data.clone(), entry.expires_at));
 
-        // Build the entry
-        let entry = Entry::new(key.to_string(), value, expires_at);
+        if found.is_some() {
+            self.touch(key);
+        }
 
-        // Set the entry in the state
-        state.entries.insert(key.to_string(), entry.clone());
+        Ok(found)
+    }
 
-        if let Some(expires_at) = entry.expires_at {
-            state.expirations.insert((expires_at, key.to_string()));
+    /// Passes a borrow of the value of `key` to `f` and returns what it returns, without
+    /// cloning the value.
+    ///
+    /// `f` runs under the state's read lock, so it must not call back into the database.
+    pub(crate) fn with_value<R, F>(&mut self, key: &str, f: F) -> anyhow::Result<Option<R>>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        log::debug!("[GET] Searching for key: {}", key);
+
+        let state = read_lock(&self.state);
+
+        let result = state
+            .entries
+            .get(key)
+            .filter(|entry| !entry.is_expired())
+            .map(|entry| f(&entry.data));
+
+        if result.is_some() {
+            self.touch(key);
         }
 
-        if self.is_disk_runtime() {
-            if let Some(ref writer) = self.writer {
-                // Serialize the entry and write it to the file
-                let mut w = writer.lock().unwrap();
+        Ok(result)
+    }
+
+    /// Returns the value of `key`, waiting up to `timeout` for it to be set if it isn't yet.
+    ///
+    /// Sleeps until something is inserted instead of polling, returns `None` if `key` still
+    /// doesn't exist once `timeout` has passed.
+    pub(crate) fn get_or_wait(&mut self, key: &str, timeout: Duration) -> anyhow::Result<Option<T>>
+    {
+        let inserted = read_lock(&self.state).inserted.clone();
+        let deadline = Instant::now() + timeout;
 
-                w.seek(SeekFrom::End(0))?; // Seek to the end of the file (append)
-                w.write_all(&bincode::serialize(&entry)?)?;
+        loop {
+            let seen = inserted.count();
 
-                // Flush the writer and sync the file
-                w.flush()?;
-                w.get_ref().sync_all()?;
+            if let Some(value) = self.get(key.to_string())? {
+                return Ok(Some(value));
             }
-        }
 
-        log::info!("[SET] Key set: {}", key);
+            let remaining = deadline.saturating_duration_since(Instant::now());
 
-        Ok(())
+            if remaining.is_zero() || !inserted.wait_past(seen, remaining) {
+                log::debug!("[GET_OR_WAIT] Timed out waiting for key: {}", key);
+                return Ok(None);
+            }
+        }
     }
 
-    pub(crate) fn update(&mut self, key: &str, value: T, ttl: Option<Duration>, upsert: Option<bool>) -> anyhow::Result<()>
+    /// Looks up a key and reports whether it is present, expired or absent.
+    ///
+    /// An expired entry is evicted from the cache once it has been reported.
+    pub(crate) fn get_status(&mut self, key: &str) -> anyhow::Result<KeyStatus<T>>
     {
-        log::debug!("[UPDATE] Attempting {} update...", key);
+        let _notify = self.notify_on_drop();
+
+        log::debug!("[GET_STATUS] Searching for key: {}", key);
 
-        let mut state = self.state.lock().unwrap();
+        let mut state = write_lock(&self.state);
 
         if !state.entries.contains_key(key) {
-            log::debug!("[UPDATE] Key not found: {}", key);
-            return Ok(());
+            return Ok(KeyStatus::Absent);
         }
 
-        if let Some(u) = upsert {
-            if !u {
-                log::debug!("[UPDATE] Upsert not enabled, skipping update");
-                return Ok(());
-            }
+        if state.remove_if_expired(key) {
+            log::debug!("[GET_STATUS] Key expired: {}", key);
+            return Ok(KeyStatus::Expired);
         }
 
-        let entry: Entry<T> = Entry::new(key.to_string(), value.clone(), None);
+        Ok(KeyStatus::Present(state.entries[key].data.clone()))
+    }
 
-        state.entries.insert(key.to_string(), entry.clone());
+    pub(crate) fn exists(&mut self, key: &str) -> anyhow::Result<bool>
+    {
+        let state = read_lock(&self.state);
 
-        if let Some(expires_at) = entry.expires_at {
-            state.expirations.insert((expires_at, key.to_string()));
-        }
+        Ok(Self::contains_live(&state, key))
+    }
 
-        if self.is_disk_runtime() {
-            let mut updated_bytes = Vec::new();
-            if let Some(ref reader) = self.reader {
-                let mut r = reader.lock().unwrap();
-
-                r.seek(SeekFrom::Start(0))?;
-
-                loop {
-                    match bincode::deserialize_from::<_, Entry<T>>(&mut r.get_mut()) {
-                        Ok(entry) => {
-                            if key == entry.key {
-                                // Update the value associated with the key
-                                updated_bytes.push(Entry::new(key.to_string(), value.clone(), self.get_ttl(ttl)?));
-                            } else {
-                                updated_bytes.push(entry)
-                            }
-                        }
-                        Err(e) => {
-                            if let bincode::ErrorKind::Io(io_err) = e.as_ref() {
-                                if io_err.kind() == io::ErrorKind::UnexpectedEof {
-                                    // Reached the end of the serialized data
-                                    break;
-                                } else {
-                                    return Err(e.into());
-                                }
-                            }
-                        }
-                    }
-                }
+    /// Checks every key in `keys` under a single lock, the results are in the same order.
+    pub(crate) fn exists_many<K: AsKey>(&mut self, keys: &[K]) -> anyhow::Result<Vec<bool>>
+    {
+        let state = read_lock(&self.state);
 
-                drop(r);
-            }
+        Ok(keys.iter().map(|key| Self::contains_live(&state, &key.as_key())).collect())
+    }
+
+    /// Whether `key` holds an entry that hasn't expired.
+    ///
+    /// Expired entries are left for the background task, so this only needs a read lock.
+    fn contains_live(state: &State<T>, key: &str) -> bool
+    {
+        state.may_contain(key) && state.entries.get(key).is_some_and(|entry| !entry.is_expired())
+    }
 
-            if let Some(ref writer) = self.writer {
-                let mut w = writer.lock().unwrap();
+    /// Returns how long `key` has left before it expires, or `None` if it has no ttl or
+    /// does not exist. Keys past their expiry that haven't been removed yet report zero.
+    pub(crate) fn ttl(&self, key: &str) -> anyhow::Result<Option<Duration>>
+    {
+        let state = read_lock(&self.state);
 
-                w.seek(SeekFrom::Start(0))?;
+        let remaining = state
+            .entries
+            .get(key)
+            .and_then(|entry| entry.expires_at)
+            .map(|expires_at| (expires_at - Utc::now()).to_std().unwrap_or(Duration::ZERO));
 
-                for entry in updated_bytes {
-                    w.write_all(&bincode::serialize(&entry)?)?;
-                }
+        Ok(remaining)
+    }
 
-                w.flush()?;
-                w.get_ref().sync_all()?;
-            }
+    /// Returns when `key` was created, last updated and when it expires, or `None` if it
+    /// doesn't exist or has expired.
+    pub(crate) fn metadata(&mut self, key: &str) -> anyhow::Result<Option<EntryMeta>>
+    {
+        let state = read_lock(&self.state);
+
+        let meta = state
+            .entries
+            .get(key)
+            .filter(|entry| !entry.is_expired())
+            .map(|entry| EntryMeta {
+                created_at: entry.created_at,
+                updated_at: entry.updated_at,
+                expires_at: entry.expires_at,
+            });
+
+        Ok(meta)
+    }
+
+    pub(crate) fn default_ttl(&self) -> Option<Duration>
+    {
+        self.config.default_ttl
+    }
+
+    /// The path of the database file, `None` for an in-memory database.
+    pub(crate) fn path(&self) -> Option<&str>
+    {
+        if self.is_disk_runtime() {
+            self.config.path.as_deref()
+        } else {
+            None
         }
+    }
 
-        log::info!("[UPDATE] Key updated: {}", key);
+    pub(crate) fn is_in_memory(&self) -> bool
+    {
+        !self.is_disk_runtime()
+    }
 
-        Ok(())
+    pub(crate) fn set(&mut self, key: &str, value: T, ttl: Option<Duration>) -> anyhow::Result<()>
+    {
+        let expires_at = self.get_ttl(ttl)?;
+
+        self.set_with_expiry(key, value, expires_at)
     }
 
-    pub(crate) fn delete(&mut self, key: &str) -> anyhow::Result<()>
+    /// Sets `key` to expire at `expires_at` instead of after a ttl, `None` never expires.
+    ///
+    /// A time in the past is stored as is, the key reads as expired straight away.
+    pub(crate) fn set_with_expiry(&mut self, key: &str, value: T, expires_at: Option<DateTime<Utc>>) -> anyhow::Result<()>
     {
-        log::debug!("[DELETE] Deleting key: {}", key);
+        let mut op = OperationLog::start("SET", key);
+        #[cfg(feature = "metrics")]
+        let _timer = self.timers.start(TimedOp::Set);
+        let _notify = self.notify_on_drop();
 
-        let mut state = self.state.lock().unwrap();
+        self.ensure_writable()?;
+        self.check_key(key)?;
 
-        if !state.entries.contains_key(key) {
-            log::debug!("[DELETE] Key not found: {}", key);
-            return Ok(());
-        }
+        log::debug!("[SET] Attempting set: {}", key);
 
-        state.entries.remove(key);
+        // First check if the data already exists; if so, update it instead
+        let mut state = write_lock(&self.state);
 
-        if self.is_disk_runtime() {
-            let mut new_buff = Vec::new();
+        self.make_room(&mut state, key)?;
 
-            if let Some(ref reader) = self.reader {
-                let mut r = reader.lock().unwrap();
-
-                // todo - Iterate over the file and remove the entry
-                // todo - later we need to find a better solution for this as its not preformat to iterate over the whole database
-                // todo - just to delete some data. Maybe we can use a linked list or something else? But for now this will do.
-                loop {
-                    match bincode::deserialize_from::<_, Entry<T>>(&mut r.get_mut()) {
-                        Ok(Entry { key: entry_key, .. }) => {
-                            if entry_key != key {
-                                new_buff.append(&mut bincode::serialize(&entry_key)?);
-                            } else {
-                                // Skip this entry
-                                continue;
-                            }
-                        }
-                        Err(e) => {
-                            if let bincode::ErrorKind::Io(io_err) = e.as_ref() {
-                                if io_err.kind() == io::ErrorKind::UnexpectedEof {
-                                    // Reached the end of the serialized data
-                                    break;
-                                } else {
-                                    return Err(e.into());
-                                }
-                            }
-                        }
-                    }
-                }
+        // Build the entry
+        let entry = Entry::new(key.to_string(), value, expires_at);
 
-                // Drop the reader so we can write to the file
-                drop(r);
-            }
+        // Set the entry in the state
+        state.insert(entry.clone());
 
-            if let Some(ref writer) = self.writer {
-                // Write the new buffer to the file and sync it
-                let mut w = writer.lock().unwrap();
-                w.seek(SeekFrom::Start(0))?; // Seek to the beginning of the file
-                w.write_all(&new_buff)?;
-                w.flush()?;
-                w.get_ref().sync_all()?;
-            }
+        if self.is_disk_runtime() {
+            self.persist(&state, &[&entry])?;
         }
 
-        log::info!("[DELETE] Key deleted: {}", key);
+        log::info!("[SET] Key set: {}", key);
+        op.outcome = "ok";
 
         Ok(())
     }
 
-    pub(crate) fn purge(&mut self) -> anyhow::Result<()>
+    /// Sets `key` and returns the value it held before, like `HashMap::insert`.
+    ///
+    /// Returns `None` if the key was missing or had expired.
+    pub(crate) fn set_and_get_previous(&mut self, key: &str, value: T, ttl: Option<Duration>) -> anyhow::Result<Option<T>>
     {
-        log::debug!("[PURGE] Purging database");
+        let _notify = self.notify_on_drop();
 
-        let mut state = self.state.lock().unwrap();
+        self.ensure_writable()?;
+        self.check_key(key)?;
 
-        state.entries.clear();
-        state.expirations.clear();
+        log::debug!("[SET] Attempting set: {}", key);
+
+        let mut state = write_lock(&self.state);
+
+        let expires_at = self.get_ttl(ttl)?;
+
+        state.remove_if_expired(key);
+
+        self.make_room(&mut state, key)?;
+
+        let entry = Entry::new(key.to_string(), value, expires_at);
+
+        let previous = state.insert(entry.clone());
 
         if self.is_disk_runtime() {
-            if let Some(ref writer) = self.writer {
-                let mut w = writer.lock().unwrap();
-                w.seek(SeekFrom::Start(0))?; // Seek to the beginning of the file
-                w.write_all(&[])?;
-                w.flush()?;
-                w.get_ref().sync_all()?;
-            }
+            self.persist(&state, &[&entry])?;
         }
 
-        log::info!("[PURGE] Database purged");
+        log::info!("[SET] Key set: {}", key);
 
-        Ok(())
+        Ok(previous.map(|entry| entry.data))
     }
 
-    /// Gets the current ttl if it exists.
-    /// Function will also try the default ttl if configured else it will return None.
-    fn get_ttl(&self, ttl: Option<Duration>) -> anyhow::Result<Option<DateTime<Utc>>>
+    /// Sets every entry under a single lock and writes them to the file in one go.
+    ///
+    /// A later entry for the same key overwrites an earlier one, like calling `set` in order.
+    pub(crate) fn set_batch(&mut self, entries: Vec<(String, T, Option<Duration>)>) -> anyhow::Result<()>
     {
-        if let Some(ttl) = ttl {
-            Ok(Some(Utc::now() + chrono::Duration::from_std(ttl)?))
-        } else if let Some(default_ttl) = self.config.default_ttl {
-            Ok(Some(Utc::now() + chrono::Duration::from_std(default_ttl)?))
-        } else {
-            Ok(None)
-        }
-    }
+        let _notify = self.notify_on_drop();
 
-    /// Checks if we need to use disk operations, the default is disk.
-    fn is_disk_runtime(&self) -> bool
-    {
-        if let Some(r) = &self.config.runtime {
-            match r._type {
-                RuntTimeType::Memory => false,
-                RuntTimeType::Disk => true,
-            }
-        } else {
-            true
-        }
-    }
+        self.ensure_writable()?;
 
-    fn load_db_into_cache(&mut self) -> anyhow::Result<()>
-    {
-        if let Some(ref reader) = self.reader {
-            let mut cached_count = 0;
+        for (key, _, _) in &entries {
+            self.check_key(key)?;
+        }
 
-            let mut r = reader.lock().unwrap();
+        log::debug!("[SET_BATCH] Attempting set of {} keys", entries.len());
 
-            r.seek(SeekFrom::Start(0))?; // Seek to the beginning of the file
+        let entries = entries
+            .into_iter()
+            .map(|(key, value, ttl)| Ok((key, value, self.get_ttl(ttl)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
 
-            loop {
-                match bincode::deserialize_from::<_, Entry<T>>(&mut r.get_mut()) {
-                    Ok(entry) => {
-                        let mut state = self.state.lock().unwrap();
+        let mut state = write_lock(&self.state);
 
-                        state.entries.insert(entry.key.clone(), entry.clone());
+        self.check_capacity(&mut state, entries.iter().map(|(key, _, _)| (key.as_str(), KeyChange::Write)))?;
 
-                        if let Some(expires_at) = entry.expires_at {
-                            state.expirations.insert((expires_at, entry.key.clone()));
-                        }
+        let mut written = Vec::with_capacity(entries.len());
 
-                        cached_count += 1;
-                    }
-                    Err(e) => {
-                        if let bincode::ErrorKind::Io(io_err) = e.as_ref() {
-                            if io_err.kind() == io::ErrorKind::UnexpectedEof {
-                                // Reached the end of the serialized data
-                                break;
-                            } else {
-                                return Err(e.into());
-                            }
-                        }
-                    }
-                }
-            }
+        for (key, value, expires_at) in entries {
+            self.make_room(&mut state, &key)?;
 
-            drop(r);
+            let entry = Entry::new(key, value, expires_at);
+            state.insert(entry.clone());
+            written.push(entry);
+        }
 
-            log::debug!("[Bootstrap] Loaded {} entries into cache", cached_count);
+        if self.is_disk_runtime() {
+            self.persist(&state, &written.iter().collect::<Vec<_>>())?;
         }
 
+        log::info!("[SET_BATCH] Set {} keys", written.len());
+
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests
-{
-    use anyhow::Result;
-    use tempfile::tempdir;
-
-    use super::*;
-
-    #[test]
-    fn test_database_new() -> Result<()>
+    /// Sets `key` unless the idempotency token `idem` was already applied.
+    ///
+    /// Returns `false` if the write was skipped as a retry.
+    pub(crate) fn set_idempotent(&mut self, key: &str, value: T, idem: &str) -> anyhow::Result<bool>
     {
-        let tmp_dir = tempdir().expect("Failed to create tempdir");
-        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+        // Held across the write so two copies of the same retry can't both get through.
+        let mut tokens = lock(&self.idempotency_tokens);
 
-        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
-        let db = Database::<String>::new(config.clone())?;
+        if tokens.contains(idem) {
+            log::debug!("[SET_IDEMPOTENT] Token already applied: {}", idem);
+            return Ok(false);
+        }
 
-        assert_eq!(db.config.path, config.path);
+        self.set(key, value, None)?;
 
-        Ok(())
+        tokens.insert(idem);
+
+        Ok(true)
     }
 
-    #[test]
-    fn test_database_get_set() -> Result<()>
+    /// Returns the value stored at `key`, or stores and returns the result of `f` if there is none.
+    ///
+    /// The state lock is held across the check and the insert, so `f` runs at most once
+    /// even when several callers race for the same key.
+    pub(crate) fn get_or_insert_with<F>(&mut self, key: &str, f: F) -> anyhow::Result<T>
+    where
+        F: FnOnce() -> T,
     {
-        let tmp_dir = tempdir().expect("Failed to create tempdir");
-        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+        let _notify = self.notify_on_drop();
 
-        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
-        let mut db = Database::<String>::new(config)?;
+        self.ensure_writable()?;
+        self.check_key(key)?;
 
-        db.set("test", "test".to_string(), None)?;
+        log::debug!("[GET_OR_INSERT] Searching for key: {}", key);
 
-        assert_eq!(db.get("test".to_string()).unwrap().unwrap(), "test".to_string());
+        let mut state = write_lock(&self.state);
 
-        Ok(())
+        state.remove_if_expired(key);
+
+        if let Some(entry) = state.entries.get(key) {
+            log::debug!("[GET_OR_INSERT] Found key: {}", key);
+            self.touch(key);
+            return Ok(entry.data.clone());
+        }
+
+        self.make_room(&mut state, key)?;
+
+        let entry = Entry::new(key.to_string(), f(), self.get_ttl(None)?);
+
+        state.insert(entry.clone());
+
+        if self.is_disk_runtime() {
+            self.persist(&state, &[&entry])?;
+        }
+
+        log::info!("[GET_OR_INSERT] Key set: {}", key);
+
+        Ok(entry.data)
     }
 
-    #[test]
-    fn test_database_update() -> Result<()>
+    /// Stores `new` at `key` only if the current value equals `expected`, where `None` means
+    /// the key must be absent. Returns whether the swap happened.
+    pub(crate) fn compare_and_swap(&mut self, key: &str, expected: Option<T>, new: T) -> anyhow::Result<bool>
     {
-        let tmp_dir = tempdir().expect("Failed to create tempdir");
-        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+        let _notify = self.notify_on_drop();
 
-        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        self.ensure_writable()?;
+        self.check_key(key)?;
 
-        let mut db = Database::<String>::new(config)?;
+        log::debug!("[CAS] Attempting swap: {}", key);
 
-        db.set("test", "test".to_string(), None)?;
+        let mut state = write_lock(&self.state);
 
-        let result = db.get("test".to_string())?.unwrap();
+        state.remove_if_expired(key);
 
-        assert_eq!(result, "test".to_string());
+        if state.entries.get(key).map(|entry| &entry.data) != expected.as_ref() {
+            log::debug!("[CAS] Value mismatch for key: {}", key);
+            return Ok(false);
+        }
 
-        db.update("test", "test2".to_string(), None, None)?;
+        self.make_room(&mut state, key)?;
 
-        let result = db.get("test".to_string())?.unwrap();
+        let entry = Entry::new(key.to_string(), new, self.get_ttl(None)?);
 
-        assert_eq!(result, "test2".to_string());
+        state.insert(entry.clone());
 
-        Ok(())
+        if self.is_disk_runtime() {
+            self.persist(&state, &[&entry])?;
+        }
+
+        log::info!("[CAS] Key swapped: {}", key);
+
+        Ok(true)
     }
 
-    #[test]
-    fn test_database_delete() -> Result<()>
+    /// Reads the current value of `key`, lets `f` compute the new one and stores it.
+    ///
+    /// The whole operation holds the state lock, so concurrent callers can't interleave
+    /// between the read and the write. Existing entries keep their ttl, new ones get the
+    /// default ttl. Returns the stored value.
+    pub(crate) fn read_modify_write<F>(&mut self, key: &str, f: F) -> anyhow::Result<T>
+    where
+        F: FnOnce(Option<&T>) -> anyhow::Result<T>,
     {
-        let tmp_dir = tempdir().expect("Failed to create tempdir");
-        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+        let _notify = self.notify_on_drop();
 
-        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        self.ensure_writable()?;
+        self.check_key(key)?;
 
-        let mut db = Database::<String>::new(config)?;
+        log::debug!("[READ_MODIFY_WRITE] Attempting update: {}", key);
 
-        db.set("test", "test".to_string(), None)?;
+        let mut state = write_lock(&self.state);
 
-        let result = db.get("test".to_string())?.unwrap();
+        state.remove_if_expired(key);
 
-        assert_eq!(result, "test".to_string());
+        let (value, expires_at) = match state.entries.get(key) {
+            Some(entry) => (f(Some(&entry.data))?, entry.expires_at),
+            None => (f(None)?, self.get_ttl(None)?),
+        };
+
+        self.make_room(&mut state, key)?;
+
+        let entry = Entry::new(key.to_string(), value.clone(), expires_at);
+
+        state.insert(entry.clone());
+
+        if self.is_disk_runtime() {
+            self.persist(&state, &[&entry])?;
+        }
+
+        log::info!("[READ_MODIFY_WRITE] Key updated: {}", key);
+
+        Ok(value)
+    }
+
+    /// `read_modify_write` over several keys under one lock, written to the file in one go.
+    ///
+    /// `f` gets the position of each key in `keys` and its current value, which for a key listed
+    /// twice is the value computed for its earlier position. Nothing is stored unless `f`
+    /// succeeds for every key. Returns the stored values in the order of `keys`.
+    pub(crate) fn read_modify_write_many<F>(&mut self, keys: &[&str], mut f: F) -> anyhow::Result<Vec<T>>
+    where
+        F: FnMut(usize, Option<&T>) -> anyhow::Result<T>,
+    {
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+
+        for key in keys {
+            self.check_key(key)?;
+        }
+
+        log::debug!("[READ_MODIFY_WRITE] Attempting update of {} keys", keys.len());
+
+        let mut state = write_lock(&self.state);
+
+        self.check_capacity(&mut state, keys.iter().map(|key| (*key, KeyChange::Write)))?;
+
+        let mut written: Vec<Entry<T>> = Vec::with_capacity(keys.len());
+
+        for (i, key) in keys.iter().enumerate() {
+            state.remove_if_expired(key);
+
+            let earlier = written.iter().rev().find(|entry| entry.key == *key);
+
+            let (value, expires_at) = match earlier.or_else(|| state.entries.get(*key)) {
+                Some(entry) => (f(i, Some(&entry.data))?, entry.expires_at),
+                None => (f(i, None)?, self.get_ttl(None)?),
+            };
+
+            written.push(Entry::new(key.to_string(), value, expires_at));
+        }
+
+        for entry in &written {
+            self.make_room(&mut state, &entry.key)?;
+            state.insert(entry.clone());
+        }
+
+        if self.is_disk_runtime() {
+            self.persist(&state, &written.iter().collect::<Vec<_>>())?;
+        }
+
+        log::info!("[READ_MODIFY_WRITE] Updated {} keys", written.len());
+
+        Ok(written.into_iter().map(|entry| entry.data).collect())
+    }
+
+    /// Passes the current value of `key` to `f` and stores what it returns, deleting the key
+    /// when it returns `None`.
+    ///
+    /// Like `read_modify_write` the whole operation holds the state lock. Existing entries keep
+    /// their ttl, new ones get the default ttl. Returns the new value.
+    pub(crate) fn merge<F>(&mut self, key: &str, f: F) -> anyhow::Result<Option<T>>
+    where
+        F: FnOnce(Option<T>) -> Option<T>,
+    {
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+        self.check_key(key)?;
+
+        log::debug!("[MERGE] Attempting merge: {}", key);
+
+        let mut state = write_lock(&self.state);
+
+        state.remove_if_expired(key);
+
+        let (current, expires_at) = match state.entries.get(key) {
+            Some(entry) => (Some(entry.data.clone()), entry.expires_at),
+            None => (None, self.get_ttl(None)?),
+        };
+
+        let merged = f(current);
+
+        match merged {
+            Some(ref value) => {
+                self.make_room(&mut state, key)?;
+
+                let entry = Entry::new(key.to_string(), value.clone(), expires_at);
+
+                state.insert(entry.clone());
+
+                if self.is_disk_runtime() {
+                    self.persist(&state, &[&entry])?;
+                }
+            }
+            None => {
+                // Deletes can't be appended, the file has to be rewritten without the key.
+                if state.remove(key).is_some() && self.is_disk_runtime() {
+                    self.rewrite_file(&state)?;
+                }
+            }
+        }
+
+        log::info!("[MERGE] Key merged: {}", key);
+
+        Ok(merged)
+    }
+
+    /// Runs `f` against a fresh `Batch` and applies its operations all at once.
+    ///
+    /// If `f` returns an error the batch is dropped and nothing is applied or written.
+    pub(crate) fn transaction<F>(&mut self, f: F) -> anyhow::Result<()>
+    where
+        F: FnOnce(&mut Batch<T>) -> anyhow::Result<()>,
+    {
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+
+        let mut batch = Batch::new();
+
+        f(&mut batch)?;
+
+        for op in &batch.ops {
+            match op {
+                BatchOp::Set { key, .. } | BatchOp::Update { key, .. } => self.check_key(key)?,
+                BatchOp::Delete { .. } => {}
+            }
+        }
+
+        log::debug!("[TRANSACTION] Applying {} operations", batch.len());
+
+        let expires_at = self.get_ttl(None)?;
+
+        let mut state = write_lock(&self.state);
+
+        let changes = batch.ops.iter().map(|op| match op {
+            BatchOp::Set { key, .. } => (key.as_str(), KeyChange::Write),
+            BatchOp::Update { key, upsert, .. } if *upsert == Some(true) => (key.as_str(), KeyChange::Write),
+            BatchOp::Update { key, .. } => (key.as_str(), KeyChange::Overwrite),
+            BatchOp::Delete { key } => (key.as_str(), KeyChange::Remove),
+        });
+        self.check_capacity(&mut state, changes)?;
+
+        let mut written = Vec::new();
+        let mut deleted = false;
+
+        for op in batch.ops {
+            match op {
+                BatchOp::Set { key, value } => {
+                    self.make_room(&mut state, &key)?;
+                    let entry = Entry::new(key, value, expires_at);
+                    state.insert(entry.clone());
+                    written.push(entry);
+                }
+                BatchOp::Update { key, value, upsert } => {
+                    state.remove_if_expired(&key);
+
+                    if !state.entries.contains_key(&key) && upsert != Some(true) {
+                        continue;
+                    }
+
+                    self.make_room(&mut state, &key)?;
+                    let entry = Entry::new(key, value, expires_at);
+                    state.insert(entry.clone());
+                    written.push(entry);
+                }
+                BatchOp::Delete { key } => deleted |= state.remove(&key).is_some(),
+            }
+        }
+
+        if self.is_disk_runtime() {
+            // Deletes can't be appended, so fall back to a single rewrite of the whole file.
+            if deleted {
+                self.rewrite_file(&state)?;
+            } else {
+                self.persist(&state, &written.iter().collect::<Vec<_>>())?;
+            }
+        }
+
+        log::info!("[TRANSACTION] Transaction committed");
+
+        Ok(())
+    }
+
+    /// Applies queued pipeline operations in order under one write lock, see `Pipeline`.
+    ///
+    /// Writes are persisted together once every operation has run.
+    pub(crate) fn execute_pipeline(&mut self, ops: Vec<PipelineOp<T>>) -> anyhow::Result<Vec<PipelineResult<T>>>
+    {
+        let _notify = self.notify_on_drop();
+
+        for op in &ops {
+            match op {
+                PipelineOp::Get { .. } => {}
+                PipelineOp::Set { key, .. } => {
+                    self.ensure_writable()?;
+                    self.check_key(key)?;
+                }
+                PipelineOp::Delete { .. } => self.ensure_writable()?,
+            }
+        }
+
+        log::debug!("[PIPELINE] Executing {} operations", ops.len());
+
+        let expires_at = self.get_ttl(None)?;
+
+        let mut state = write_lock(&self.state);
+
+        let changes = ops.iter().filter_map(|op| match op {
+            PipelineOp::Get { .. } => None,
+            PipelineOp::Set { key, .. } => Some((key.as_str(), KeyChange::Write)),
+            PipelineOp::Delete { key } => Some((key.as_str(), KeyChange::Remove)),
+        });
+        self.check_capacity(&mut state, changes)?;
+
+        let mut results = Vec::with_capacity(ops.len());
+        let mut written = Vec::new();
+        let mut deleted = false;
+
+        for op in ops {
+            let result = match op {
+                PipelineOp::Get { key } => {
+                    let value = state
+                        .entries
+                        .get(&key)
+                        .filter(|entry| !entry.is_expired())
+                        .map(|entry| entry.data.clone());
+
+                    if value.is_some() {
+                        self.touch(&key);
+                    }
+
+                    PipelineResult::Get(value)
+                }
+                PipelineOp::Set { key, value } => {
+                    self.make_room(&mut state, &key)?;
+                    let entry = Entry::new(key, value, expires_at);
+                    state.insert(entry.clone());
+                    written.push(entry);
+
+                    PipelineResult::Set
+                }
+                PipelineOp::Delete { key } => {
+                    let removed = state.remove(&key).is_some();
+                    deleted |= removed;
+
+                    PipelineResult::Delete(removed)
+                }
+            };
+
+            results.push(result);
+        }
+
+        if self.is_disk_runtime() {
+            // Deletes can't be appended, so fall back to a single rewrite of the whole file.
+            if deleted {
+                self.rewrite_file(&state)?;
+            } else if !written.is_empty() {
+                self.persist(&state, &written.iter().collect::<Vec<_>>())?;
+            }
+        }
+
+        log::info!("[PIPELINE] Executed {} operations", results.len());
+
+        Ok(results)
+    }
+
+    /// Replaces the value of an existing key, or inserts it when `upsert` is `Some(true)`.
+    ///
+    /// Returns `false` if the key was missing and nothing was written.
+    pub(crate) fn update(&mut self, key: &str, value: T, ttl: Option<Duration>, upsert: Option<bool>)
+        -> anyhow::Result<bool>
+    {
+        let mut op = OperationLog::start("UPDATE", key);
+        #[cfg(feature = "metrics")]
+        let _timer = self.timers.start(TimedOp::Update);
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+        self.check_key(key)?;
+
+        log::debug!("[UPDATE] Attempting {} update...", key);
+
+        let mut state = write_lock(&self.state);
+
+        state.remove_if_expired(key);
+
+        if !state.entries.contains_key(key) && upsert != Some(true) {
+            log::debug!("[UPDATE] Key not found and upsert not enabled: {}", key);
+            op.outcome = "not_found";
+            return Ok(false);
+        }
+
+        self.make_room(&mut state, key)?;
+
+        let entry = Entry::new(key.to_string(), value, self.get_ttl(ttl)?);
+
+        state.insert(entry.clone());
+
+        if self.is_disk_runtime() {
+            // The new record shadows the old one on reload, compaction drops the old one later.
+            self.persist(&state, &[&entry])?;
+        }
+
+        log::info!("[UPDATE] Key updated: {}", key);
+        op.outcome = "ok";
+
+        Ok(true)
+    }
+
+    pub(crate) fn delete(&mut self, key: &str) -> anyhow::Result<()>
+    {
+        let mut op = OperationLog::start("DELETE", key);
+        #[cfg(feature = "metrics")]
+        let _timer = self.timers.start(TimedOp::Delete);
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+
+        log::debug!("[DELETE] Deleting key: {}", key);
+
+        if !read_lock(&self.state).entries.contains_key(key) {
+            log::debug!("[DELETE] Key not found: {}", key);
+            op.outcome = "not_found";
+            return Ok(());
+        }
+
+        // todo - this still rewrites the whole file to drop a single key.
+        self.rewrite_filtered(|entry_key, _| entry_key != key)?;
+
+        log::info!("[DELETE] Key deleted: {}", key);
+        op.outcome = "ok";
+
+        Ok(())
+    }
+
+    /// Removes `key` and returns its value, both under one lock so two callers can't claim
+    /// the same value.
+    pub(crate) fn pop(&mut self, key: &str) -> anyhow::Result<Option<T>>
+    {
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+
+        log::debug!("[POP] Popping key: {}", key);
+
+        let mut state = write_lock(&self.state);
+
+        if state.remove_if_expired(key) {
+            log::debug!("[POP] Key expired: {}", key);
+            return Ok(None);
+        }
+
+        let entry = match state.remove(key) {
+            Some(entry) => entry,
+            None => {
+                log::debug!("[POP] Key not found: {}", key);
+                return Ok(None);
+            }
+        };
+
+        if self.is_disk_runtime() {
+            self.rewrite_file(&state)?;
+        }
+
+        log::info!("[POP] Key popped: {}", key);
+
+        Ok(Some(entry.data))
+    }
+
+    /// Makes an existing key expire `ttl` from now, leaving its value alone.
+    ///
+    /// Returns `false` if the key is missing or has already expired.
+    pub(crate) fn refresh_ttl(&mut self, key: &str, ttl: Duration) -> anyhow::Result<bool>
+    {
+        self.ensure_writable()?;
+
+        log::debug!("[TOUCH] Refreshing ttl: {}", key);
+
+        let mut state = write_lock(&self.state);
+
+        let expires_at = self.get_ttl(Some(ttl))?;
+
+        if state.remove_if_expired(key) || !state.set_expiry(key, expires_at) {
+            log::debug!("[TOUCH] Key not found: {}", key);
+            return Ok(false);
+        }
+
+        self.touch(key);
+
+        if self.is_disk_runtime() {
+            if let Some(entry) = state.entries.get(key).cloned() {
+                self.persist(&state, &[&entry])?;
+            }
+        }
+
+        log::info!("[TOUCH] Ttl refreshed: {}", key);
+
+        Ok(true)
+    }
+
+    /// Moves the entry under `from` to `to`, keeping its expiry and overwriting whatever `to` held.
+    ///
+    /// Returns `false` if `from` does not exist.
+    pub(crate) fn rename(&mut self, from: &str, to: &str) -> anyhow::Result<bool>
+    {
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+        self.check_key(to)?;
+
+        log::debug!("[RENAME] Renaming key: {} -> {}", from, to);
+
+        let mut state = write_lock(&self.state);
+
+        if state.remove_if_expired(from) {
+            log::debug!("[RENAME] Key expired: {}", from);
+            return Ok(false);
+        }
+
+        let entry = match state.remove(from) {
+            Some(entry) => entry,
+            None => {
+                log::debug!("[RENAME] Key not found: {}", from);
+                return Ok(false);
+            }
+        };
+
+        self.touch(to);
+        state.insert(Entry::new(to.to_string(), entry.data, entry.expires_at));
+
+        if self.is_disk_runtime() {
+            // The old key has to disappear from the file, which an append can't do.
+            self.rewrite_file(&state)?;
+        }
+
+        log::info!("[RENAME] Key renamed: {} -> {}", from, to);
+
+        Ok(true)
+    }
+
+    pub(crate) fn delete_prefix(&mut self, prefix: &str) -> anyhow::Result<usize>
+    {
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+
+        log::debug!("[DELETE_PREFIX] Deleting keys starting with: {}", prefix);
+
+        let removed = self.rewrite_filtered(|key, _| !key.starts_with(prefix))?;
+
+        log::info!("[DELETE_PREFIX] Deleted {} keys starting with: {}", removed, prefix);
+
+        Ok(removed)
+    }
+
+    pub(crate) fn retain<F>(&mut self, f: F) -> anyhow::Result<usize>
+    where
+        F: Fn(&str, &T) -> bool,
+    {
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+
+        log::debug!("[RETAIN] Filtering entries");
+
+        let removed = self.rewrite_filtered(|key, entry| f(key, &entry.data))?;
+
+        log::info!("[RETAIN] Removed {} entries", removed);
+
+        Ok(removed)
+    }
+
+    pub(crate) fn clear_expired(&mut self) -> anyhow::Result<usize>
+    {
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+
+        log::debug!("[CLEAR_EXPIRED] Removing expired entries");
+
+        // Taken out first so they are reported as expired rather than deleted.
+        let expired = write_lock(&self.state).remove_expired(Utc::now()).len();
+        let removed = expired + self.rewrite_filtered(|_, entry| !entry.is_expired())?;
+
+        log::info!("[CLEAR_EXPIRED] Removed {} expired entries", removed);
+
+        Ok(removed)
+    }
+
+    /// Returns the live entries whose ttl runs out before `when`, soonest first.
+    ///
+    /// Walks the sorted `expirations` set, so only the matching range is visited.
+    pub(crate) fn entries_expiring_before(&mut self, when: DateTime<Utc>) -> anyhow::Result<Vec<(String, T)>>
+    {
+        let state = read_lock(&self.state);
+        let now = Utc::now();
+
+        let entries = state
+            .expirations
+            .range(..(when, String::new()))
+            .filter(|(expires_at, _)| *expires_at > now)
+            .filter_map(|(_, key)| state.entries.get(key))
+            .map(|entry| (entry.key.clone(), entry.data.clone()))
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Returns the live entries whose key falls in `[start, end)`, sorted by key.
+    pub(crate) fn range(&mut self, start: &str, end: &str) -> anyhow::Result<Vec<(String, T)>>
+    {
+        let state = read_lock(&self.state);
+
+        // `BTreeSet::range` panics on an inverted range, treat it as empty instead.
+        if start >= end {
+            return Ok(Vec::new());
+        }
+
+        let entries = state
+            .sorted_keys
+            .range::<str, _>((Bound::Included(start), Bound::Excluded(end)))
+            .filter_map(|key| state.entries.get(key))
+            .filter(|entry| !entry.is_expired())
+            .map(|entry| (entry.key.clone(), entry.data.clone()))
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Indexes every entry by what `extractor` returns for its value, replacing any index
+    /// called `name`.
+    pub(crate) fn create_index<K>(&mut self, name: &str, extractor: fn(&T) -> K)
+    where
+        K: Ord + Clone + Send + Sync + 'static,
+    {
+        log::debug!("[INDEX] Creating index: {}", name);
+
+        let mut state = write_lock(&self.state);
+        let state = &mut *state;
+
+        let entries = state.entries.iter().map(|(key, entry)| (key.as_str(), &entry.data));
+        state.indexes.create(name, extractor, entries);
+
+        log::info!("[INDEX] Index created: {}", name);
+    }
+
+    /// Returns the live entries in index `name` whose values map to `index_key`, sorted by key.
+    pub(crate) fn query_index<K>(&self, name: &str, index_key: &K) -> anyhow::Result<Vec<(String, T)>>
+    where
+        K: Ord + Clone + Send + Sync + 'static,
+    {
+        let state = read_lock(&self.state);
+
+        let mut keys = state
+            .indexes
+            .get(name, index_key)
+            .ok_or_else(|| QuickKvError::IndexNotFound(name.to_string()))?;
+        keys.sort();
+
+        let entries = keys
+            .iter()
+            .filter_map(|key| state.entries.get(key))
+            .filter(|entry| !entry.is_expired())
+            .map(|entry| (entry.key.clone(), entry.data.clone()))
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Writes every entry to a database file at `path`, in the layout a disk database with the
+    /// same configuration loads.
+    ///
+    /// Like a rewrite, the file is written next to `path` and renamed over it. `path` is locked
+    /// meanwhile, so a database another client has open isn't replaced under it.
+    pub(crate) fn save_to(&self, path: &str) -> anyhow::Result<()>
+    {
+        config::ensure_not_directory(path)?;
+
+        let _lock = lock_database(path)?;
+
+        log::debug!("[SAVE] Saving database to: {}", path);
+
+        let state = read_lock(&self.state);
+        let (_, records) = self.write_temp_file_for(&state, path)?;
+
+        std::fs::rename(temp_path(path), path)?;
+
+        if self.config.sync_on_write.unwrap_or(true) {
+            sync_parent_dir(path)?;
+        }
+
+        log::info!("[SAVE] Saved {} entries to: {}", records, path);
+
+        Ok(())
+    }
+
+    /// Writes every live entry to `w` as a stream of records, each a little-endian `u64`
+    /// length followed by the bincode encoded entry.
+    ///
+    /// The stream doesn't depend on the database file's format or compression, so it can be
+    /// imported into any database holding the same type.
+    pub(crate) fn export<W: Write>(&self, w: &mut W) -> anyhow::Result<()>
+    {
+        log::debug!("[EXPORT] Exporting database");
+
+        let state = read_lock(&self.state);
+        let mut exported = 0;
+
+        for entry in state.entries.values().filter(|entry| !entry.is_expired()) {
+            let bytes = bincode::serialize(entry)?;
+
+            w.write_all(&(bytes.len() as u64).to_le_bytes())?;
+            w.write_all(&bytes)?;
+
+            exported += 1;
+        }
+
+        w.flush()?;
+
+        log::info!("[EXPORT] Exported {} entries", exported);
+
+        Ok(())
+    }
+
+    /// Reads a stream written by `export`, merging its entries into the database.
+    ///
+    /// Imported keys replace existing ones and keep the expiry they were exported with,
+    /// entries that expired in the meantime are skipped. Returns the number of entries imported.
+    pub(crate) fn import<R: Read>(&mut self, r: &mut R) -> anyhow::Result<usize>
+    {
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+
+        log::debug!("[IMPORT] Importing entries");
+
+        let mut entries = Vec::new();
+
+        loop {
+            let mut len = [0; 8];
+
+            match r.read_exact(&mut len) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+
+            let len = u64::from_le_bytes(len);
+
+            if len > MAX_IMPORT_RECORD_LEN {
+                anyhow::bail!("Import record too large: {} bytes", len);
+            }
+
+            // Read through `take` so a truncated stream can't make us allocate the whole length up front.
+            let mut bytes = Vec::new();
+            r.by_ref().take(len).read_to_end(&mut bytes)?;
+
+            if bytes.len() as u64 != len {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+
+            let entry: Entry<T> = bincode::deserialize(&bytes)?;
+
+            if !entry.is_expired() {
+                entries.push(entry);
+            }
+        }
+
+        for entry in &entries {
+            self.check_key(&entry.key)?;
+        }
+
+        // Decode everything before touching the state, a corrupt stream imports nothing.
+        let mut state = write_lock(&self.state);
+
+        self.check_capacity(&mut state, entries.iter().map(|entry| (entry.key.as_str(), KeyChange::Write)))?;
+
+        for entry in &entries {
+            self.make_room(&mut state, &entry.key)?;
+            state.insert(entry.clone());
+        }
+
+        if self.is_disk_runtime() {
+            self.persist(&state, &entries.iter().collect::<Vec<_>>())?;
+        }
+
+        log::info!("[IMPORT] Imported {} entries", entries.len());
+
+        Ok(entries.len())
+    }
+
+    pub(crate) fn compact(&mut self) -> anyhow::Result<()>
+    {
+        self.ensure_writable()?;
+
+        log::debug!("[COMPACT] Compacting database");
+
+        // Keeping every entry still rewrites the file, which drops stale records left by appends.
+        self.rewrite_filtered(|_, _| true)?;
+
+        log::info!("[COMPACT] Database compacted");
+
+        Ok(())
+    }
+
+    /// Removes every entry rejected by `keep` from the cache, then rewrites the database file
+    /// once with whatever is left.
+    ///
+    /// This is the single rewrite path used by bulk removals and compaction.
+    /// Returns the number of entries removed.
+    pub(crate) fn rewrite_filtered(&mut self, keep: impl Fn(&str, &Entry<T>) -> bool) -> anyhow::Result<usize>
+    {
+        let mut state = write_lock(&self.state);
+
+        let removed = state
+            .entries
+            .iter()
+            .filter(|(key, entry)| !keep(key.as_str(), *entry))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<String>>();
+
+        for key in &removed {
+            state.remove(key);
+        }
+
+        if self.is_disk_runtime() {
+            self.rewrite_file(&state)?;
+        }
+
+        Ok(removed.len())
+    }
+
+    pub(crate) fn purge(&mut self) -> anyhow::Result<()>
+    {
+        let _notify = self.notify_on_drop();
+
+        self.ensure_writable()?;
+
+        log::debug!("[PURGE] Purging database");
+
+        let mut state = write_lock(&self.state);
+
+        state.clear();
+
+        if self.is_disk_runtime() {
+            match self.config.namespace {
+                None => self.truncate_file()?,
+                // Other namespaces sharing the file keep their records.
+                Some(_) => self.rewrite_file(&state)?,
+            }
+        }
+
+        log::info!("[PURGE] Database purged");
+
+        Ok(())
+    }
+
+    /// Flushes the database and exits the process when SIGINT or SIGTERM is received.
+    ///
+    /// `ctrlc` doesn't tell the handler which signal arrived, so both exit with code 130.
+    /// Only one handler can be installed per process, a second call returns an error.
+    #[cfg(feature = "signals")]
+    pub(crate) fn install_shutdown_handler(&self) -> anyhow::Result<()>
+    {
+        let writer = self.writer.clone();
+        let ttl_manager = self.ttl_manager.clone();
+
+        ctrlc::set_handler(move || {
+            log::info!("[SHUTDOWN] Signal received, flushing database");
+
+            if let Err(e) = flush_and_stop(writer.as_ref(), &ttl_manager) {
+                log::error!("[SHUTDOWN] Failed to flush database: {}", e);
+            }
+
+            // 128 + SIGINT, the usual exit code for an interrupted process. SIGTERM would be 143,
+            // but the handler can't tell the two apart.
+            std::process::exit(130);
+        })?;
+
+        Ok(())
+    }
+
+    /// How many records were read and skipped when the database file was loaded.
+    pub(crate) fn load_report(&self) -> LoadReport
+    {
+        self.load_report
+    }
+
+    /// Reads the database file again and returns how many records can't be decoded as `T`.
+    ///
+    /// The file is only read, records that fail are counted and skipped whatever the
+    /// `CorruptionPolicy` says.
+    pub(crate) fn validate_schema(&mut self) -> anyhow::Result<usize>
+    {
+        let skipped = self.read_entries(CorruptionPolicy::SkipAndContinue)?.skipped;
+
+        if skipped > 0 {
+            log::warn!("[SCHEMA] {} records don't match the current schema", skipped);
+        }
+
+        Ok(skipped)
+    }
+
+    /// Syncs every write made so far to disk.
+    ///
+    /// Only needed with `sync_on_write` turned off, otherwise each write is already synced.
+    pub(crate) fn flush(&mut self) -> anyhow::Result<()>
+    {
+        if let Some(ref writer) = self.writer {
+            sync_writer(writer, &self.syncs)?;
+        }
+
+        Ok(())
+    }
+
+    /// Registers `f` to be called after every change to a key.
+    pub(crate) fn on_change(&mut self, f: ChangeListener)
+    {
+        self.listeners.add(&self.state, f);
+    }
+
+    /// Passes the changes made by the calling function to the `on_change` callbacks when dropped.
+    fn notify_on_drop(&self) -> NotifyOnDrop<T>
+    {
+        NotifyOnDrop {
+            state: self.state.clone(),
+            listeners: self.listeners.clone(),
+        }
+    }
+
+    /// Fails with `QuickKvError::InvalidKey` if `key` is longer than `max_key_len` or is
+    /// rejected by `key_validator`.
+    fn check_key(&self, key: &str) -> anyhow::Result<()>
+    {
+        if let Some(max_key_len) = self.config.max_key_len {
+            if key.len() > max_key_len {
+                return Err(QuickKvError::InvalidKey {
+                    reason: format!("the key is {} bytes long, the limit is {}", key.len(), max_key_len),
+                }
+                .into());
+            }
+        }
+
+        if let Some(validator) = self.config.key_validator {
+            if !validator(key) {
+                return Err(QuickKvError::InvalidKey {
+                    reason: format!("\"{}\" was rejected by the key validator", key),
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the current ttl if it exists.
+    /// Function will also try the default ttl if configured else it will return None.
+    ///
+    /// A zero ttl is rejected rather than stored, since the entry would already be expired
+    /// by the time anyone could read it.
+    fn get_ttl(&self, ttl: Option<Duration>) -> anyhow::Result<Option<DateTime<Utc>>>
+    {
+        if ttl.or(self.config.default_ttl).is_some_and(|ttl| ttl.is_zero()) {
+            return Err(QuickKvError::InvalidTtl.into());
+        }
+
+        if let Some(ttl) = ttl {
+            Ok(Some(Utc::now() + chrono::Duration::from_std(ttl)?))
+        } else if let Some(default_ttl) = self.config.default_ttl {
+            Ok(Some(Utc::now() + chrono::Duration::from_std(default_ttl)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Fails with `QuickKvError::CapacityExceeded` if applying `changes` in order would take the
+    /// state past `max_entries`, before any of them is applied.
+    ///
+    /// Operations writing several keys check this first, so a batch that runs out of room
+    /// changes nothing instead of leaving part of it in memory. With eviction enabled there is
+    /// always room, unless `max_entries` is zero.
+    fn check_capacity<'a, I>(&self, state: &mut State<T>, changes: I) -> anyhow::Result<()>
+    where
+        I: IntoIterator<Item = (&'a str, KeyChange)>,
+    {
+        let max_entries = match self.config.max_entries {
+            Some(max_entries) => max_entries,
+            None => return Ok(()),
+        };
+
+        // Like `make_room`, entries the background task hasn't removed yet don't count.
+        state.remove_expired(Utc::now());
+
+        let evicting = self.access_order.is_some();
+        let mut present = HashMap::default();
+        let mut len = state.entries.len();
+
+        for (key, change) in changes {
+            let exists = *present.entry(key).or_insert_with(|| state.entries.contains_key(key));
+
+            match change {
+                KeyChange::Remove if exists => {
+                    present.insert(key, false);
+                    len -= 1;
+                }
+                KeyChange::Write if !exists => {
+                    present.insert(key, true);
+                    len += 1;
+
+                    if len > max_entries {
+                        if !evicting || max_entries == 0 {
+                            return Err(QuickKvError::CapacityExceeded { max_entries }.into());
+                        }
+
+                        len = max_entries;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Makes sure `key` can be inserted without passing `max_entries`, evicting the least
+    /// recently used keys under `EvictionPolicy::Lru`, and records `key` as just used.
+    ///
+    /// Fails with `QuickKvError::CapacityExceeded` when there is no room and nothing can be
+    /// evicted. Existing keys can always be overwritten.
+    fn make_room(&self, state: &mut State<T>, key: &str) -> anyhow::Result<()>
+    {
+        let max_entries = match self.config.max_entries {
+            Some(max_entries) => max_entries,
+            None => return Ok(()),
+        };
+
+        if !state.entries.contains_key(key) && state.entries.len() >= max_entries {
+            // Entries the background task hasn't removed yet shouldn't count against the limit.
+            state.remove_expired(Utc::now());
+
+            if state.entries.len() >= max_entries {
+                self.evict(state, max_entries.saturating_sub(1));
+            }
+
+            if state.entries.len() >= max_entries {
+                return Err(QuickKvError::CapacityExceeded { max_entries }.into());
+            }
+        }
+
+        self.touch(key);
+
+        Ok(())
+    }
+
+    /// Evicts the least recently used keys until at most `keep` remain, if eviction is enabled.
+    ///
+    /// The evicted records are still live in the file, so this marks it for a rewrite on the
+    /// next `persist`.
+    fn evict(&self, state: &mut State<T>, keep: usize)
+    {
+        let mut access_order = match self.access_order {
+            Some(ref access_order) => lock(access_order),
+            None => return,
+        };
+
+        while state.entries.len() > keep {
+            let victim = access_order.pop_least_recent().or_else(|| state.order.first().cloned());
+
+            match victim {
+                Some(victim) => {
+                    if state.remove(&victim).is_some() {
+                        log::debug!("[EVICT] Evicted key: {}", victim);
+                        self.evicted.store(true, Ordering::Relaxed);
+                    }
+                }
+                None => break,
+            }
+        }
+
+        // Deleted keys are only dropped from the access order lazily, keep it from growing unbounded.
+        if access_order.len() > state.entries.len() * 2 {
+            access_order.retain(|key| state.entries.contains_key(key));
+        }
+    }
+
+    /// Records `key` as just used, for `EvictionPolicy::Lru`.
+    fn touch(&self, key: &str)
+    {
+        if let Some(ref access_order) = self.access_order {
+            lock(access_order).touch(key);
+        }
+    }
+
+    fn io_buffer_size(&self) -> usize
+    {
+        self.config.io_buffer_size.unwrap_or(DEFAULT_IO_BUFFER_SIZE)
+    }
+
+    fn format(&self) -> SerializationFormat
+    {
+        self.config.format.unwrap_or_default()
+    }
+
+    /// The codec for the configured serialization format.
+    fn codec(&self) -> &'static dyn EntryCodec<T>
+    {
+        self.format().codec()
+    }
+
+    /// Fails with `QuickKvError::ReadOnly` if the database was opened read-only.
+    fn ensure_writable(&self) -> anyhow::Result<()>
+    {
+        if self.config.read_only.unwrap_or_default() {
+            return Err(QuickKvError::ReadOnly.into());
+        }
+
+        Ok(())
+    }
+
+    /// Encodes `entry` as it is stored in the file, encrypted if encryption is configured.
+    fn encode_record(&self, entry: &Entry<T>) -> anyhow::Result<Vec<u8>>
+    {
+        let bytes = self.codec().encode(entry)?;
+
+        match self.config.encryption {
+            Some(ref encryption) => encryption.seal(&bytes),
+            None => Ok(bytes),
+        }
+    }
+
+    /// Decodes the next record written by `encode_record`, with `codec` in place of the current
+    /// one for files written by an older version.
+    fn decode_record(&self, codec: &dyn EntryCodec<T>, reader: &mut dyn BufRead) -> anyhow::Result<Option<Entry<T>>>
+    {
+        match self.config.encryption {
+            Some(ref encryption) => match encryption.open_next(reader)? {
+                Some(plaintext) => codec.decode(&mut plaintext.as_slice()),
+                None => Ok(None),
+            },
+            None => codec.decode(reader),
+        }
+    }
+
+    /// Checks if we need to use disk operations, the default is disk.
+    fn is_disk_runtime(&self) -> bool
+    {
+        if let Some(r) = &self.config.runtime {
+            match r._type {
+                RuntTimeType::Memory => false,
+                RuntTimeType::Disk => true,
+            }
+        } else {
+            true
+        }
+    }
+
+    /// Returns a summary of disk write latencies.
+    ///
+    /// Everything is zero unless `track_write_latency` is enabled.
+    pub(crate) fn write_latency_stats(&self) -> LatencyStats
+    {
+        match self.write_latency {
+            Some(ref histogram) => lock(histogram).stats(),
+            None => LatencyStats::default(),
+        }
+    }
+
+    /// Returns how long `get`, `set`, `update` and `delete` have taken so far.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn timings(&self) -> OpTimings
+    {
+        self.timers.timings()
+    }
+
+    /// Returns the number of entries and how much of the file they take up.
+    ///
+    /// `live_bytes_estimate` comes from encoding every entry again, encryption included, while
+    /// the state is locked. That costs about as much as a `compact`, so this is meant for
+    /// occasional checks rather than every write.
+    pub(crate) fn stats(&mut self) -> anyhow::Result<DbStats>
+    {
+        let state = read_lock(&self.state);
+
+        let expired_pending = state.entries.values().filter(|entry| entry.is_expired()).count();
+
+        let mut live_bytes_estimate = 0;
+
+        for entry in state.entries.values() {
+            live_bytes_estimate += self.encode_record(&self.stored_entry(entry))?.len() as u64;
+        }
+
+        // Other namespaces' entries are kept with their stored keys already.
+        if let Some(prefix) = self.namespace_prefix() {
+            let namespaces = lock(&self.namespaces);
+
+            for entry in namespaces.entries.values().filter(|entry| !entry.key.starts_with(&prefix)) {
+                live_bytes_estimate += self.encode_record(entry)?.len() as u64;
+            }
+        }
+
+        let file_size_bytes = match self.reader {
+            Some(ref reader) => lock(reader).get_ref().metadata()?.len(),
+            None => 0,
+        };
+
+        let records = self.file_records.load(Ordering::Relaxed);
+        let live = self.live_records(&state);
+
+        let stale_record_ratio = if records > 0 {
+            records.saturating_sub(live) as f64 / records as f64
+        } else {
+            0.0
+        };
+
+        Ok(DbStats {
+            entry_count: state.entries.len() - expired_pending,
+            expired_pending,
+            file_size_bytes,
+            live_bytes_estimate,
+            stale_record_ratio,
+        })
+    }
+
+    /// Records how long a disk write took, callers hold the writer lock while doing so.
+    fn record_write_latency(&self, latency: Duration)
+    {
+        if let Some(ref histogram) = self.write_latency {
+            lock(histogram).record(latency);
+        }
+    }
+
+    /// Writes entries that were just inserted into `state` to disk.
+    ///
+    /// Plain files are appended to. A gzip stream can't be appended to or seeked into, so
+    /// compressed databases rewrite the whole file instead, trading write speed for size.
+    fn persist(&self, state: &State<T>, entries: &[&Entry<T>]) -> anyhow::Result<()>
+    {
+        // Appending would leave the evicted keys' records live, to be loaded again on restart.
+        if self.evicted.load(Ordering::Relaxed) {
+            return self.rewrite_file(state);
+        }
+
+        match self.config.compression {
+            Some(Compression::Gzip) => self.rewrite_file(state),
+            None => {
+                self.append_entries(entries)?;
+
+                if self.needs_compaction(state) {
+                    log::debug!("[COMPACT] Too many stale records, rewriting the database file");
+                    self.rewrite_file(state)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether the share of stale records in the file has passed `compaction_threshold`.
+    fn needs_compaction(&self, state: &State<T>) -> bool
+    {
+        let records = self.file_records.load(Ordering::Relaxed);
+        let live = self.live_records(state);
+        let stale = records.saturating_sub(live);
+
+        let threshold = self.config.compaction_threshold.unwrap_or(DEFAULT_COMPACTION_THRESHOLD) as usize;
+
+        records > 0 && stale * 100 > records * threshold
+    }
+
+    /// Lets namespaces opened on this file later share its handles, see `OpenFiles`.
+    fn register(&self, open_files: &mut OpenFiles, path: &str) -> anyhow::Result<()>
+    {
+        let (Some(writer), Some(reader), Some(file_lock)) = (&self.writer, &self.reader, &self.file_lock) else {
+            return Ok(());
+        };
+
+        let shared = SharedFile {
+            writer: writer.clone(),
+            reader: reader.clone(),
+            file_lock: file_lock.clone(),
+            file_records: self.file_records.clone(),
+            syncs: self.syncs.clone(),
+            flush_manager: self.flush_manager.clone(),
+            namespaces: self.namespaces.clone(),
+        };
+
+        open_files.register(path, &shared, self.format(), self.config.compression)
+    }
+
+    /// How many live records the file holds, including other namespaces' records.
+    fn live_records(&self, state: &State<T>) -> usize
+    {
+        match self.config.namespace {
+            Some(_) => lock(&self.namespaces).entries.len(),
+            None => state.entries.len(),
+        }
+    }
+
+    /// The prefix stored keys carry when a namespace is configured.
+    fn namespace_prefix(&self) -> Option<String>
+    {
+        self.config
+            .namespace
+            .as_ref()
+            .map(|namespace| format!("{}{}", namespace, NAMESPACE_SEPARATOR))
+    }
+
+    /// The entry as it is written to disk, with the namespace added to its key.
+    fn stored_entry<'a>(&self, entry: &'a Entry<T>) -> Cow<'a, Entry<T>>
+    {
+        match self.namespace_prefix() {
+            Some(prefix) => Cow::Owned(Entry {
+                key: format!("{}{}", prefix, entry.key),
+                data: entry.data.clone(),
+                ..*entry
+            }),
+            None => Cow::Borrowed(entry),
+        }
+    }
+
+    /// Appends entries to the end of the database file.
+    fn append_entries(&self, entries: &[&Entry<T>]) -> anyhow::Result<()>
+    {
+        if let Some(ref writer) = self.writer {
+            let mut w = lock(&writer);
+            let started = Instant::now();
+
+            let mut bytes = 0;
+
+            // A purged file is empty, it gets its header back before the first record.
+            if w.seek(SeekFrom::End(0))? == 0 {
+                let header = header::encode(self.format());
+                bytes += header.len();
+                w.write_all(&header)?;
+            }
+
+            for entry in entries {
+                let record = self.encode_record(&self.stored_entry(entry))?;
+                bytes += record.len();
+                w.write_all(&record)?;
+            }
+
+            self.sync(&mut w, bytes)?;
+
+            self.file_records.fetch_add(entries.len(), Ordering::Relaxed);
+
+            if self.config.namespace.is_some() {
+                let mut namespaces = lock(&self.namespaces);
+
+                for entry in entries {
+                    namespaces.insert(self.stored_entry(entry).into_owned());
+                }
+            }
+
+            self.record_write_latency(started.elapsed());
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the writer after `bytes` were written, then syncs the file unless `sync_on_write`
+    /// is turned off.
+    ///
+    /// With `flush_interval` set the sync is left to the flush task, unless enough bytes are
+    /// waiting that it shouldn't wait for the next tick.
+    fn sync(&self, w: &mut BufWriter<File>, bytes: usize) -> anyhow::Result<()>
+    {
+        w.flush()?;
+
+        let sync_now = match self.flush_manager {
+            Some(ref flush_manager) => flush_manager.wrote(bytes),
+            None => self.config.sync_on_write.unwrap_or(true),
+        };
+
+        if sync_now {
+            w.get_ref().sync_all()?;
+            self.syncs.fetch_add(1, Ordering::Relaxed);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the file header, only called on an empty file.
+    fn write_header(&self) -> anyhow::Result<()>
+    {
+        if let Some(ref writer) = self.writer {
+            let mut w = lock(&writer);
+
+            let header = header::encode(self.format());
+
+            w.seek(SeekFrom::Start(0))?;
+            w.write_all(&header)?;
+            self.sync(&mut w, header.len())?;
+        }
+
+        Ok(())
+    }
+
+    /// Truncates the database file to zero bytes, header included.
+    ///
+    /// The header is written again before the next record, or when the file is next opened.
+    fn truncate_file(&self) -> anyhow::Result<()>
+    {
+        if let Some(ref writer) = self.writer {
+            let mut w = lock(&writer);
+
+            w.flush()?;
+            w.get_ref().set_len(0)?;
+            w.seek(SeekFrom::Start(0))?;
+            self.sync(&mut w, 0)?;
+
+            self.file_records.store(0, Ordering::Relaxed);
+            self.evicted.store(false, Ordering::Relaxed);
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the contents of the database file with the entries held in `state`.
+    ///
+    /// Entries are written in insertion order so the order survives a reload. The new contents
+    /// go to a temporary file that is renamed over the database file, so a crash part way
+    /// through leaves the old file untouched.
+    fn rewrite_file(&self, state: &State<T>) -> anyhow::Result<()>
+    {
+        if let Some(ref writer) = self.writer {
+            let mut w = lock(&writer);
+            let started = Instant::now();
+
+            let (file, records) = self.write_temp_file(state)?;
+
+            let path = self.config.path.clone().unwrap_or_default();
+
+            std::fs::rename(temp_path(&path), &path)?;
+
+            if self.config.sync_on_write.unwrap_or(true) {
+                sync_parent_dir(&path)?;
+            }
+
+            // The old handles still point at the replaced file.
+            if let Some(ref reader) = self.reader {
+                *lock(reader) = BufReader::with_capacity(self.io_buffer_size(), file.try_clone()?);
+            }
+
+            *w = BufWriter::with_capacity(self.io_buffer_size(), file);
+
+            self.file_records.store(records, Ordering::Relaxed);
+            self.evicted.store(false, Ordering::Relaxed);
+
+            // This namespace's records in the file are now exactly the entries in `state`.
+            if let Some(prefix) = self.namespace_prefix() {
+                let mut namespaces = lock(&self.namespaces);
+
+                let replaced = namespaces
+                    .entries
+                    .keys()
+                    .filter(|key| key.starts_with(&prefix))
+                    .cloned()
+                    .collect::<Vec<String>>();
+
+                for key in &replaced {
+                    namespaces.remove(key);
+                }
+
+                for entry in state.order.iter().filter_map(|key| state.entries.get(key)) {
+                    namespaces.insert(self.stored_entry(entry).into_owned());
+                }
+            }
+
+            self.record_write_latency(started.elapsed());
+        }
+
+        Ok(())
+    }
+
+    /// Writes the entries held in `state` and other namespaces' records to the temporary file
+    /// next to the database file, returning it along with the number of records written.
+    fn write_temp_file(&self, state: &State<T>) -> anyhow::Result<(File, usize)>
+    {
+        self.write_temp_file_for(state, &self.config.path.clone().unwrap_or_default())
+    }
+
+    /// `write_temp_file`, next to the database file at `path` instead of this one.
+    fn write_temp_file_for(&self, state: &State<T>, path: &str) -> anyhow::Result<(File, usize)>
+    {
+        let mut bytes = Vec::new();
+        let mut records = 0;
+
+        // Other namespaces sharing the file keep their records, this one's come from `state`.
+        if let Some(prefix) = self.namespace_prefix() {
+            let namespaces = lock(&self.namespaces);
+
+            for entry in namespaces
+                .order
+                .iter()
+                .filter(|key| !key.starts_with(&prefix))
+                .filter_map(|key| namespaces.entries.get(key))
+            {
+                bytes.extend(self.encode_record(entry)?);
+                records += 1;
+            }
+        }
+
+        for entry in state.order.iter().filter_map(|key| state.entries.get(key)) {
+            bytes.extend(self.encode_record(&self.stored_entry(entry))?);
+            records += 1;
+        }
+
+        if let Some(Compression::Gzip) = self.config.compression {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&bytes)?;
+            bytes = encoder.finish()?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(temp_path(path))?;
+
+        file.write_all(&header::encode(self.format()))?;
+        file.write_all(&bytes)?;
+
+        if self.config.sync_on_write.unwrap_or(true) {
+            file.sync_all()?;
+        }
+
+        Ok((file, records))
+    }
+
+    /// Reads every record in the database file, in the order they were written.
+    ///
+    /// Records that can't be read are handled according to `policy`.
+    fn read_entries(&self, policy: CorruptionPolicy) -> anyhow::Result<LoadedFile<T>>
+    {
+        let Some(ref reader) = self.reader else {
+            return Ok(LoadedFile::new(Vec::new()));
+        };
+
+        let mut r = lock(&reader);
+
+        r.seek(SeekFrom::Start(0))?; // Seek to the beginning of the file
+
+        // A new file gets its header before anything is appended to it.
+        if r.get_ref().metadata()?.len() == 0 {
+            drop(r);
+            self.write_header()?;
+            return Ok(LoadedFile::new(Vec::new()));
+        }
+
+        // Files written before the header was added start straight away with their records.
+        let legacy = !r.fill_buf()?.starts_with(header::MAGIC);
+
+        let mut version = header::FORMAT_VERSION;
+
+        if !legacy {
+            let mut header = Vec::with_capacity(header::HEADER_LEN);
+            (&mut *r).take(header::HEADER_LEN as u64).read_to_end(&mut header)?;
+            version = header::validate(&header, self.format())?;
+        }
+
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes)?;
+        drop(r);
+
+        // A new compressed database has no gzip header yet.
+        if let (Some(Compression::Gzip), false) = (self.config.compression, bytes.is_empty()) {
+            bytes = gunzip(&bytes)?;
+        }
+
+        if legacy {
+            let mut loaded = LoadedFile::new(migrate::decode_legacy(&bytes, self.format())?);
+            loaded.legacy = true;
+            return Ok(loaded);
+        }
+
+        let mut loaded = self.decode_records(&bytes, version, policy)?;
+        loaded.legacy = version < header::FORMAT_VERSION;
+
+        Ok(loaded)
+    }
+
+    /// Decodes the records that follow the header of a file written with format `version`.
+    fn decode_records(&self, bytes: &[u8], version: u8, policy: CorruptionPolicy) -> anyhow::Result<LoadedFile<T>>
+    {
+        let codec: &dyn EntryCodec<T> = match (version, self.format()) {
+            (1, SerializationFormat::Bincode) => &migrate::UnframedBincodeCodec,
+            (2, SerializationFormat::Bincode) => &BincodeCodec { checksum: false },
+            _ => self.codec(),
+        };
+
+        // Only a JSON decoder skips over blank lines at the end, every other record format must
+        // end exactly where the data does.
+        let skips_blank_lines = self.format() == SerializationFormat::Json && self.config.encryption.is_none();
+
+        let mut loaded = LoadedFile::new(Vec::new());
+        let mut cursor = Cursor::new(bytes);
+
+        loop {
+            let start = cursor.position();
+
+            let error = match self.decode_record(codec, &mut cursor) {
+                Ok(Some(entry)) => {
+                    loaded.entries.push(entry);
+                    continue;
+                }
+                Ok(None) if skips_blank_lines || start as usize == bytes.len() => break,
+                Ok(None) => anyhow::anyhow!("the file ends part way through a record"),
+                Err(e) => e,
+            };
+
+            let record = loaded.entries.len() + loaded.skipped;
+
+            if policy == CorruptionPolicy::Fail {
+                return Err(error.context(format!(
+                    "Record {} of the database file is corrupted, see `CorruptionPolicy` to recover the rest",
+                    record
+                )));
+            }
+
+            log::warn!("[Bootstrap] Skipping corrupted record {}: {}", record, error);
+            loaded.skipped += 1;
+
+            // Nothing follows a partial record, and truncating drops everything after a bad one.
+            if cursor.position() as usize >= bytes.len() || policy == CorruptionPolicy::SkipAndTruncate {
+                break;
+            }
+
+            // Make sure a decoder that failed without reading anything doesn't fail there forever.
+            if cursor.position() == start {
+                cursor.set_position(start + 1);
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Loads the entries in the database file into the cache.
+    ///
+    /// When `shared`, another namespace already has the file open and its records are taken
+    /// from `namespaces` instead of reading it again.
+    fn load_db_into_cache(&mut self, shared: bool) -> anyhow::Result<()>
+    {
+        let loaded = if shared {
+            let namespaces = lock(&self.namespaces);
+
+            LoadedFile::new(
+                namespaces
+                    .order
+                    .iter()
+                    .filter_map(|key| namespaces.entries.get(key))
+                    .cloned()
+                    .collect(),
+            )
+        } else {
+            let loaded = self.read_entries(self.config.on_corruption.unwrap_or_default())?;
+            self.file_records.store(loaded.entries.len(), Ordering::Relaxed);
+            loaded
+        };
+
+        self.load_report = LoadReport {
+            recovered: loaded.entries.len(),
+            skipped: loaded.skipped,
+        };
+
+        let mut state = write_lock(&self.state);
+        let mut namespaces = lock(&self.namespaces);
+
+        let prefix = self.namespace_prefix();
+
+        // Stale records and other namespaces make this an overestimate, never an underestimate.
+        state.reserve(loaded.entries.len());
+
+        // Later records win, since they were written after the ones before them.
+        for mut entry in loaded.entries {
+            if let Some(ref prefix) = prefix {
+                if !shared {
+                    sweep(&mut namespaces, entry.clone());
+                }
+
+                match entry.key.strip_prefix(prefix.as_str()) {
+                    Some(key) => entry.key = key.to_string(),
+                    None => continue,
+                }
+            }
+
+            sweep(&mut state, entry);
+        }
+
+        // Nothing is known about how keys were used before the restart, fall back to insertion order.
+        if let Some(ref access_order) = self.access_order {
+            let mut access_order = lock(access_order);
+
+            for key in state.order.iter() {
+                access_order.touch(key);
+            }
+        }
+
+        if let Some(max_entries) = self.config.max_entries {
+            self.evict(&mut state, max_entries);
+        }
+
+        if loaded.legacy {
+            log::info!("[Bootstrap] Migrating a database file written by an older version");
+        }
+
+        // Appending after a bad record would leave the new records unreadable too.
+        if loaded.legacy || loaded.skipped > 0 || self.evicted.load(Ordering::Relaxed) {
+            drop(namespaces);
+            self.rewrite_file(&state)?;
+        }
+
+        log::debug!("[Bootstrap] Loaded {} entries into cache", state.entries.len());
+
+        Ok(())
+    }
+}
+
+/// Inserts a record read from the database file into `state`.
+///
+/// Loading doubles as a sweep. An expired record still replaces older ones for its key, it just
+/// isn't kept. The file keeps it until the next rewrite.
+fn sweep<T>(state: &mut State<T>, entry: Entry<T>)
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone,
+{
+    if entry.is_expired() {
+        state.remove(&entry.key);
+    } else {
+        state.insert(entry);
+    }
+}
+
+/// Installs the stdout logger the first time a client with logging enabled is created.
+///
+/// There is only one global logger per process, so later clients share it and their
+/// `log_level` and `log_format` are ignored. If the application installed its own logger first,
+/// that one is kept.
+fn init_logger(level: LevelFilter, format: LogFormat)
+{
+    static LOGGER: Once = Once::new();
+
+    LOGGER.call_once(|| {
+        let result = match format {
+            LogFormat::Text => SimpleLogger::new()
+                .with_colors(true)
+                .with_level(level)
+                .with_timestamp_format(format_description!("[year]-[month]-[day] [hour]:[minute]:[second]"))
+                .init(),
+            LogFormat::Json => JsonLogger::init(level),
+        };
+
+        if result.is_err() {
+            log::debug!("[Bootstrap] A logger is already installed, keeping it");
+        }
+    });
+}
+
+/// Where a rewrite of the database file at `path` is written before it replaces it.
+fn temp_path(path: &str) -> String
+{
+    format!("{}.tmp", path)
+}
+
+/// Takes an exclusive lock on `<path>.lock`, held until the returned file is closed.
+///
+/// The lock lives in a file of its own because rewrites replace the database file, which
+/// would silently drop a lock taken on it. Fails with `QuickKvError::Locked` if another
+/// client, in this process or another one, already holds it.
+fn lock_database(path: &str) -> anyhow::Result<File>
+{
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(format!("{}.lock", path))?;
+
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(file),
+        Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+            Err(QuickKvError::Locked { path: path.to_string() }.into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Syncs the directory holding `path`, so a rename inside it survives a power loss.
+#[cfg(unix)]
+fn sync_parent_dir(path: &str) -> std::io::Result<()>
+{
+    let dir = std::path::Path::new(path)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| std::path::Path::new("."));
+
+    File::open(dir)?.sync_all()
+}
+
+/// Directories can't be opened for syncing here, the rename is as durable as the OS makes it.
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &str) -> std::io::Result<()>
+{
+    Ok(())
+}
+
+/// Decompresses a gzipped database file.
+fn gunzip(compressed: &[u8]) -> std::io::Result<Vec<u8>>
+{
+    let mut bytes = Vec::new();
+    GzDecoder::new(compressed).read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Flushes pending writes to disk and stops the background ttl task.
+///
+/// Takes the parts it needs rather than a `Database` so the signal handler can own them.
+fn flush_and_stop(writer: Option<&Arc<Mutex<BufWriter<File>>>>, ttl_manager: &TTLManager) -> anyhow::Result<()>
+{
+    if let Some(writer) = writer {
+        let mut w = lock(&writer);
+        w.flush()?;
+        w.get_ref().sync_all()?;
+    }
+
+    ttl_manager.stop();
+
+    Ok(())
+}
+
+/// Calls `flush_and_stop` when dropped.
+///
+/// Every clone of a `Database` shares one, so it's dropped along with the last clone. With
+/// `sync_on_write` turned off this is what gets the final writes onto the disk.
+#[derive(Debug)]
+pub(super) struct FlushOnDrop
+{
+    writer: Option<Arc<Mutex<BufWriter<File>>>>,
+    ttl_manager: Arc<TTLManager>,
+}
+
+impl Drop for FlushOnDrop
+{
+    fn drop(&mut self)
+    {
+        if let Err(e) = flush_and_stop(self.writer.as_ref(), &self.ttl_manager) {
+            log::error!("[SHUTDOWN] Failed to flush database: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::db::encryption::EncryptionConfig;
+
+    #[test]
+    fn test_database_new() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        let db = Database::<String>::new(config.clone())?;
+
+        assert_eq!(db.config.path, config.path);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_get_set() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("test", "test".to_string(), None)?;
+
+        assert_eq!(db.get("test".to_string()).unwrap().unwrap(), "test".to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_update() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("test", "test".to_string(), None)?;
+
+        let result = db.get("test".to_string())?.unwrap();
+
+        assert_eq!(result, "test".to_string());
+
+        db.update("test", "test2".to_string(), None, None)?;
+
+        let result = db.get("test".to_string())?.unwrap();
+
+        assert_eq!(result, "test2".to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_get_status() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        // Keep the background task out of the way so the expired key is still cached.
+        let config = DatabaseConfiguration {
+            ttl_interval: Some(Duration::from_secs(60)),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("present", "test".to_string(), None)?;
+        db.set("expired", "test".to_string(), Some(Duration::from_millis(50)))?;
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        assert_eq!(db.get_status("present")?, KeyStatus::Present("test".to_string()));
+        assert_eq!(db.get_status("expired")?, KeyStatus::Expired);
+        assert_eq!(db.get_status("absent")?, KeyStatus::Absent);
+
+        // Once reported, the expired key is gone for good.
+        assert_eq!(db.get_status("expired")?, KeyStatus::Absent);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_delete() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("test", "test".to_string(), None)?;
+
+        let result = db.get("test".to_string())?.unwrap();
+
+        assert_eq!(result, "test".to_string());
+
+        db.delete("test")?;
+
+        let result = db.get("test".to_string())?;
+
+        assert_eq!(result, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_delete_persists() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config.clone())?;
+
+        db.set("test", "test".to_string(), None)?;
+        db.set("test2", "test2".to_string(), None)?;
+        db.delete("test")?;
+
+        // Reload from disk to make sure the remaining records were written back intact.
+        drop(db);
+        let mut reloaded = Database::<String>::new(config)?;
+
+        assert_eq!(reloaded.get("test".to_string())?, None);
+        assert_eq!(reloaded.get("test2".to_string())?, Some("test2".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_delete_prefix() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config.clone())?;
+
+        db.set("user:1", "a".to_string(), None)?;
+        db.set("user:2", "b".to_string(), None)?;
+        db.set("post:1", "c".to_string(), None)?;
+
+        assert_eq!(db.delete_prefix("user:")?, 2);
+        assert_eq!(db.get("user:1".to_string())?, None);
+
+        drop(db);
+        let mut reloaded = Database::<String>::new(config)?;
+
+        assert_eq!(reloaded.get("user:2".to_string())?, None);
+        assert_eq!(reloaded.get("post:1".to_string())?, Some("c".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_retain() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config.clone())?;
+
+        db.set("a", "keep".to_string(), None)?;
+        db.set("b", "drop".to_string(), None)?;
+        db.set("c", "keep".to_string(), None)?;
+
+        assert_eq!(db.retain(|_, value| value == "keep")?, 1);
+
+        drop(db);
+        let mut reloaded = Database::<String>::new(config)?;
+
+        assert_eq!(reloaded.get("a".to_string())?, Some("keep".to_string()));
+        assert_eq!(reloaded.get("b".to_string())?, None);
+        assert_eq!(reloaded.get("c".to_string())?, Some("keep".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_clear_expired() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            ttl_interval: Some(Duration::from_secs(60)),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("short", "test".to_string(), Some(Duration::from_millis(50)))?;
+        db.set("long", "test".to_string(), None)?;
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        assert_eq!(db.clear_expired()?, 1);
+        assert_eq!(db.state.read().unwrap().entries.len(), 1);
+        assert!(db.state.read().unwrap().expirations.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_transaction_update_skips_expired() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            ttl_interval: Some(Duration::from_secs(60)),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("short", "test".to_string(), Some(Duration::from_millis(50)))?;
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        db.transaction(|batch| {
+            assert!(batch.is_empty());
+            batch.update("short", "updated".to_string(), None);
+            assert!(!batch.is_empty());
+            Ok(())
+        })?;
+
+        assert_eq!(db.get("short".to_string())?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_compact() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config.clone())?;
+
+        // Each set appends a new record, leaving stale copies of the key behind.
+        for i in 0..10 {
+            db.set("test", format!("value{}", i), None)?;
+        }
+
+        let size_before = std::fs::metadata(&tmp_file)?.len();
+
+        db.compact()?;
+
+        let size_after = std::fs::metadata(&tmp_file)?.len();
+
+        assert!(size_after < size_before);
+
+        drop(db);
+        let mut reloaded = Database::<String>::new(config)?;
+
+        assert_eq!(reloaded.get("test".to_string())?, Some("value9".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_rejects_directory_path() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let dir_path = tmp_dir.path().to_str().unwrap().to_string();
+
+        let err = DatabaseConfiguration::new(Some(dir_path.clone()), None, None, None, None).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<QuickKvError>(),
+            Some(QuickKvError::InvalidPath { .. })
+        ));
+
+        // Configurations built by hand are checked again when the database opens.
+        let config = DatabaseConfiguration {
+            path: Some(dir_path),
+            ..DatabaseConfiguration::default()
+        };
+
+        let err = Database::<String>::new(config).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<QuickKvError>(),
+            Some(QuickKvError::InvalidPath { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_rejects_zero_ttl() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let err = DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, Some(Duration::ZERO)).unwrap_err();
+
+        assert!(matches!(err.downcast_ref::<QuickKvError>(), Some(QuickKvError::InvalidTtl)));
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        let mut db = Database::<String>::new(config)?;
+
+        let err = db.set("test", "test".to_string(), Some(Duration::ZERO)).unwrap_err();
+
+        assert!(matches!(err.downcast_ref::<QuickKvError>(), Some(QuickKvError::InvalidTtl)));
+        assert_eq!(db.get_status("test")?, KeyStatus::Absent);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_formats_persist() -> Result<()>
+    {
+        for format in [SerializationFormat::Bincode, SerializationFormat::Json] {
+            let tmp_dir = tempdir().expect("Failed to create tempdir");
+            let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+            let config = DatabaseConfiguration {
+                format: Some(format),
+                ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+            };
+
+            let mut db = Database::<String>::new(config.clone())?;
+
+            db.set("test", "test".to_string(), None)?;
+
+            drop(db);
+            let mut reloaded = Database::<String>::new(config)?;
+
+            assert_eq!(reloaded.get("test".to_string())?, Some("test".to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_json_is_ndjson() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            format: Some(SerializationFormat::Json),
+            ..DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("first", "one".to_string(), None)?;
+        db.set("second", "two".to_string(), None)?;
+
+        let contents = std::fs::read(&tmp_file)?;
+        let contents = std::str::from_utf8(&contents[header::HEADER_LEN..])?;
+        let lines: Vec<&str> = contents.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+
+        for (line, key) in lines.iter().zip(["first", "second"]) {
+            let value: serde_json::Value = serde_json::from_str(line)?;
+            assert_eq!(value["key"], key);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_entries_expiring_before() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("soon", "soon".to_string(), Some(Duration::from_secs(10)))?;
+        db.set("sooner", "sooner".to_string(), Some(Duration::from_secs(5)))?;
+        db.set("later", "later".to_string(), Some(Duration::from_secs(600)))?;
+        db.set("forever", "forever".to_string(), None)?;
+
+        let expiring = db.entries_expiring_before(Utc::now() + chrono::Duration::seconds(60))?;
+
+        assert_eq!(
+            expiring,
+            vec![
+                ("sooner".to_string(), "sooner".to_string()),
+                ("soon".to_string(), "soon".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_range() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        let mut db = Database::<String>::new(config)?;
+
+        for key in ["a", "b", "c", "d"] {
+            db.set(key, key.to_uppercase(), None)?;
+        }
+
+        // Start is inclusive, end is exclusive.
+        assert_eq!(
+            db.range("b", "d")?,
+            vec![("b".to_string(), "B".to_string()), ("c".to_string(), "C".to_string())]
+        );
+
+        db.delete("c")?;
+
+        assert_eq!(db.range("b", "d")?, vec![("b".to_string(), "B".to_string())]);
+
+        // Empty and inverted ranges return nothing.
+        assert!(db.range("b", "b")?.is_empty());
+        assert!(db.range("d", "a")?.is_empty());
+        assert!(db.range("x", "z")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_flush_and_stop() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        let mut db = Database::<String>::new(config.clone())?;
+
+        db.set("test", "test".to_string(), None)?;
+        flush_and_stop(db.writer.as_ref(), &db.ttl_manager)?;
+
+        drop(db);
+        let mut reloaded = Database::<String>::new(config)?;
+
+        assert_eq!(reloaded.get("test".to_string())?, Some("test".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_drop_flushes() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            sync_on_write: Some(false),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config.clone())?;
+        let clone = db.clone();
+
+        db.set("first", "one".to_string(), None)?;
+
+        // Dropping a clone leaves the database running.
+        drop(clone);
+        db.set("last", "two".to_string(), None)?;
+
+        drop(db);
+
+        let mut reloaded = Database::<String>::new(config)?;
+
+        assert_eq!(reloaded.get("first".to_string())?, Some("one".to_string()));
+        assert_eq!(reloaded.get("last".to_string())?, Some("two".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_validate_schema() -> Result<()>
+    {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, serde::Deserialize)]
+        struct V1
+        {
+            name: String,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, serde::Deserialize)]
+        struct V2
+        {
+            name: String,
+            #[serde(default)]
+            age: u8,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, serde::Deserialize)]
+        struct V2Required
+        {
+            name: String,
+            age: u8,
+        }
+
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            format: Some(SerializationFormat::Json),
+            on_corruption: Some(CorruptionPolicy::SkipAndContinue),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<V1>::new(config.clone())?;
+
+        for i in 0..3 {
+            db.set(
+                &format!("user_{}", i),
+                V1 {
+                    name: format!("user {}", i),
+                },
+                None,
+            )?;
+        }
+
+        assert_eq!(db.validate_schema()?, 0);
+        drop(db);
+
+        // A new field with a default still reads the old records.
+        let mut db = Database::<V2>::new(config.clone())?;
+
+        assert_eq!(db.validate_schema()?, 0);
+        assert_eq!(db.get("user_1".to_string())?.map(|user| user.age), Some(0));
+        drop(db);
+
+        // Opened read-only, so loading doesn't rewrite the file without the records it skipped.
+        let mut db = Database::<V2Required>::new(DatabaseConfiguration {
+            read_only: Some(true),
+            ..config
+        })?;
+
+        assert_eq!(db.load_report().skipped, 3);
+        assert_eq!(db.validate_schema()?, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_flush_interval() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            flush_interval: Some(Duration::from_millis(50)),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<u64>::new(config.clone())?;
+
+        for i in 0..100 {
+            db.set(&format!("key_{}", i), i, None)?;
+        }
+
+        // Give the flush task a few ticks to pick up whatever is left.
+        std::thread::sleep(Duration::from_millis(200));
+
+        let syncs = db.syncs.load(Ordering::Relaxed);
+
+        assert!(syncs > 0);
+        assert!(syncs < 100);
+
+        drop(db);
+
+        let mut reloaded = Database::<u64>::new(config)?;
+
+        for i in 0..100 {
+            assert_eq!(reloaded.get(format!("key_{}", i))?, Some(i));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_update_refreshes_ttl() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        let mut db = Database::<String>::new(config.clone())?;
+
+        db.set("test", "test".to_string(), Some(Duration::from_secs(10)))?;
+        db.update("test", "updated".to_string(), Some(Duration::from_secs(100)), None)?;
+
+        let remaining = db.ttl("test")?.unwrap();
+        assert!(remaining > Duration::from_secs(90));
+
+        {
+            let state = db.state.read().unwrap();
+            let expires_at = state.entries.get("test").unwrap().expires_at.unwrap();
+
+            // The old expiration is gone, only the refreshed one is tracked.
+            assert_eq!(state.expirations.len(), 1);
+            assert!(state.expirations.contains(&(expires_at, "test".to_string())));
+        }
+
+        // The file agrees with the cache.
+        drop(db);
+        let reloaded = Database::<String>::new(config)?;
+        assert!(reloaded.ttl("test")?.unwrap() > Duration::from_secs(90));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_update_missing_key() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        let mut db = Database::<String>::new(config)?;
+
+        assert!(!db.update("test", "test".to_string(), None, None)?);
+        assert!(!db.update("test", "test".to_string(), None, Some(false))?);
+        assert_eq!(db.get("test".to_string())?, None);
+
+        assert!(db.update("test", "test".to_string(), None, Some(true))?);
+        assert_eq!(db.get("test".to_string())?, Some("test".to_string()));
+
+        assert!(db.update("test", "test2".to_string(), None, None)?);
+        assert_eq!(db.get("test".to_string())?, Some("test2".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_logging_clients_coexist() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+
+        for name in ["first.qkv", "second.qkv"] {
+            let tmp_file = tmp_dir.path().join(name).to_str().unwrap().to_string();
+            let config = DatabaseConfiguration::new(Some(tmp_file), None, Some(true), Some(LevelFilter::Debug), None)?;
+
+            assert!(Database::<String>::new(config).is_ok());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_load_drops_expired() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config.clone())?;
+        db.set("kept", "value".to_string(), None)?;
+        db.set("expired", "value".to_string(), Some(Duration::from_millis(50)))?;
+        drop(db);
+
+        std::thread::sleep(Duration::from_millis(100));
+
+        let db = Database::<String>::new(config)?;
+        let state = db.state.read().unwrap();
+
+        assert!(state.entries.contains_key("kept"));
+        assert!(!state.entries.contains_key("expired"));
+        assert!(state.expirations.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_update_appends() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            // Never compact, so every update stays in the file.
+            compaction_threshold: Some(100),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config.clone())?;
+        db.set("key", "first".to_string(), None)?;
+        db.update("key", "second".to_string(), None, None)?;
+        db.update("key", "third".to_string(), None, None)?;
+
+        assert_eq!(db.file_records.load(Ordering::Relaxed), 3);
+        drop(db);
+
+        // The last record written wins on reload.
+        let mut db = Database::<String>::new(config)?;
+
+        assert_eq!(db.get("key".to_string())?, Some("third".to_string()));
+        assert_eq!(db.file_records.load(Ordering::Relaxed), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_update_compacts() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            compaction_threshold: Some(50),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config)?;
+        db.set("a", "value".to_string(), None)?;
+        db.set("b", "value".to_string(), None)?;
+
+        for i in 0..10 {
+            db.update("a", format!("value_{}", i), None, None)?;
+
+            // Two live keys, so more than two stale records would pass the threshold.
+            assert!(db.file_records.load(Ordering::Relaxed) <= 4);
+        }
+
+        assert_eq!(
+            db.read_entries(CorruptionPolicy::Fail)?.entries.len(),
+            db.file_records.load(Ordering::Relaxed)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_max_entries() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            max_entries: Some(2),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("a", "value".to_string(), None)?;
+        db.set("b", "value".to_string(), None)?;
+
+        let err = db.set("c", "value".to_string(), None).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<QuickKvError>(),
+            Some(QuickKvError::CapacityExceeded { max_entries: 2 })
+        ));
+
+        let err = db.update("c", "value".to_string(), None, Some(true)).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<QuickKvError>(),
+            Some(QuickKvError::CapacityExceeded { .. })
+        ));
+        assert_eq!(db.get("c".to_string())?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_max_entries_batches_change_nothing_when_full() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            max_entries: Some(2),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+        let mut db = Database::<String>::new(config.clone())?;
+
+        db.set("a", "value".to_string(), None)?;
+
+        let assert_unchanged = |db: &Database<String>, err: anyhow::Error| {
+            assert!(matches!(
+                err.downcast_ref::<QuickKvError>(),
+                Some(QuickKvError::CapacityExceeded { max_entries: 2 })
+            ));
+
+            let state = read_lock(&db.state);
+            assert_eq!(state.entries.len(), 1);
+            assert!(state.entries.contains_key("a"));
+        };
+
+        let err = db
+            .transaction(|batch| {
+                batch.set("b", "value".to_string());
+                batch.set("c", "value".to_string());
+                Ok(())
+            })
+            .unwrap_err();
+        assert_unchanged(&db, err);
+
+        let ops = ["b", "c"]
+            .into_iter()
+            .map(|key| PipelineOp::Set {
+                key: key.to_string(),
+                value: "value".to_string(),
+            })
+            .collect();
+        let err = db.execute_pipeline(ops).unwrap_err();
+        assert_unchanged(&db, err);
+
+        let entries = vec![
+            ("b".to_string(), "value".to_string(), None),
+            ("c".to_string(), "value".to_string(), None),
+        ];
+        let err = db.set_batch(entries).unwrap_err();
+        assert_unchanged(&db, err);
+
+        let err = db
+            .read_modify_write_many(&["b", "c"], |_, _| Ok("value".to_string()))
+            .unwrap_err();
+        assert_unchanged(&db, err);
+
+        let source_file = tmp_dir.path().join("source.qkv").to_str().unwrap().to_string();
+        let mut source = Database::<String>::new(DatabaseConfiguration::new(Some(source_file), None, None, None, None)?)?;
+
+        for key in ["x", "y"] {
+            source.set(key, "value".to_string(), None)?;
+        }
+
+        let mut backup = Vec::new();
+        source.export(&mut backup)?;
+
+        let err = db.import(&mut backup.as_slice()).unwrap_err();
+        assert_unchanged(&db, err);
+
+        // Deletes earlier in the batch free up room for later writes.
+        db.transaction(|batch| {
+            batch.delete("a");
+            batch.set("b", "value".to_string());
+            batch.set("c", "value".to_string());
+            Ok(())
+        })?;
+
+        drop(db);
+
+        // Nothing from the failed batches made it to the file either.
+        let mut db = Database::<String>::new(DatabaseConfiguration {
+            eviction: Some(EvictionPolicy::Lru),
+            ..config
+        })?;
+
+        let mut keys = read_lock(&db.state).entries.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, vec!["b", "c"]);
+
+        // With eviction, the batch makes room instead of failing.
+        db.transaction(|batch| {
+            for key in ["d", "e", "f"] {
+                batch.set(key, "value".to_string());
+            }
+            Ok(())
+        })?;
+
+        assert_eq!(read_lock(&db.state).entries.len(), 2);
+        assert!(db.exists("f")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_import_rejects_oversized_records() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let mut db = Database::<String>::new(DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?)?;
+
+        let oversized = (MAX_IMPORT_RECORD_LEN + 1).to_le_bytes();
+        assert!(db.import(&mut oversized.as_slice()).is_err());
+
+        // A length that fits the limit but runs past the end of the stream.
+        let mut truncated = 1024u64.to_le_bytes().to_vec();
+        truncated.extend_from_slice(b"short");
+        assert!(db.import(&mut truncated.as_slice()).is_err());
+
+        assert!(read_lock(&db.state).entries.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_max_entries_allows_overwrites() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            max_entries: Some(2),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("a", "value".to_string(), None)?;
+        db.set("b", "value".to_string(), None)?;
+
+        db.set("a", "set".to_string(), None)?;
+        assert!(db.update("b", "updated".to_string(), None, None)?);
+
+        assert_eq!(db.get("a".to_string())?, Some("set".to_string()));
+        assert_eq!(db.get("b".to_string())?, Some("updated".to_string()));
+
+        // Deleting frees a slot for a new key.
+        db.delete("a")?;
+        db.set("c", "value".to_string(), None)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_lru_eviction() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            max_entries: Some(3),
+            eviction: Some(EvictionPolicy::Lru),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+        let mut db = Database::<String>::new(config.clone())?;
+
+        db.set("a", "value".to_string(), None)?;
+        db.set("b", "value".to_string(), None)?;
+        db.set("c", "value".to_string(), None)?;
+
+        // Reading `a` makes `b` the least recently used key.
+        db.get("a".to_string())?;
+        db.set("d", "value".to_string(), None)?;
+
+        assert_eq!(db.get("b".to_string())?, None);
+        assert!(db.exists("a")?);
+        assert!(db.exists("c")?);
+        assert!(db.exists("d")?);
+
+        // Writes count as a use too.
+        db.set("c", "updated".to_string(), None)?;
+        db.set("e", "value".to_string(), None)?;
+
+        assert_eq!(db.get("a".to_string())?, None);
+        assert_eq!(read_lock(&db.state).entries.len(), 3);
+        drop(db);
+
+        // Evicted keys don't come back on reload.
+        let db = Database::<String>::new(config)?;
 
-        db.delete("test")?;
+        let mut keys = read_lock(&db.state).entries.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, vec!["c", "d", "e"]);
 
-        let result = db.get("test".to_string())?;
+        Ok(())
+    }
 
-        assert_eq!(result, None);
+    #[test]
+    fn test_database_set_batch() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            track_write_latency: Some(true),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config.clone())?;
+
+        let entries = (0..100)
+            .map(|i| (format!("key_{}", i), i.to_string(), None))
+            .collect::<Vec<_>>();
+
+        db.set_batch(entries)?;
+
+        // One write for the whole batch.
+        assert_eq!(db.write_latency_stats().count, 1);
+        drop(db);
+
+        let mut db = Database::<String>::new(config)?;
+
+        for i in 0..100 {
+            assert_eq!(db.get(format!("key_{}", i))?, Some(i.to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_metadata() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+        let mut db = Database::<String>::new(config.clone())?;
+
+        assert_eq!(db.metadata("key")?, None);
+
+        db.set("key", "first".to_string(), None)?;
+        let created = db.metadata("key")?.unwrap();
+
+        assert_eq!(created.created_at, created.updated_at);
+        assert_eq!(created.expires_at, None);
+
+        std::thread::sleep(Duration::from_millis(10));
+        db.update("key", "second".to_string(), None, None)?;
+        let updated = db.metadata("key")?.unwrap();
+
+        assert_eq!(updated.created_at, created.created_at);
+        assert!(updated.updated_at > created.updated_at);
+        drop(db);
+
+        // The update was appended, reloading must not lose the original creation time.
+        let mut db = Database::<String>::new(config)?;
+
+        assert_eq!(db.metadata("key")?, Some(updated));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_file_header() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config.clone())?;
+        db.set("key", "value".to_string(), None)?;
+        drop(db);
+
+        let bytes = std::fs::read(&tmp_file)?;
+        assert_eq!(&bytes[..header::HEADER_LEN], &header::encode(SerializationFormat::Bincode));
+
+        let mut db = Database::<String>::new(config.clone())?;
+        assert_eq!(db.get("key".to_string())?, Some("value".to_string()));
+        drop(db);
+
+        let mut corrupted = bytes;
+        corrupted[..header::MAGIC.len()].copy_from_slice(b"NOPE");
+        std::fs::write(&tmp_file, corrupted)?;
+
+        let err = QuickKvError::from(Database::<String>::new(config).unwrap_err());
+        assert!(matches!(err, QuickKvError::BadFormat { .. }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_migrates_legacy_file() -> Result<()>
+    {
+        #[derive(Serialize)]
+        struct LegacyRecord
+        {
+            key: String,
+            data: String,
+            expires_at: Option<DateTime<Utc>>,
+        }
+
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        // A headerless file, as written before entries had timestamps.
+        let mut legacy = Vec::new();
+
+        for i in 0..3 {
+            legacy.extend(bincode::serialize(&LegacyRecord {
+                key: format!("key_{}", i),
+                data: format!("value_{}", i),
+                expires_at: None,
+            })?);
+        }
+
+        std::fs::write(&tmp_file, legacy)?;
+
+        let config = DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, None)?;
+        let mut db = Database::<String>::new(config.clone())?;
+
+        for i in 0..3 {
+            assert_eq!(db.get(format!("key_{}", i))?, Some(format!("value_{}", i)));
+        }
+
+        assert_eq!(db.metadata("key_0")?.unwrap().created_at, DateTime::<Utc>::default());
+        drop(db);
+
+        let bytes = std::fs::read(&tmp_file)?;
+        assert_eq!(&bytes[..header::HEADER_LEN], &header::encode(SerializationFormat::Bincode));
+
+        let mut db = Database::<String>::new(config)?;
+
+        for i in 0..3 {
+            assert_eq!(db.get(format!("key_{}", i))?, Some(format!("value_{}", i)));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_migrates_unframed_records() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        // Version 1 wrote bincode records back to back, without their length.
+        let mut old = header::encode(SerializationFormat::Bincode).to_vec();
+        old[header::HEADER_LEN - 1] = 1;
+
+        for i in 0..3 {
+            old.extend(bincode::serialize(&Entry::new(format!("key_{}", i), i.to_string(), None))?);
+        }
+
+        std::fs::write(&tmp_file, old)?;
+
+        let config = DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, None)?;
+        let mut db = Database::<String>::new(config.clone())?;
+
+        for i in 0..3 {
+            assert_eq!(db.get(format!("key_{}", i))?, Some(i.to_string()));
+        }
+
+        drop(db);
+
+        let bytes = std::fs::read(&tmp_file)?;
+        assert_eq!(&bytes[..header::HEADER_LEN], &header::encode(SerializationFormat::Bincode));
+
+        let mut db = Database::<String>::new(config)?;
+
+        for i in 0..3 {
+            assert_eq!(db.get(format!("key_{}", i))?, Some(i.to_string()));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_encryption() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            encryption: Some(EncryptionConfig::new([1; 32])),
+            ..DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config.clone())?;
+        db.set("secret", "hunter2".to_string(), None)?;
+        db.update("secret", "correct horse".to_string(), None, None)?;
+        drop(db);
+
+        let bytes = std::fs::read(&tmp_file)?;
+        assert!(!bytes.windows(b"hunter2".len()).any(|window| window == b"hunter2"));
+
+        let mut db = Database::<String>::new(config.clone())?;
+        assert_eq!(db.get("secret".to_string())?, Some("correct horse".to_string()));
+        drop(db);
+
+        let wrong_key = DatabaseConfiguration {
+            encryption: Some(EncryptionConfig::new([2; 32])),
+            ..config
+        };
+
+        let err = QuickKvError::from(Database::<String>::new(wrong_key).unwrap_err());
+        assert!(matches!(err, QuickKvError::Decryption));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_truncates_partial_record() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config.clone())?;
+
+        for i in 0..3 {
+            db.set(&format!("key_{}", i), i.to_string(), None)?;
+        }
+
+        drop(db);
+
+        // What a crash part way through an append leaves behind: a record length with only the
+        // start of the record.
+        let mut file = OpenOptions::new().append(true).open(&tmp_file)?;
+        file.write_all(&[40, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, b'k', b'e'])?;
+        drop(file);
+
+        assert!(Database::<String>::new(config.clone()).is_err());
+
+        let recovering = DatabaseConfiguration {
+            on_corruption: Some(CorruptionPolicy::SkipAndTruncate),
+            ..config.clone()
+        };
+
+        let mut db = Database::<String>::new(recovering)?;
+
+        assert_eq!(
+            db.load_report(),
+            LoadReport {
+                recovered: 3,
+                skipped: 1
+            }
+        );
+
+        for i in 0..3 {
+            assert_eq!(db.get(format!("key_{}", i))?, Some(i.to_string()));
+        }
+
+        db.set("key_3", "3".to_string(), None)?;
+        drop(db);
+
+        // The partial record was cut off, so the file loads cleanly again.
+        let mut db = Database::<String>::new(config)?;
+
+        assert_eq!(
+            db.load_report(),
+            LoadReport {
+                recovered: 4,
+                skipped: 0
+            }
+        );
+        assert_eq!(db.get("key_3".to_string())?, Some("3".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_skips_corrupted_record() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            format: Some(SerializationFormat::Json),
+            on_corruption: Some(CorruptionPolicy::SkipAndContinue),
+            ..DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config.clone())?;
+        db.set("first", "one".to_string(), None)?;
+        drop(db);
+
+        let mut file = OpenOptions::new().append(true).open(&tmp_file)?;
+        file.write_all(b"{\"key\": \"broken\"\n")?;
+        drop(file);
+
+        let mut db = Database::<String>::new(config.clone())?;
+        db.set("second", "two".to_string(), None)?;
+        drop(db);
+
+        let mut db = Database::<String>::new(config)?;
+
+        assert_eq!(db.get("first".to_string())?, Some("one".to_string()));
+        assert_eq!(db.get("second".to_string())?, Some("two".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_detects_bit_rot() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config.clone())?;
+        db.set("first", "one".to_string(), None)?;
+        db.set("second", "two".to_string(), None)?;
+        db.set("third", "three".to_string(), None)?;
+        drop(db);
+
+        // A single flipped bit turns "two" into "twn", a perfectly valid string.
+        let mut bytes = std::fs::read(&tmp_file)?;
+        let two = bytes.windows(3).position(|window| window == b"two").unwrap();
+        bytes[two + 2] ^= 1;
+        std::fs::write(&tmp_file, bytes)?;
+
+        assert!(Database::<String>::new(config.clone()).is_err());
+
+        let recovering = DatabaseConfiguration {
+            on_corruption: Some(CorruptionPolicy::SkipAndContinue),
+            ..config
+        };
+
+        let mut db = Database::<String>::new(recovering)?;
+
+        assert_eq!(
+            db.load_report(),
+            LoadReport {
+                recovered: 2,
+                skipped: 1
+            }
+        );
+        assert_eq!(db.get("second".to_string())?, None);
+        assert_eq!(db.get("third".to_string())?, Some("three".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_interrupted_rewrite_keeps_file() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file.clone()), None, None, None, None)?;
+
+        let mut db = Database::<String>::new(config.clone())?;
+        db.set("first", "one".to_string(), None)?;
+        db.set("second", "two".to_string(), None)?;
+
+        let before = std::fs::read(&tmp_file)?;
+
+        // Write the rewrite out but crash before the rename.
+        let mut replacement = State::new();
+        replacement.insert(Entry::new("third".to_string(), "three".to_string(), None));
+        db.write_temp_file(&replacement)?;
+        drop(db);
+
+        assert_eq!(std::fs::read(&tmp_file)?, before);
+
+        let mut db = Database::<String>::new(config.clone())?;
+
+        assert_eq!(db.get("first".to_string())?, Some("one".to_string()));
+        assert_eq!(db.get("third".to_string())?, None);
+
+        // A completed rewrite replaces the file and keeps working with the new one.
+        db.delete("first")?;
+        db.set("fourth", "four".to_string(), None)?;
+        drop(db);
+
+        let mut db = Database::<String>::new(config)?;
+
+        assert_eq!(db.get("first".to_string())?, None);
+        assert_eq!(db.get("second".to_string())?, Some("two".to_string()));
+        assert_eq!(db.get("fourth".to_string())?, Some("four".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_file_lock() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?;
+
+        let db = Database::<String>::new(config.clone())?;
+
+        let err = Database::<String>::new(config.clone()).unwrap_err();
+        assert!(matches!(QuickKvError::from(err), QuickKvError::Locked { .. }));
+
+        // Readers don't take the lock.
+        let read_only = DatabaseConfiguration {
+            read_only: Some(true),
+            ..config.clone()
+        };
+        Database::<String>::new(read_only)?;
+
+        drop(db);
+
+        Database::<String>::new(config)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_max_key_len() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            max_key_len: Some(8),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("short", "value".to_string(), None)?;
+
+        let err = db.set("much_too_long", "value".to_string(), None).unwrap_err();
+        assert!(matches!(QuickKvError::from(err), QuickKvError::InvalidKey { .. }));
+
+        let err = db.update("much_too_long", "value".to_string(), None, Some(true)).unwrap_err();
+        assert!(matches!(QuickKvError::from(err), QuickKvError::InvalidKey { .. }));
+
+        assert_eq!(db.get("much_too_long".to_string())?, None);
+        assert_eq!(db.get("short".to_string())?, Some("value".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_io_buffer_size() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        // Smaller than a single record, so every read and write goes past the buffer.
+        let config = DatabaseConfiguration {
+            io_buffer_size: Some(4),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config.clone())?;
+
+        for i in 0..100 {
+            db.set(&format!("key{}", i), format!("value{}", i), None)?;
+        }
+
+        db.delete("key0")?;
+        db.set("key1", "updated".to_string(), None)?;
+        drop(db);
+
+        let mut db = Database::<String>::new(config)?;
+
+        assert_eq!(db.get("key0".to_string())?, None);
+        assert_eq!(db.get("key1".to_string())?, Some("updated".to_string()));
+        assert_eq!(db.get("key99".to_string())?, Some("value99".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_database_key_validator() -> Result<()>
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = DatabaseConfiguration {
+            key_validator: Some(|key| !key.contains(' ')),
+            ..DatabaseConfiguration::new(Some(tmp_file), None, None, None, None)?
+        };
+
+        let mut db = Database::<String>::new(config)?;
+
+        db.set("user_1", "jamal".to_string(), None)?;
+
+        let err = db.set("user 2", "bob".to_string(), None).unwrap_err();
+        assert!(matches!(QuickKvError::from(err), QuickKvError::InvalidKey { .. }));
+
+        assert!(db.rename("user_1", "user 1").is_err());
+        assert_eq!(db.get("user_1".to_string())?, Some("jamal".to_string()));
 
         Ok(())
     }
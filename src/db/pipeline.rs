@@ -0,0 +1,105 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::db::Database;
+use crate::error::QuickKvError;
+
+/// Operations queued with `BaseClient::pipeline`, run together by `execute`.
+///
+/// Unlike calling each method on the client, every operation runs under a single lock and
+/// writes are persisted once at the end.
+#[derive(Debug)]
+pub struct Pipeline<'a, T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
+{
+    db: &'a mut Database<T>,
+    ops: Vec<PipelineOp<T>>,
+}
+
+/// The outcome of one queued operation, in the order it was queued.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineResult<T>
+{
+    /// The value a `get` found, `None` if the key was missing or had expired.
+    Get(Option<T>),
+    /// A `set` was applied.
+    Set,
+    /// Whether a `delete` removed a key.
+    Delete(bool),
+}
+
+#[derive(Debug)]
+pub(crate) enum PipelineOp<T>
+{
+    Get
+    {
+        key: String
+    },
+    Set
+    {
+        key: String, value: T
+    },
+    Delete
+    {
+        key: String
+    },
+}
+
+impl<'a, T> Pipeline<'a, T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
+{
+    pub(crate) fn new(db: &'a mut Database<T>) -> Self
+    {
+        Self { db, ops: Vec::new() }
+    }
+
+    /// Queues reading `key`, it sees the writes queued before it.
+    pub fn get(&mut self, key: &str) -> &mut Self
+    {
+        self.ops.push(PipelineOp::Get { key: key.to_string() });
+        self
+    }
+
+    /// Queues setting `key` to `value`, with the default ttl if one is configured.
+    pub fn set(&mut self, key: &str, value: T) -> &mut Self
+    {
+        self.ops.push(PipelineOp::Set {
+            key: key.to_string(),
+            value,
+        });
+        self
+    }
+
+    /// Queues deleting `key`.
+    pub fn delete(&mut self, key: &str) -> &mut Self
+    {
+        self.ops.push(PipelineOp::Delete { key: key.to_string() });
+        self
+    }
+
+    /// The number of queued operations.
+    pub fn len(&self) -> usize
+    {
+        self.ops.len()
+    }
+
+    /// Whether nothing has been queued yet.
+    pub fn is_empty(&self) -> bool
+    {
+        self.ops.is_empty()
+    }
+
+    /// Runs every queued operation in order and returns their results in the same order.
+    ///
+    /// Keys are checked before anything runs, so an invalid key fails the whole pipeline
+    /// without applying any of it.
+    pub fn execute(self) -> Result<Vec<PipelineResult<T>>, QuickKvError>
+    {
+        Ok(self.db.execute_pipeline(self.ops)?)
+    }
+}
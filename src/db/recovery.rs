@@ -0,0 +1,64 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::db::entry::Entry;
+
+/// What to do when a record in the database file can't be read on load.
+///
+/// A crash in the middle of an append leaves a partial record at the end of the file, which
+/// is the usual way for a file to end up corrupted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CorruptionPolicy
+{
+    /// Refuse to open the database.
+    #[default]
+    Fail,
+    /// Keep every record before the bad one and drop the rest of the file.
+    SkipAndTruncate,
+    /// Skip the bad record and keep reading after it.
+    ///
+    /// Every record carries its length, or ends at a newline for JSON, so a bad record can be
+    /// skipped on its own. Only a corrupted length loses the records after it as well.
+    SkipAndContinue,
+}
+
+/// How many records were read from the database file when it was opened, see `load_report`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadReport
+{
+    /// Records read successfully, including ones later replaced or expired.
+    pub recovered: usize,
+    /// Records that couldn't be read and were dropped. With `CorruptionPolicy::SkipAndTruncate`
+    /// everything from the first bad record on counts as one.
+    pub skipped: usize,
+}
+
+/// The records read from the database file.
+pub(crate) struct LoadedFile<T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync,
+{
+    pub(crate) entries: Vec<Entry<T>>,
+    /// The file predates the header or the current format version, and has to be rewritten in
+    /// the current layout.
+    pub(crate) legacy: bool,
+    /// How many records were dropped by the `CorruptionPolicy`.
+    pub(crate) skipped: usize,
+}
+
+impl<T> LoadedFile<T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync,
+{
+    pub(crate) fn new(entries: Vec<Entry<T>>) -> Self
+    {
+        Self {
+            entries,
+            legacy: false,
+            skipped: 0,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::any::Any;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, Weak};
+
+use crate::db::codec::{Compression, SerializationFormat};
+use crate::db::flush::FlushManager;
+use crate::db::state::State;
+use crate::error::lock;
+use crate::types::HashMap;
+
+/// The parts of an open database file that every namespace opened on it shares.
+///
+/// The first namespace opened on a file takes the lock on it as usual, the ones opened while it
+/// is still open join it through `OpenFiles` instead of being refused with `Locked`.
+pub(super) struct SharedFile<T>
+{
+    pub(super) writer: Arc<Mutex<BufWriter<File>>>,
+    pub(super) reader: Arc<Mutex<BufReader<File>>>,
+    pub(super) file_lock: Arc<File>,
+    pub(super) file_records: Arc<AtomicUsize>,
+    pub(super) syncs: Arc<AtomicUsize>,
+    pub(super) flush_manager: Option<Arc<FlushManager>>,
+    pub(super) namespaces: Arc<Mutex<State<T>>>,
+}
+
+/// `SharedFile` without keeping the file open, the registry shouldn't outlive the databases.
+struct WeakFile<T>
+{
+    writer: Weak<Mutex<BufWriter<File>>>,
+    reader: Weak<Mutex<BufReader<File>>>,
+    file_lock: Weak<File>,
+    file_records: Weak<AtomicUsize>,
+    syncs: Weak<AtomicUsize>,
+    flush_manager: Option<Weak<FlushManager>>,
+    namespaces: Weak<Mutex<State<T>>>,
+    format: SerializationFormat,
+    compression: Option<Compression>,
+}
+
+impl<T> WeakFile<T>
+{
+    /// `None` once every database on the file was dropped.
+    fn upgrade(&self) -> Option<SharedFile<T>>
+    {
+        Some(SharedFile {
+            writer: self.writer.upgrade()?,
+            reader: self.reader.upgrade()?,
+            file_lock: self.file_lock.upgrade()?,
+            file_records: self.file_records.upgrade()?,
+            syncs: self.syncs.upgrade()?,
+            flush_manager: match self.flush_manager {
+                Some(ref flush_manager) => Some(flush_manager.upgrade()?),
+                None => None,
+            },
+            namespaces: self.namespaces.upgrade()?,
+        })
+    }
+}
+
+/// Namespaced database files open in this process, by canonical path.
+pub(super) struct OpenFiles(HashMap<PathBuf, Box<dyn Any + Send>>);
+
+impl OpenFiles
+{
+    /// Locks the registry, hold the guard from `join` until `register` so two namespaces
+    /// opened at once can't both take the file.
+    pub(super) fn lock() -> MutexGuard<'static, Self>
+    {
+        static OPEN_FILES: OnceLock<Mutex<OpenFiles>> = OnceLock::new();
+
+        lock(OPEN_FILES.get_or_init(|| Mutex::new(Self(HashMap::default()))))
+    }
+
+    /// The shared parts of `path` if another namespace has it open.
+    ///
+    /// Fails if it was opened with another format or compression, records written both ways
+    /// can't be read back from one file.
+    pub(super) fn join<T: Send + 'static>(
+        &mut self,
+        path: &str,
+        format: SerializationFormat,
+        compression: Option<Compression>,
+    ) -> anyhow::Result<Option<SharedFile<T>>>
+    {
+        let Ok(path) = Path::new(path).canonicalize() else {
+            return Ok(None);
+        };
+
+        // A file open with another value type isn't joined, opening it fails with `Locked`.
+        let Some(open) = self.0.get(&path).and_then(|open| open.downcast_ref::<WeakFile<T>>()) else {
+            return Ok(None);
+        };
+
+        // Every database on the file was dropped since.
+        let Some(shared) = open.upgrade() else {
+            self.0.remove(&path);
+            return Ok(None);
+        };
+
+        if open.format != format || open.compression != compression {
+            anyhow::bail!("{} is already open with another format or compression", path.display());
+        }
+
+        Ok(Some(shared))
+    }
+
+    /// Records that `path` is open so namespaces opened later can join it.
+    pub(super) fn register<T: Send + 'static>(
+        &mut self,
+        path: &str,
+        shared: &SharedFile<T>,
+        format: SerializationFormat,
+        compression: Option<Compression>,
+    ) -> anyhow::Result<()>
+    {
+        let open = WeakFile {
+            writer: Arc::downgrade(&shared.writer),
+            reader: Arc::downgrade(&shared.reader),
+            file_lock: Arc::downgrade(&shared.file_lock),
+            file_records: Arc::downgrade(&shared.file_records),
+            syncs: Arc::downgrade(&shared.syncs),
+            flush_manager: shared.flush_manager.as_ref().map(Arc::downgrade),
+            namespaces: Arc::downgrade(&shared.namespaces),
+            format,
+            compression,
+        };
+
+        self.0.insert(Path::new(path).canonicalize()?, Box::new(open));
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
 use std::hash::Hash;
 
@@ -6,7 +6,11 @@ use chrono::{DateTime, Utc};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+#[cfg(feature = "bloom")]
+use crate::db::bloom::BloomFilter;
 use crate::db::entry::Entry;
+use crate::db::index::Indexes;
+use crate::db::watch::{ChangeKind, InsertSignal};
 use crate::types::HashMap;
 
 #[derive(Debug, Clone)]
@@ -28,6 +32,34 @@ where
     /// insufficient for the key. A unique key (`String`) is used to
     /// break these ties.
     pub(crate) expirations: BTreeSet<(DateTime<Utc>, String)>,
+
+    /// Tracks the order in which keys were first inserted.
+    ///
+    /// Overwriting an existing key keeps its original position, so only new keys
+    /// are appended here. Lookups still go through `entries`.
+    pub(crate) order: InsertionOrder,
+
+    /// Keys sorted lexically, used for range scans.
+    ///
+    /// Like `expirations`, this mirrors `entries` so a range can be found in
+    /// O(log n) instead of sorting every key.
+    pub(crate) sorted_keys: BTreeSet<String>,
+
+    /// Secondary indexes created with `create_index`, updated along with `entries`.
+    pub(crate) indexes: Indexes<T>,
+
+    /// Rules out keys that were never inserted.
+    #[cfg(feature = "bloom")]
+    pub(crate) bloom: BloomFilter,
+
+    /// Only set once an `on_change` callback is registered, nothing is recorded before that.
+    pub(crate) record_changes: bool,
+
+    /// Changes not yet passed to the `on_change` callbacks.
+    pub(crate) changes: Vec<(String, ChangeKind)>,
+
+    /// Notified on every insert, for `get_or_wait`.
+    pub(crate) inserted: InsertSignal,
 }
 
 impl<T> State<T>
@@ -35,10 +67,312 @@ where
     T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone,
 {
     pub(crate) fn new() -> Self
+    {
+        Self::with_capacity(0)
+    }
+
+    /// A state with room for `capacity` keys before it has to grow.
+    pub(crate) fn with_capacity(capacity: usize) -> Self
     {
         Self {
-            entries: HashMap::default(),
+            entries: HashMap::with_capacity_and_hasher(capacity, Default::default()),
             expirations: BTreeSet::new(),
+            order: InsertionOrder::with_capacity(capacity),
+            sorted_keys: BTreeSet::new(),
+            indexes: Indexes::default(),
+            #[cfg(feature = "bloom")]
+            bloom: BloomFilter::default(),
+            record_changes: false,
+            changes: Vec::new(),
+            inserted: InsertSignal::default(),
+        }
+    }
+
+    /// Inserts an entry while keeping `expirations`, `order`, `sorted_keys` and `indexes` in sync.
+    ///
+    /// An overwritten key keeps its original `created_at`.
+    ///
+    /// Returns the entry previously stored under the same key, if any.
+    pub(crate) fn insert(&mut self, mut entry: Entry<T>) -> Option<Entry<T>>
+    {
+        let key = entry.key.clone();
+        let expires_at = entry.expires_at;
+
+        if let Some(existing) = self.entries.get(&key) {
+            entry.created_at = existing.created_at;
+        }
+
+        let previous = self.entries.insert(key.clone(), entry);
+
+        match &previous {
+            Some(old) => {
+                if let Some(old_expires_at) = old.expires_at {
+                    self.expirations.remove(&(old_expires_at, key.clone()));
+                }
+
+                self.indexes.remove(&key, &old.data);
+            }
+            None => {
+                self.order.push(key.clone());
+                self.sorted_keys.insert(key.clone());
+
+                #[cfg(feature = "bloom")]
+                self.insert_bloom(&key);
+            }
+        }
+
+        // After the old value is removed, which may have been in the same bucket.
+        if let Some(entry) = self.entries.get(&key) {
+            self.indexes.insert(&key, &entry.data);
+        }
+
+        let kind = if previous.is_some() {
+            ChangeKind::Updated
+        } else {
+            ChangeKind::Set
+        };
+        self.record(&key, kind);
+
+        if let Some(expires_at) = expires_at {
+            self.expirations.insert((expires_at, key));
+        }
+
+        self.inserted.notify();
+
+        previous
+    }
+
+    /// Changes when `key` expires, keeping `expirations` in sync.
+    ///
+    /// Returns `false` if the key isn't in the state.
+    pub(crate) fn set_expiry(&mut self, key: &str, expires_at: Option<DateTime<Utc>>) -> bool
+    {
+        let Some(entry) = self.entries.get_mut(key) else {
+            return false;
+        };
+
+        if let Some(old_expires_at) = entry.expires_at {
+            self.expirations.remove(&(old_expires_at, key.to_string()));
+        }
+
+        entry.expires_at = expires_at;
+
+        if let Some(expires_at) = expires_at {
+            self.expirations.insert((expires_at, key.to_string()));
+        }
+
+        true
+    }
+
+    /// Makes room for at least `additional` more keys.
+    pub(crate) fn reserve(&mut self, additional: usize)
+    {
+        self.entries.reserve(additional);
+        self.order.reserve(additional);
+    }
+
+    /// Removes an entry along with its expiration, insertion order, sorted key and index records.
+    pub(crate) fn remove(&mut self, key: &str) -> Option<Entry<T>>
+    {
+        self.remove_as(key, ChangeKind::Deleted)
+    }
+
+    /// `remove`, recording the change as `kind`.
+    fn remove_as(&mut self, key: &str, kind: ChangeKind) -> Option<Entry<T>>
+    {
+        let entry = self.entries.remove(key)?;
+
+        if let Some(expires_at) = entry.expires_at {
+            self.expirations.remove(&(expires_at, key.to_string()));
+        }
+
+        self.order.remove(key);
+        self.sorted_keys.remove(key);
+        self.indexes.remove(key, &entry.data);
+        self.record(key, kind);
+
+        Some(entry)
+    }
+
+    /// Removes `key` if its ttl has already passed.
+    ///
+    /// Returns `true` if the entry was expired and removed.
+    pub(crate) fn remove_if_expired(&mut self, key: &str) -> bool
+    {
+        if self.entries.get(key).is_some_and(|entry| entry.is_expired()) {
+            self.remove_as(key, ChangeKind::Expired);
+            return true;
+        }
+
+        false
+    }
+
+    /// Removes every entry whose expiration is at or before `now`.
+    ///
+    /// Returns the keys that were removed.
+    pub(crate) fn remove_expired(&mut self, now: DateTime<Utc>) -> Vec<String>
+    {
+        let mut expired = Vec::new();
+
+        while self.expirations.first().is_some_and(|(expires_at, _)| *expires_at <= now) {
+            if let Some((expires_at, key)) = self.expirations.pop_first() {
+                // Only remove the entry if this expiration still belongs to it.
+                if self
+                    .entries
+                    .get(&key)
+                    .is_some_and(|entry| entry.expires_at == Some(expires_at))
+                {
+                    self.remove_as(&key, ChangeKind::Expired);
+                    expired.push(key);
+                }
+            }
+        }
+
+        expired
+    }
+
+    /// Removes every entry from the state.
+    pub(crate) fn clear(&mut self)
+    {
+        if self.record_changes {
+            let removed = self.order.iter().map(|key| (key.clone(), ChangeKind::Deleted));
+            self.changes.extend(removed);
+        }
+
+        self.entries.clear();
+        self.expirations.clear();
+        self.order.clear();
+        self.sorted_keys.clear();
+        self.indexes.clear();
+
+        #[cfg(feature = "bloom")]
+        {
+            self.bloom = BloomFilter::default();
         }
     }
+
+    /// Queues a change for the `on_change` callbacks, if any are registered.
+    fn record(&mut self, key: &str, kind: ChangeKind)
+    {
+        if self.record_changes {
+            self.changes.push((key.to_string(), kind));
+        }
+    }
+
+    /// Returns `false` if `key` is definitely not in the state.
+    #[cfg(feature = "bloom")]
+    pub(crate) fn may_contain(&self, key: &str) -> bool
+    {
+        self.bloom.may_contain(key)
+    }
+
+    /// Without the `bloom` feature every key might be present.
+    #[cfg(not(feature = "bloom"))]
+    pub(crate) fn may_contain(&self, _key: &str) -> bool
+    {
+        true
+    }
+
+    /// Adds `key` to the Bloom filter, rebuilding it at twice the size once it fills up.
+    #[cfg(feature = "bloom")]
+    fn insert_bloom(&mut self, key: &str)
+    {
+        if self.bloom.is_full() {
+            let mut bloom = BloomFilter::with_capacity(self.bloom.capacity() * 2);
+
+            // `key` is already in `sorted_keys`, so it is covered here.
+            for existing in &self.sorted_keys {
+                bloom.insert(existing);
+            }
+
+            self.bloom = bloom;
+            return;
+        }
+
+        self.bloom.insert(key);
+    }
+}
+
+/// The order keys were first inserted in, see `State::order`.
+///
+/// Like `AccessOrder`, every key gets a sequence number so a key can be removed in O(log n)
+/// instead of searching a list for it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InsertionOrder
+{
+    next: u64,
+    sequences: HashMap<String, u64>,
+    by_sequence: BTreeMap<u64, String>,
+}
+
+impl InsertionOrder
+{
+    fn with_capacity(capacity: usize) -> Self
+    {
+        Self {
+            sequences: HashMap::with_capacity_and_hasher(capacity, Default::default()),
+            ..Default::default()
+        }
+    }
+
+    /// Appends `key`, which must not be tracked already.
+    pub(crate) fn push(&mut self, key: String)
+    {
+        self.sequences.insert(key.clone(), self.next);
+        self.by_sequence.insert(self.next, key);
+        self.next += 1;
+    }
+
+    pub(crate) fn remove(&mut self, key: &str)
+    {
+        if let Some(sequence) = self.sequences.remove(key) {
+            self.by_sequence.remove(&sequence);
+        }
+    }
+
+    /// The key inserted first.
+    pub(crate) fn first(&self) -> Option<&String>
+    {
+        self.by_sequence.values().next()
+    }
+
+    /// Every key, oldest first.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &String>
+    {
+        self.by_sequence.values()
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize)
+    {
+        self.sequences.reserve(additional);
+    }
+
+    pub(crate) fn clear(&mut self)
+    {
+        self.sequences.clear();
+        self.by_sequence.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_insertion_order_remove()
+    {
+        let mut order = InsertionOrder::default();
+
+        for key in ["a", "b", "c", "d"] {
+            order.push(key.to_string());
+        }
+
+        order.remove("b");
+        order.remove("missing");
+        order.push("b".to_string());
+
+        assert_eq!(order.first(), Some(&"a".to_string()));
+        assert_eq!(order.iter().cloned().collect::<Vec<_>>(), vec!["a", "c", "d", "b"]);
+    }
 }
@@ -0,0 +1,81 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::db::state::State;
+use crate::db::watch::Listeners;
+use crate::db::TTLSignal;
+use crate::error::write_lock;
+
+/// How often the background task checks for expired entries when no interval is configured.
+pub(crate) const DEFAULT_TTL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Handle to the background task that removes expired entries.
+///
+/// The handle is shared between clones of a `Database`, once the last one is dropped
+/// the task is told to exit.
+#[derive(Debug)]
+pub(crate) struct TTLManager
+{
+    sender: Sender<TTLSignal>,
+}
+
+impl TTLManager
+{
+    /// Spawns the background task, it wakes up every `interval` or whenever a
+    /// `TTLSignal::Check` is received. Removed keys are passed to `listeners` as expired.
+    pub(crate) fn spawn<T>(state: Arc<RwLock<State<T>>>, listeners: Listeners, interval: Duration) -> anyhow::Result<Self>
+    where
+        T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<TTLSignal>();
+
+        thread::Builder::new().name("quick-kv-ttl".to_string()).spawn(move || loop {
+            match receiver.recv_timeout(interval) {
+                Ok(TTLSignal::Check) | Err(RecvTimeoutError::Timeout) => {
+                    let expired = write_lock(&state).remove_expired(Utc::now());
+
+                    if !expired.is_empty() {
+                        log::debug!("[TTL] Removed {} expired keys", expired.len());
+                        listeners.notify(&state);
+                    }
+                }
+                Ok(TTLSignal::Exit) | Err(RecvTimeoutError::Disconnected) => {
+                    log::debug!("[TTL] Background task exiting");
+                    break;
+                }
+            }
+        })?;
+
+        Ok(Self { sender })
+    }
+
+    /// Asks the background task to check for expired entries right away.
+    #[allow(dead_code)]
+    pub(crate) fn check(&self)
+    {
+        // The task only stops receiving once it has exited, so there is nothing to do on error.
+        let _ = self.sender.send(TTLSignal::Check);
+    }
+
+    /// Tells the background task to exit, sending it again later is harmless.
+    pub(crate) fn stop(&self)
+    {
+        let _ = self.sender.send(TTLSignal::Exit);
+    }
+}
+
+impl Drop for TTLManager
+{
+    fn drop(&mut self)
+    {
+        self.stop();
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::db::state::State;
+use crate::error::{lock, read_lock, write_lock};
+
+/// What happened to a key, passed to the callbacks registered with `on_change`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind
+{
+    /// A new key was stored.
+    Set,
+    /// An existing key was overwritten.
+    Updated,
+    /// The key was deleted, renamed away, purged or evicted.
+    Deleted,
+    /// The key's ttl ran out and it was removed.
+    Expired,
+}
+
+/// A callback registered with `on_change`.
+pub type ChangeListener = Arc<dyn Fn(&str, ChangeKind) + Send + Sync>;
+
+/// The callbacks registered on a database, shared between its clones.
+#[derive(Clone, Default)]
+pub(crate) struct Listeners
+{
+    callbacks: Arc<Mutex<Vec<ChangeListener>>>,
+}
+
+impl Debug for Listeners
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.debug_struct("Listeners")
+            .field("callbacks", &lock(&self.callbacks).len())
+            .finish()
+    }
+}
+
+impl Listeners
+{
+    /// Registers `f` and starts recording changes to `state` for it.
+    pub(crate) fn add<T>(&self, state: &RwLock<State<T>>, f: ChangeListener)
+    where
+        T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone,
+    {
+        lock(&self.callbacks).push(f);
+        write_lock(state).record_changes = true;
+    }
+
+    /// Takes the changes recorded in `state` and passes each of them to every callback.
+    ///
+    /// Must be called without holding the state lock, callbacks are free to use the database.
+    pub(crate) fn notify<T>(&self, state: &RwLock<State<T>>)
+    where
+        T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone,
+    {
+        // Most calls change nothing, a shared lock keeps those from queueing behind each other.
+        if read_lock(state).changes.is_empty() {
+            return;
+        }
+
+        let changes = std::mem::take(&mut write_lock(state).changes);
+        let callbacks = lock(&self.callbacks).clone();
+
+        for (key, kind) in &changes {
+            for callback in &callbacks {
+                callback(key, *kind);
+            }
+        }
+    }
+}
+
+/// Wakes up `get_or_wait` callers whenever a key is stored.
+///
+/// The state sits behind an `RwLock`, which a `Condvar` can't wait on, so inserts are counted
+/// under a mutex of their own. A waiter remembers the count before it looks for its key and
+/// only sleeps while it hasn't changed, so an insert in between is never missed.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InsertSignal
+{
+    inserts: Arc<(Mutex<u64>, Condvar)>,
+}
+
+impl InsertSignal
+{
+    pub(crate) fn notify(&self)
+    {
+        let (count, condvar) = &*self.inserts;
+
+        *lock(count) += 1;
+        condvar.notify_all();
+    }
+
+    /// How many inserts have happened so far.
+    pub(crate) fn count(&self) -> u64
+    {
+        *lock(&self.inserts.0)
+    }
+
+    /// Blocks until the count moves past `seen` or `timeout` passes.
+    ///
+    /// Returns `false` if it timed out.
+    pub(crate) fn wait_past(&self, seen: u64, timeout: Duration) -> bool
+    {
+        let (count, condvar) = &*self.inserts;
+
+        let (_count, result) = condvar
+            .wait_timeout_while(lock(count), timeout, |count| *count == seen)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        !result.timed_out()
+    }
+}
+
+/// Calls `Listeners::notify` when dropped.
+///
+/// Declared before the state guard in a function, it is dropped after it, so callbacks run
+/// once the lock is released no matter which path the function returns through.
+pub(crate) struct NotifyOnDrop<T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone,
+{
+    pub(crate) state: Arc<RwLock<State<T>>>,
+    pub(crate) listeners: Listeners,
+}
+
+impl<T> Drop for NotifyOnDrop<T>
+where
+    T: Serialize + DeserializeOwned + Debug + Eq + PartialEq + Hash + Send + Sync + Clone,
+{
+    fn drop(&mut self)
+    {
+        self.listeners.notify(&self.state);
+    }
+}
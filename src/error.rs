@@ -0,0 +1,179 @@
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Errors returned by the database.
+#[derive(Debug)]
+pub enum QuickKvError
+{
+    /// The database path can't be used, for example because it points to a directory.
+    InvalidPath
+    {
+        reason: String
+    },
+    /// The database file doesn't start with a header this version can read, it may belong
+    /// to something else, be written in another format or by a newer version.
+    BadFormat
+    {
+        reason: String
+    },
+    /// A record in the database file could not be decrypted, either the encryption key is
+    /// wrong or the file was modified.
+    Decryption,
+    /// A time-to-live of zero was given. Entries must live for at least some amount of time.
+    InvalidTtl,
+    /// The key is longer than `max_key_len` or was rejected by `key_validator`.
+    InvalidKey
+    {
+        reason: String
+    },
+    /// Reading or writing the database file failed.
+    Io(std::io::Error),
+    /// An entry could not be encoded or decoded with bincode.
+    Serialize(bincode::Error),
+    /// An entry could not be encoded or decoded as JSON.
+    Json(serde_json::Error),
+    /// The key does not exist.
+    KeyNotFound(String),
+    /// There is no index with this name, or it was created with a different key type.
+    IndexNotFound(String),
+    /// The database was opened read-only, so it can't be written to.
+    ReadOnly,
+    /// Another client already has the database file open for writing.
+    Locked
+    {
+        path: String
+    },
+    /// Inserting a new key would take the database past its `max_entries` limit.
+    CapacityExceeded
+    {
+        max_entries: usize
+    },
+    /// A thread panicked while holding one of the database locks.
+    ///
+    /// Locks are recovered automatically, this is kept for callers that match on it.
+    Poisoned,
+    /// Any other failure, such as an overflowing `increment`.
+    Other(anyhow::Error),
+}
+
+impl fmt::Display for QuickKvError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            QuickKvError::InvalidPath { reason } => write!(f, "Invalid database path: {}", reason),
+            QuickKvError::BadFormat { reason } => write!(f, "Bad database file: {}", reason),
+            QuickKvError::Decryption => write!(f, "Failed to decrypt the database file, is the key right?"),
+            QuickKvError::InvalidTtl => write!(f, "Invalid ttl: a ttl of zero would expire immediately"),
+            QuickKvError::InvalidKey { reason } => write!(f, "Invalid key: {}", reason),
+            QuickKvError::Io(e) => write!(f, "I/O error: {}", e),
+            QuickKvError::Serialize(e) => write!(f, "Serialization error: {}", e),
+            QuickKvError::Json(e) => write!(f, "JSON error: {}", e),
+            QuickKvError::KeyNotFound(key) => write!(f, "Key not found: {}", key),
+            QuickKvError::IndexNotFound(name) => write!(f, "Index not found: {}", name),
+            QuickKvError::ReadOnly => write!(f, "The database was opened read-only"),
+            QuickKvError::Locked { path } => write!(f, "The database at {} is already open for writing", path),
+            QuickKvError::CapacityExceeded { max_entries } => {
+                write!(f, "Capacity exceeded: the database is limited to {} entries", max_entries)
+            }
+            QuickKvError::Poisoned => write!(f, "A database lock was poisoned by a panicking thread"),
+            QuickKvError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for QuickKvError
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)>
+    {
+        match self {
+            QuickKvError::Io(e) => Some(e),
+            QuickKvError::Serialize(e) => Some(e),
+            QuickKvError::Json(e) => Some(e),
+            QuickKvError::Other(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for QuickKvError
+{
+    fn from(e: std::io::Error) -> Self
+    {
+        QuickKvError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for QuickKvError
+{
+    fn from(e: bincode::Error) -> Self
+    {
+        QuickKvError::Serialize(e)
+    }
+}
+
+impl From<serde_json::Error> for QuickKvError
+{
+    fn from(e: serde_json::Error) -> Self
+    {
+        QuickKvError::Json(e)
+    }
+}
+
+/// The database internals use `anyhow`, this recovers the typed error where there is one.
+impl From<anyhow::Error> for QuickKvError
+{
+    fn from(e: anyhow::Error) -> Self
+    {
+        let e = match e.downcast::<QuickKvError>() {
+            Ok(e) => return e,
+            Err(e) => e,
+        };
+
+        let e = match e.downcast::<std::io::Error>() {
+            Ok(e) => return QuickKvError::Io(e),
+            Err(e) => e,
+        };
+
+        let e = match e.downcast::<bincode::Error>() {
+            Ok(e) => return QuickKvError::Serialize(e),
+            Err(e) => e,
+        };
+
+        match e.downcast::<serde_json::Error>() {
+            Ok(e) => QuickKvError::Json(e),
+            Err(e) => QuickKvError::Other(e),
+        }
+    }
+}
+
+/// Locks `mutex`, recovering the guard if another thread panicked while holding it.
+///
+/// Every write to the state finishes before the lock is released, so a panic can at worst
+/// leave one operation half done. Refusing every later operation would be worse for a
+/// long-running server.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T>
+{
+    mutex.lock().unwrap_or_else(|poisoned| {
+        log::warn!("[LOCK] Recovering a lock poisoned by a panicking thread");
+        poisoned.into_inner()
+    })
+}
+
+/// Takes a shared lock on `rwlock`, recovering it if poisoned, see `lock`.
+pub(crate) fn read_lock<T>(rwlock: &RwLock<T>) -> RwLockReadGuard<'_, T>
+{
+    rwlock.read().unwrap_or_else(|poisoned| {
+        log::warn!("[LOCK] Recovering a lock poisoned by a panicking thread");
+        poisoned.into_inner()
+    })
+}
+
+/// Takes an exclusive lock on `rwlock`, recovering it if poisoned, see `lock`.
+pub(crate) fn write_lock<T>(rwlock: &RwLock<T>) -> RwLockWriteGuard<'_, T>
+{
+    rwlock.write().unwrap_or_else(|poisoned| {
+        log::warn!("[LOCK] Recovering a lock poisoned by a panicking thread");
+        poisoned.into_inner()
+    })
+}
@@ -0,0 +1,293 @@
+//! A small HTTP/JSON server front-end.
+//!
+//! Values are stored as JSON documents and addressed by key:
+//!
+//! - `GET /kv/:key` returns the value, or `404` if the key does not exist.
+//! - `PUT /kv/:key` stores the JSON request body, returning `400` if it is not valid JSON.
+//! - `DELETE /kv/:key` removes the key, or returns `404` if it does not exist.
+//!
+//! Keys are percent-decoded, so `/kv/a%2Fb` addresses the key `a/b`. A query string is ignored.
+
+use std::io::{Cursor, Read};
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use serde_json::Value;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::clients::{BaseClient, SharedClient};
+use crate::error::lock;
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+/// The largest request body `PUT` accepts, larger ones are refused with `413`.
+pub const MAX_BODY_LEN: u64 = 16 * 1024 * 1024;
+
+/// Serves a `SharedClient<serde_json::Value>` over HTTP.
+pub struct HttpServer
+{
+    server: Server,
+    client: SharedClient<Value>,
+}
+
+impl HttpServer
+{
+    /// Binds the server to `addr`, use port `0` to let the OS pick one.
+    pub fn bind<A: ToSocketAddrs>(addr: A, client: SharedClient<Value>) -> anyhow::Result<Self>
+    {
+        let server = Server::http(addr).map_err(|e| anyhow::anyhow!(e))?;
+
+        Ok(Self { server, client })
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> anyhow::Result<SocketAddr>
+    {
+        self.server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| anyhow::anyhow!("Server is not listening on an ip address"))
+    }
+
+    /// Handles requests forever.
+    pub fn run(self) -> anyhow::Result<()>
+    {
+        for mut request in self.server.incoming_requests() {
+            let response = handle_request(&self.client, &mut request);
+
+            if let Err(e) = request.respond(response) {
+                log::error!("[HTTP] Failed to send response: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn handle_request(client: &SharedClient<Value>, request: &mut Request) -> HttpResponse
+{
+    let path = request.url().split(['?', '#']).next().unwrap_or_default();
+
+    let key = match path.strip_prefix("/kv/") {
+        Some(key) if !key.is_empty() => match percent_decode(key) {
+            Some(key) => key,
+            None => return json_response(400, &error_body("invalid percent-encoding in key")),
+        },
+        _ => return json_response(404, &error_body("not found")),
+    };
+
+    let result = match request.method() {
+        Method::Get => get(client, &key),
+        Method::Put => {
+            let mut body = String::new();
+
+            // One byte past the limit is enough to tell the body is too large.
+            if let Err(e) = request.as_reader().take(MAX_BODY_LEN + 1).read_to_string(&mut body) {
+                return json_response(400, &error_body(&e.to_string()));
+            }
+
+            if body.len() as u64 > MAX_BODY_LEN {
+                return json_response(413, &error_body("request body too large"));
+            }
+
+            match serde_json::from_str::<Value>(&body) {
+                Ok(value) => put(client, &key, value),
+                Err(e) => return json_response(400, &error_body(&e.to_string())),
+            }
+        }
+        Method::Delete => delete(client, &key),
+        _ => return json_response(405, &error_body("method not allowed")),
+    };
+
+    result.unwrap_or_else(|e| json_response(500, &error_body(&e.to_string())))
+}
+
+fn get(client: &SharedClient<Value>, key: &str) -> anyhow::Result<HttpResponse>
+{
+    match lock(client).get(key)? {
+        Some(value) => Ok(json_response(200, &value)),
+        None => Ok(json_response(404, &error_body("key not found"))),
+    }
+}
+
+fn put(client: &SharedClient<Value>, key: &str, value: Value) -> anyhow::Result<HttpResponse>
+{
+    lock(client).set(key, value)?;
+
+    Ok(json_response(204, &Value::Null))
+}
+
+fn delete(client: &SharedClient<Value>, key: &str) -> anyhow::Result<HttpResponse>
+{
+    let mut client = lock(client);
+
+    if !client.exists(key)? {
+        return Ok(json_response(404, &error_body("key not found")));
+    }
+
+    client.delete(key)?;
+
+    Ok(json_response(204, &Value::Null))
+}
+
+/// Decodes the `%XX` escapes in a path segment, `None` if one is malformed or the result isn't
+/// valid UTF-8.
+fn percent_decode(segment: &str) -> Option<String>
+{
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            // `from_str_radix` would also take a sign, so the digits are checked first.
+            let hex = tail.get(..2).filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+fn error_body(message: &str) -> Value
+{
+    serde_json::json!({ "error": message })
+}
+
+fn json_response(status: u16, body: &Value) -> HttpResponse
+{
+    // 204 responses must not carry a body.
+    let body = if status == 204 { String::new() } else { body.to_string() };
+
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::clients::normal::QuickClient;
+    use crate::clients::ClientConfig;
+
+    /// Sends a raw HTTP/1.1 request and returns the status code and body.
+    fn send(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String)
+    {
+        let mut stream = TcpStream::connect(addr).unwrap();
+
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let status = response.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+
+        (status, body)
+    }
+
+    #[test]
+    fn test_http_server_requests()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            ..Default::default()
+        };
+        let client = Arc::new(Mutex::new(QuickClient::<Value>::new(config)));
+
+        let server = HttpServer::bind("127.0.0.1:0", client).unwrap();
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.run());
+
+        assert_eq!(send(addr, "GET", "/kv/user", "").0, 404);
+        assert_eq!(send(addr, "PUT", "/kv/user", r#"{"name":"alice"}"#).0, 204);
+
+        let (status, body) = send(addr, "GET", "/kv/user", "");
+        assert_eq!(status, 200);
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            serde_json::json!({ "name": "alice" })
+        );
+
+        assert_eq!(send(addr, "PUT", "/kv/user", "not json").0, 400);
+        assert_eq!(send(addr, "DELETE", "/kv/user", "").0, 204);
+        assert_eq!(send(addr, "DELETE", "/kv/user", "").0, 404);
+        assert_eq!(send(addr, "GET", "/kv/user", "").0, 404);
+    }
+
+    #[test]
+    fn test_http_server_decodes_keys()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            ..Default::default()
+        };
+        let client = Arc::new(Mutex::new(QuickClient::<Value>::new(config)));
+
+        let server = HttpServer::bind("127.0.0.1:0", client.clone()).unwrap();
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.run());
+
+        assert_eq!(send(addr, "PUT", "/kv/a%2Fb", "1").0, 204);
+        assert_eq!(send(addr, "PUT", "/kv/x?y=1", "2").0, 204);
+        assert_eq!(send(addr, "PUT", "/kv/caf%C3%A9", "3").0, 204);
+
+        {
+            let mut client = lock(&client);
+            assert_eq!(client.get("a/b").unwrap(), Some(serde_json::json!(1)));
+            assert_eq!(client.get("x").unwrap(), Some(serde_json::json!(2)));
+            assert_eq!(client.get("café").unwrap(), Some(serde_json::json!(3)));
+            assert_eq!(client.get("a%2Fb").unwrap(), None);
+        }
+
+        assert_eq!(send(addr, "GET", "/kv/x?y=2", "").1, "2");
+        assert_eq!(send(addr, "DELETE", "/kv/a%2fb", "").0, 204);
+
+        assert_eq!(send(addr, "GET", "/kv/%zz", "").0, 400);
+        assert_eq!(send(addr, "GET", "/kv/a%2", "").0, 400);
+        assert_eq!(send(addr, "GET", "/kv/%FF", "").0, 400);
+    }
+
+    #[test]
+    fn test_percent_decode()
+    {
+        assert_eq!(percent_decode("plain").as_deref(), Some("plain"));
+        assert_eq!(percent_decode("a%20b%2F").as_deref(), Some("a b/"));
+        assert_eq!(percent_decode("%"), None);
+        assert_eq!(percent_decode("%g0"), None);
+        assert_eq!(percent_decode("%+1"), None);
+    }
+}
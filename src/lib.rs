@@ -112,8 +112,13 @@
 #![allow(ambiguous_glob_reexports)]
 
 pub mod clients;
+#[cfg(feature = "server")]
+pub mod http;
 pub mod prelude;
+#[cfg(feature = "resp")]
+pub mod resp;
 
 mod db;
+mod error;
 mod types;
 mod utils;
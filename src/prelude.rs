@@ -1,8 +1,29 @@
 pub use anyhow::Result;
+pub use chrono::{DateTime, Utc};
 pub use log::LevelFilter;
 // Re-exported from other crates
 pub use serde::*;
 
+#[cfg(feature = "async")]
+pub use crate::clients::asynchronous::AsyncQuickClient;
 pub use crate::clients::memory::QuickMemoryClient;
+pub use crate::clients::namespaced::NamespacedClient;
 pub use crate::clients::normal::QuickClient;
-pub use crate::clients::{BaseClient, ClientConfig};
+pub use crate::clients::{BaseClient, ClientConfig, ListClient, NumericClient, SharedClient};
+pub use crate::db::batcher::Batch;
+pub use crate::db::codec::{Compression, SerializationFormat};
+pub use crate::db::encryption::EncryptionConfig;
+pub use crate::db::eviction::EvictionPolicy;
+pub use crate::db::logging::LogFormat;
+#[cfg(feature = "metrics")]
+pub use crate::db::metrics::OpTimings;
+pub use crate::db::metrics::{DbStats, LatencyStats};
+pub use crate::db::pipeline::{Pipeline, PipelineResult};
+pub use crate::db::recovery::{CorruptionPolicy, LoadReport};
+pub use crate::db::watch::{ChangeKind, ChangeListener};
+pub use crate::error::QuickKvError;
+#[cfg(feature = "server")]
+pub use crate::http::HttpServer;
+#[cfg(feature = "resp")]
+pub use crate::resp::RespServer;
+pub use crate::types::{AsKey, EntryMeta, KeyStatus};
@@ -0,0 +1,335 @@
+//! A minimal server speaking the Redis serialization protocol (RESP).
+//!
+//! Only `PING`, `GET`, `SET`, `DEL`, `EXISTS` and `KEYS` are supported, which is enough for
+//! existing Redis clients to use quick-kv as a simple string store.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+
+use crate::clients::{BaseClient, SharedClient};
+use crate::error::{lock, QuickKvError};
+
+/// The most arguments a single command may carry, the same limit Redis uses.
+const MAX_ARGS: usize = 1024 * 1024;
+
+/// The largest bulk string a peer may send, the same limit Redis uses.
+const MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+
+/// Serves a `SharedClient<String>` over TCP using RESP.
+pub struct RespServer
+{
+    listener: TcpListener,
+    client: SharedClient<String>,
+}
+
+impl RespServer
+{
+    /// Binds the server to `addr`, use port `0` to let the OS pick one.
+    pub fn bind<A: ToSocketAddrs>(addr: A, client: SharedClient<String>) -> anyhow::Result<Self>
+    {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            client,
+        })
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> anyhow::Result<SocketAddr>
+    {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Accepts connections forever, each connection is served on its own thread.
+    pub fn run(self) -> anyhow::Result<()>
+    {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let client = self.client.clone();
+
+            thread::spawn(move || {
+                if let Err(e) = handle_connection(stream, client) {
+                    log::error!("[RESP] Connection closed: {}", e);
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn handle_connection(stream: TcpStream, client: SharedClient<String>) -> anyhow::Result<()>
+{
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    while let Some(args) = read_command(&mut reader)? {
+        if args.is_empty() {
+            continue;
+        }
+
+        let reply = execute(&client, &args);
+
+        writer.write_all(reply.as_bytes())?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Reads one command, either a RESP array of bulk strings or an inline command.
+///
+/// Returns `None` once the peer closes the connection.
+fn read_command<R: BufRead>(reader: &mut R) -> anyhow::Result<Option<Vec<String>>>
+{
+    let line = match read_line(reader)? {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+
+    let count = match line.strip_prefix('*') {
+        Some(count) => count.parse::<usize>()?,
+        // Inline commands are what you get when typing into telnet or nc.
+        None => return Ok(Some(line.split_whitespace().map(String::from).collect())),
+    };
+
+    // Lengths come from the peer, so check them before allocating anything.
+    if count > MAX_ARGS {
+        anyhow::bail!("Too many arguments: {} (max {})", count, MAX_ARGS);
+    }
+
+    let mut args = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let header = read_line(reader)?.ok_or_else(|| anyhow::anyhow!("Unexpected end of stream"))?;
+
+        let len = header
+            .strip_prefix('$')
+            .ok_or_else(|| anyhow::anyhow!("Expected a bulk string, got \"{}\"", header))?
+            .parse::<usize>()?;
+
+        if len > MAX_BULK_LEN {
+            anyhow::bail!("Bulk string too long: {} bytes (max {})", len, MAX_BULK_LEN);
+        }
+
+        // The payload is followed by a trailing CRLF.
+        let mut payload = vec![0; len.checked_add(2).ok_or_else(|| anyhow::anyhow!("Bulk length overflows"))?];
+        reader.read_exact(&mut payload)?;
+        payload.truncate(len);
+
+        args.push(String::from_utf8(payload)?);
+    }
+
+    Ok(Some(args))
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> anyhow::Result<Option<String>>
+{
+    let mut line = String::new();
+
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn execute(client: &SharedClient<String>, args: &[String]) -> String
+{
+    let mut client = lock(client);
+
+    let result = match (args[0].to_uppercase().as_str(), &args[1..]) {
+        ("PING", []) => Ok(simple("PONG")),
+        ("GET", [key]) => client.get(key).map(|value| bulk(value.as_deref())),
+        ("SET", [key, value]) => client.set(key, value.clone()).map(|_| simple("OK")),
+        ("DEL", keys) if !keys.is_empty() => count_existing(&mut *client, keys, true),
+        ("EXISTS", keys) if !keys.is_empty() => count_existing(&mut *client, keys, false),
+        ("KEYS", [pattern]) => client.keys().map(|keys| {
+            let keys: Vec<String> = keys
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|key| matches_pattern(pattern, key))
+                .collect();
+
+            array(&keys)
+        }),
+        (command, _) => return error(&format!("unknown command or wrong number of arguments for '{}'", command)),
+    };
+
+    result.unwrap_or_else(|e| error(&e.to_string()))
+}
+
+/// Counts how many of `keys` exist, deleting them along the way if `delete` is set.
+fn count_existing<C: BaseClient<String>>(client: &mut C, keys: &[String], delete: bool) -> Result<String, QuickKvError>
+{
+    let mut count = 0;
+
+    for key in keys {
+        if client.exists(key)? {
+            count += 1;
+
+            if delete {
+                client.delete(key)?;
+            }
+        }
+    }
+
+    Ok(integer(count))
+}
+
+/// Supports the `*` wildcard, which covers the common `KEYS *` and `KEYS prefix*` patterns.
+fn matches_pattern(pattern: &str, key: &str) -> bool
+{
+    if !pattern.contains('*') {
+        return pattern == key;
+    }
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+
+    let mut rest = match key.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let parts: Vec<&str> = parts.collect();
+
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+fn simple(value: &str) -> String
+{
+    format!("+{}\r\n", value)
+}
+
+fn error(message: &str) -> String
+{
+    format!("-ERR {}\r\n", message)
+}
+
+fn integer(value: usize) -> String
+{
+    format!(":{}\r\n", value)
+}
+
+fn bulk(value: Option<&str>) -> String
+{
+    match value {
+        Some(value) => format!("${}\r\n{}\r\n", value.len(), value),
+        None => "$-1\r\n".to_string(),
+    }
+}
+
+fn array(values: &[String]) -> String
+{
+    let mut reply = format!("*{}\r\n", values.len());
+
+    for value in values {
+        reply.push_str(&bulk(Some(value)));
+    }
+
+    reply
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::sync::{Arc, Mutex};
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::clients::normal::QuickClient;
+    use crate::clients::ClientConfig;
+
+    fn send(stream: &mut TcpStream, reader: &mut BufReader<TcpStream>, args: &[&str]) -> String
+    {
+        let mut request = format!("*{}\r\n", args.len());
+
+        for arg in args {
+            request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+        }
+
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut reply = String::new();
+        reader.read_line(&mut reply).unwrap();
+
+        // Bulk strings carry their payload on a second line.
+        if reply.starts_with('$') && reply != "$-1\r\n" {
+            reader.read_line(&mut reply).unwrap();
+        }
+
+        reply
+    }
+
+    #[test]
+    fn test_resp_server_set_get()
+    {
+        let tmp_dir = tempdir().expect("Failed to create tempdir");
+        let tmp_file = tmp_dir.path().join("test.qkv").to_str().unwrap().to_string();
+
+        let config = ClientConfig {
+            path: Some(tmp_file),
+            log: None,
+            log_level: None,
+            ..Default::default()
+        };
+        let client = Arc::new(Mutex::new(QuickClient::<String>::new(config)));
+
+        let server = RespServer::bind("127.0.0.1:0", client).unwrap();
+        let addr = server.local_addr().unwrap();
+
+        thread::spawn(move || server.run());
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        assert_eq!(send(&mut stream, &mut reader, &["PING"]), "+PONG\r\n");
+        assert_eq!(send(&mut stream, &mut reader, &["SET", "greeting", "hello"]), "+OK\r\n");
+        assert_eq!(send(&mut stream, &mut reader, &["GET", "greeting"]), "$5\r\nhello\r\n");
+        assert_eq!(send(&mut stream, &mut reader, &["EXISTS", "greeting", "missing"]), ":1\r\n");
+        assert_eq!(send(&mut stream, &mut reader, &["DEL", "greeting"]), ":1\r\n");
+        assert_eq!(send(&mut stream, &mut reader, &["GET", "greeting"]), "$-1\r\n");
+    }
+
+    #[test]
+    fn test_resp_rejects_oversized_lengths()
+    {
+        let too_many = format!("*{}\r\n", MAX_ARGS + 1);
+        assert!(read_command(&mut too_many.as_bytes()).is_err());
+
+        let too_long = format!("*1\r\n${}\r\n", MAX_BULK_LEN + 1);
+        assert!(read_command(&mut too_long.as_bytes()).is_err());
+
+        let overflow = format!("*1\r\n${}\r\n", usize::MAX);
+        assert!(read_command(&mut overflow.as_bytes()).is_err());
+
+        assert_eq!(
+            read_command(&mut "*1\r\n$4\r\nPING\r\n".as_bytes()).unwrap(),
+            Some(vec!["PING".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_resp_matches_pattern()
+    {
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("user:*", "user:1"));
+        assert!(matches_pattern("*:name", "user:name"));
+        assert!(matches_pattern("user:*:name", "user:1:name"));
+        assert!(!matches_pattern("user:*", "session:1"));
+        assert!(!matches_pattern("user", "user:1"));
+    }
+}
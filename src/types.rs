@@ -1,11 +1,129 @@
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+#[cfg(not(feature = "ahash"))]
 use rustc_hash::{FxHashMap, FxHashSet};
 
 // Type aliases for the Hashing. This is to make it easier to change the hashing algorithm in the future
 // as we don't need cryptographic security in our offline db.
 
 // For HashMap
+#[cfg(not(feature = "ahash"))]
 pub type HashMap<K, V> = FxHashMap<K, V>;
 
+// The `ahash` feature swaps in aHash, still a plain std `HashMap` so every call site works unchanged.
+#[cfg(feature = "ahash")]
+pub type HashMap<K, V> = std::collections::HashMap<K, V, ahash::RandomState>;
+
 // For HashSet
+#[cfg(not(feature = "ahash"))]
 #[allow(dead_code)]
 pub type HashSet<V> = FxHashSet<V>;
+
+#[cfg(feature = "ahash")]
+#[allow(dead_code)]
+pub type HashSet<V> = std::collections::HashSet<V, ahash::RandomState>;
+
+/// The state of a key as seen by `get_status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyStatus<T>
+{
+    /// The key exists and has not expired.
+    Present(T),
+    /// The key existed but its time-to-live has passed.
+    Expired,
+    /// The key was never set, or it has already been removed.
+    Absent,
+}
+
+/// When a key was written and when it expires, as returned by `metadata`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryMeta
+{
+    /// When the key was first stored. Overwriting the key doesn't change it.
+    ///
+    /// Entries written by versions that didn't track timestamps report the Unix epoch.
+    pub created_at: DateTime<Utc>,
+    /// When the key's value was last written.
+    pub updated_at: DateTime<Utc>,
+    /// When the key expires, if it has a ttl.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Anything that can be used as a key, like `&str`, `String`, `Cow<str>` or a path.
+///
+/// Keys are always stored as `String`. Paths and OS strings that aren't valid UTF-8 are
+/// converted lossily, invalid sequences become `U+FFFD`, so two such paths can map to the same key.
+pub trait AsKey
+{
+    /// The key as a string, borrowed whenever possible.
+    fn as_key(&self) -> Cow<'_, str>;
+}
+
+impl AsKey for str
+{
+    fn as_key(&self) -> Cow<'_, str>
+    {
+        Cow::Borrowed(self)
+    }
+}
+
+impl AsKey for String
+{
+    fn as_key(&self) -> Cow<'_, str>
+    {
+        Cow::Borrowed(self)
+    }
+}
+
+impl AsKey for Cow<'_, str>
+{
+    fn as_key(&self) -> Cow<'_, str>
+    {
+        Cow::Borrowed(self)
+    }
+}
+
+impl AsKey for OsStr
+{
+    fn as_key(&self) -> Cow<'_, str>
+    {
+        self.to_string_lossy()
+    }
+}
+
+impl AsKey for OsString
+{
+    fn as_key(&self) -> Cow<'_, str>
+    {
+        self.to_string_lossy()
+    }
+}
+
+impl AsKey for Path
+{
+    fn as_key(&self) -> Cow<'_, str>
+    {
+        self.to_string_lossy()
+    }
+}
+
+impl AsKey for PathBuf
+{
+    fn as_key(&self) -> Cow<'_, str>
+    {
+        self.to_string_lossy()
+    }
+}
+
+impl<K> AsKey for &K
+where
+    K: AsKey + ?Sized,
+{
+    fn as_key(&self) -> Cow<'_, str>
+    {
+        (**self).as_key()
+    }
+}